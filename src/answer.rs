@@ -0,0 +1,476 @@
+/// Answer lines: what QBReader's answer keys accept, prompt on and reject
+///
+/// A key like `<b><u>Washington</u></b> Redskins [accept either underlined part; prompt on
+/// football team by asking "Which one?"; do not accept or prompt on Commanders]` is split
+/// into the main answer and its clauses. The main answer (and anything after `accept` or
+/// `or`) is accepted, `prompt on` answers get a prompt, and `do not accept` answers are
+/// wrong however close they are to the rest.
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::normalize::normalize;
+
+/// Underlined (or, failing that, bold) text, which is the part of an answer that's required
+static UNDERLINED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<u>(.+?)</u>").expect("Failed to compile regex"));
+static BOLD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<b>(.+?)</b>").expect("Failed to compile regex"));
+static TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"</?\w+>").expect("Failed to compile regex"));
+/// `by asking "In what setting?"`, at the end of a prompt
+static PROMPT_TEXT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\s*,?\s+by asking\s+(.+)$").expect("Failed to compile regex")
+});
+/// `before "oscillations"` or `until it is read`, at the end of a clause
+static CONDITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)\s+(before|until|after)\s+(["“'‘].*|.*\b(?:read|mention(?:ed)?|said)\b.*)$"#)
+        .expect("Failed to compile regex")
+});
+
+/// Parenthesized bits of an answer, which can be left out (e.g. "Mao (Zedong)")
+static OPTIONAL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\([^)]*\)").expect("Failed to compile regex"));
+
+const QUOTES: &[char] = &['"', '“', '”', '\'', '‘', '’'];
+
+/// One acceptable (or promptable, or wrong) answer
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Answer {
+    /// The whole answer, as plain text
+    pub text: String,
+    /// The underlined parts, which are enough on their own
+    pub required: Vec<String>,
+    /// When it applies, e.g. `before "oscillations"` (always, if there's no condition)
+    pub condition: Option<Condition>,
+}
+
+/// When an answer counts, going by how much of the question had been read at the buzz
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Until these words are read (or the answer itself, if there aren't any)
+    Before(Option<String>),
+    /// Once these words have been read
+    After(Option<String>),
+}
+
+impl Condition {
+    /// `before`, `until` or `after`, and what comes after it, e.g. `"Bourbon" is read`
+    fn parse(when: &str, what: &str) -> Self {
+        let mut what = plain(what);
+        for ending in [
+            " is read",
+            " are read",
+            " is mentioned",
+            " are mentioned",
+            " is said",
+            " read",
+            " mentioned",
+        ] {
+            if let Some(rest) = strip_suffix_ignore_case(&what, ending) {
+                what = rest.to_string();
+                break;
+            }
+        }
+        let what = what.trim().trim_matches(QUOTES).trim();
+        let what = match what.to_lowercase().as_str() {
+            "" | "it" | "they" | "them" | "mention" | "read" => None,
+            _ => Some(
+                strip_prefix_ignore_case(what, "the words ")
+                    .or(strip_prefix_ignore_case(what, "the word "))
+                    .unwrap_or(what)
+                    .trim_matches(QUOTES)
+                    .to_string(),
+            ),
+        };
+        if when.eq_ignore_ascii_case("after") {
+            Condition::After(what)
+        } else {
+            Condition::Before(what)
+        }
+    }
+
+    /// Whether it holds for `answer`, with `read` being the question as far as it was read
+    pub fn holds(&self, answer: &str, read: &str) -> bool {
+        let (Condition::Before(words) | Condition::After(words)) = self;
+        let target = normalize(&OPTIONAL.replace_all(words.as_deref().unwrap_or(answer), ""));
+        let said = contains_words(&normalize(read), &target);
+        match self {
+            Condition::Before(_) => !said,
+            Condition::After(_) => said,
+        }
+    }
+}
+
+/// `text` without an (ASCII) `prefix`, whatever its case
+///
+/// Lowercasing can change how long non-ASCII text is, so this compares in place rather
+/// than cutting the original at offsets found in a lowercased copy.
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &text[prefix.len()..])
+}
+
+/// `text` without an (ASCII) `suffix`, whatever its case
+fn strip_suffix_ignore_case<'a>(text: &'a str, suffix: &str) -> Option<&'a str> {
+    let start = text.len().checked_sub(suffix.len())?;
+    let tail = text.get(start..)?;
+    tail.eq_ignore_ascii_case(suffix).then(|| &text[..start])
+}
+
+/// Whether `words` turn up in `text` (both normalized), allowing for plurals
+///
+/// Only whole words count, so "article" being read isn't "art".
+fn contains_words(text: &str, words: &str) -> bool {
+    let text: Vec<&str> = text.split_whitespace().collect();
+    let words: Vec<&str> = words.split_whitespace().collect();
+    let same = |read: &str, word: &str| {
+        read.strip_prefix(word)
+            .is_some_and(|suffix| matches!(suffix, "" | "s" | "es"))
+    };
+    !words.is_empty()
+        && text.windows(words.len()).any(|window| {
+            window
+                .iter()
+                .zip(&words)
+                .all(|(read, word)| same(read, word))
+        })
+}
+
+impl Answer {
+    /// Whether the answer counts, given how much of the question was read
+    pub fn applies(&self, read: &str) -> bool {
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| condition.holds(&self.text, read))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    pub answer: Answer,
+    /// What to ask, e.g. "In what setting?"
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnswerLine {
+    /// The main answer first, then everything else that's accepted
+    pub accept: Vec<Answer>,
+    pub prompt: Vec<Prompt>,
+    pub reject: Vec<Answer>,
+}
+
+/// What the answer line says about a response, when it says anything
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Accept,
+    Prompt(Option<String>),
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Directive {
+    Accept,
+    Prompt,
+    Reject,
+}
+
+/// How clauses start, longest first so `do not accept or prompt on` isn't read as `do not accept`
+const DIRECTIVES: &[(&str, Directive)] = &[
+    ("do not accept or prompt on", Directive::Reject),
+    ("do not accept or prompt", Directive::Reject),
+    ("don't accept or prompt on", Directive::Reject),
+    ("do not prompt or accept on", Directive::Reject),
+    ("do not prompt on", Directive::Reject),
+    ("do not accept", Directive::Reject),
+    ("don't accept", Directive::Reject),
+    ("reject", Directive::Reject),
+    // Anti-prompts are for answers that are right, just more specific than needed
+    ("anti-prompt on", Directive::Accept),
+    ("antiprompt on", Directive::Accept),
+    ("also accept", Directive::Accept),
+    ("accept", Directive::Accept),
+    ("prompt on", Directive::Prompt),
+    ("prompt", Directive::Prompt),
+    ("or", Directive::Accept),
+];
+
+/// Bits of clauses that aren't answers
+const NOT_ANSWERS: &[&str] = &[
+    "equivalents",
+    "obvious equivalents",
+    "clear equivalents",
+    "word forms",
+    "synonyms",
+];
+
+fn plain(html: &str) -> String {
+    let text = TAG.replace_all(html, "");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn answer(html: &str, condition: Option<&Condition>) -> Answer {
+    let html = html.trim().trim_matches(QUOTES).trim();
+    let without_bold = html.replace("<b>", "").replace("</b>", "");
+    let mut required: Vec<String> = UNDERLINED
+        .captures_iter(&without_bold)
+        .map(|capture| plain(&capture[1]))
+        .collect();
+    if required.is_empty() {
+        required = BOLD
+            .captures_iter(html)
+            .map(|capture| plain(&capture[1]))
+            .collect();
+    }
+    Answer {
+        text: plain(html).trim_matches(QUOTES).to_string(),
+        required,
+        condition: condition.cloned(),
+    }
+}
+
+/// Split `text` on any of `separators`, but not inside quotes or brackets
+fn split_top_level<'a>(text: &'a str, separators: &[&str]) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quoted = false;
+    let mut start = 0;
+    let mut index = 0;
+    while index < text.len() {
+        let rest = &text[index..];
+        let c = rest.chars().next().unwrap();
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '"' => quoted = !quoted,
+            '“' => quoted = true,
+            '”' => quoted = false,
+            _ => {}
+        }
+        if depth == 0 && !quoted {
+            if let Some(separator) = separators
+                .iter()
+                .find(|separator| starts_with_ignoring_case(rest, separator))
+            {
+                parts.push(&text[start..index]);
+                index += separator.len();
+                start = index;
+                continue;
+            }
+        }
+        index += c.len_utf8();
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// The main answer, and the text of every bracketed (or parenthesized) clause group
+fn split_groups(html: &str) -> (String, Vec<String>) {
+    let mut main = String::new();
+    let mut groups = Vec::new();
+    let mut group = String::new();
+    let mut depth = 0;
+    for c in html.chars() {
+        match c {
+            '[' | '(' => {
+                if depth > 0 {
+                    group.push(c);
+                }
+                depth += 1;
+            }
+            ']' | ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    groups.push(std::mem::take(&mut group));
+                } else {
+                    group.push(c);
+                }
+            }
+            _ if depth > 0 => group.push(c),
+            _ => main.push(c),
+        }
+    }
+    if !group.is_empty() {
+        groups.push(group);
+    }
+    (main, groups)
+}
+
+fn starts_with_ignoring_case(text: &str, start: &str) -> bool {
+    text.get(..start.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(start))
+}
+
+/// The directive a clause starts with, and the rest of it
+fn directive(clause: &str) -> Option<(Directive, &str)> {
+    DIRECTIVES.iter().find_map(|(start, directive)| {
+        if !starts_with_ignoring_case(clause, start) {
+            return None;
+        }
+        let rest = &clause[start.len()..];
+        // Whole words only: "order" doesn't start with "or"
+        if rest.starts_with(|c: char| c.is_alphanumeric()) {
+            return None;
+        }
+        Some((*directive, rest.trim_start_matches([':', ' '])))
+    })
+}
+
+/// The clauses in a group, split on semicolons (and on commas, when a new directive starts)
+///
+/// Some keys write `(accept SHOs, prompt on "harmonic oscillators")`, which is two clauses.
+fn clauses(group: &str) -> Vec<&str> {
+    let mut clauses = Vec::new();
+    for clause in split_top_level(group, &[";"]) {
+        let clause = clause.trim();
+        let mut start = 0;
+        let mut offset = 0;
+        for piece in split_top_level(clause, &[", "]) {
+            let new_directive = directive(piece.trim_start())
+                .is_some_and(|_| !starts_with_ignoring_case(piece.trim_start(), "or "));
+            if offset > 0 && new_directive {
+                clauses.push(clause[start..offset - 2].trim());
+                start = offset;
+            }
+            offset += piece.len() + 2;
+        }
+        clauses.push(clause[start..].trim());
+    }
+    clauses
+}
+
+impl AnswerLine {
+    /// Parse an answer line (the HTML version, so underlining can be found)
+    pub fn parse(html: &str) -> Self {
+        let (main, groups) = split_groups(html);
+        let mut line = AnswerLine::default();
+        let main = answer(&main, None);
+        let mut underlined = main.required.clone();
+        if !main.text.is_empty() {
+            line.accept.push(main);
+        }
+        for group in groups {
+            // A group without a directive at the start is a note (e.g. "(the) Beatles"), not clauses
+            let mut current = None;
+            for clause in clauses(&group) {
+                let (directive, rest) = match directive(clause) {
+                    Some((directive, rest)) => (directive, rest),
+                    None => match current {
+                        Some(directive) => (directive, clause),
+                        None => continue,
+                    },
+                };
+                current = Some(directive);
+                line.add_clause(directive, rest, &mut underlined);
+            }
+        }
+        line
+    }
+
+    /// Whether anything depends on how much of the question was read
+    pub fn is_conditional(&self) -> bool {
+        self.accept
+            .iter()
+            .chain(self.reject.iter())
+            .chain(self.prompt.iter().map(|prompt| &prompt.answer))
+            .any(|answer| answer.condition.is_some())
+    }
+
+    fn add_clause(&mut self, directive: Directive, clause: &str, underlined: &mut Vec<String>) {
+        let mut clause = clause.trim().to_string();
+        let mut prompt_text = None;
+        if let Some(capture) = PROMPT_TEXT.captures(&clause) {
+            let asking = plain(&capture[1]);
+            prompt_text = Some(if asking.starts_with(QUOTES) {
+                asking.trim_matches(QUOTES).to_string()
+            } else {
+                format!("PROMPT ({})", asking)
+            });
+            clause.truncate(capture.get(0).unwrap().start());
+        }
+        let mut condition = None;
+        if let Some(capture) = CONDITION.captures(&clause) {
+            condition = Some(Condition::parse(&capture[1], &capture[2]));
+            clause.truncate(capture.get(0).unwrap().start());
+        }
+        let lowercase = plain(&clause).to_lowercase();
+        // "accept either underlined part" lets each underlined part stand on its own
+        if directive == Directive::Accept && lowercase.contains("underlined part") {
+            for part in underlined.drain(..) {
+                self.accept.push(Answer {
+                    text: part,
+                    ..Default::default()
+                });
+            }
+            return;
+        }
+        for item in split_top_level(&clause, &[", ", " or "]) {
+            let answer = answer(item, condition.as_ref());
+            if answer.text.is_empty() || NOT_ANSWERS.contains(&answer.text.to_lowercase().as_str())
+            {
+                continue;
+            }
+            match directive {
+                Directive::Accept => self.accept.push(answer),
+                Directive::Reject => self.reject.push(answer),
+                Directive::Prompt => self.prompt.push(Prompt {
+                    answer,
+                    text: prompt_text.clone(),
+                }),
+            }
+        }
+    }
+
+    /// Accept, prompt or reject `response` if the answer line says to
+    ///
+    /// Only the clearest cases are decided here: the response has to be one of the answers
+    /// (or its underlined parts) word for word, once both are normalized (so case, accents,
+    /// punctuation and how numbers are written don't matter). Answers with a condition
+    /// (`before "Bourbon" is read`) only count if it held when the player buzzed, with
+    /// `read` being the question up to then.
+    pub fn judge(&self, response: &str, read: &str) -> Option<Verdict> {
+        let response = normalize(response);
+        if response.is_empty() {
+            return None;
+        }
+        let exact = |answer: &Answer| {
+            normalize(&answer.text) == response
+                || normalize(&OPTIONAL.replace_all(&answer.text, "")) == response
+        };
+        let required = |answer: &Answer| covers_required(answer, &response);
+        // Exact matches win over matches on the underlined parts
+        for matches in [&exact as &dyn Fn(&Answer) -> bool, &required] {
+            let applies = |answer: &Answer| answer.applies(read) && matches(answer);
+            if self.reject.iter().any(applies) {
+                return Some(Verdict::Reject);
+            }
+            if self.accept.iter().any(applies) {
+                return Some(Verdict::Accept);
+            }
+            if let Some(prompt) = self.prompt.iter().find(|prompt| applies(&prompt.answer)) {
+                return Some(Verdict::Prompt(prompt.text.clone()));
+            }
+        }
+        None
+    }
+}
+
+/// Whether a (normalized) response has every underlined part, and nothing not in the answer
+fn covers_required(answer: &Answer, response: &str) -> bool {
+    if answer.required.is_empty() {
+        return false;
+    }
+    let text = normalize(&answer.text);
+    let words: Vec<&str> = text.split(' ').collect();
+    answer
+        .required
+        .iter()
+        .all(|part| response.contains(&normalize(part)))
+        && response.split(' ').all(|word| {
+            words
+                .iter()
+                .any(|answer_word| answer_word.starts_with(word) || word.starts_with(answer_word))
+        })
+}
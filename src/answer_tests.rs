@@ -0,0 +1,234 @@
+#[cfg(test)]
+mod tests {
+    use crate::answer::*;
+
+    fn texts(answers: &[Answer]) -> Vec<&str> {
+        answers.iter().map(|answer| answer.text.as_str()).collect()
+    }
+
+    const ZIDANE: &str = r#"Zinedine <b><u>Zidane headbutt</u></b>ing Marco Materazzi in the 2006 FIFA World Cup Final [or: Zinedine <b><u>Zidane's ejection</u></b>, obvious equivalents; prompt on: "<b><u>2006</u></b> FIFA <b><u>World Cup Final</u></b>", "<b><u>headbutt</u></b>"]"#;
+    const PLASMA: &str = r#"<b><u>wave</u></b>s in <b><u>plasma</u></b>s [accept plasma waves; accept <b><u>oscillations</u></b> in <b><u>plasma</u></b>s or <b><u>plasma oscillation</u></b>s before “oscillations”; accept plasma modes; prompt on waves or oscillations or modes or Alfvén waves or Langmuir waves by asking "In what setting?"]"#;
+    const JOKES: &str = r#"jokes [or jests; accept musical joke; prompt on scherzos or scherzi or Spass by asking for the English translation; prompt on divertimento until "divertimento"]"#;
+
+    #[test]
+    fn parses_clauses() {
+        let line = AnswerLine::parse(JOKES);
+        assert_eq!(texts(&line.accept), vec!["jokes", "jests", "musical joke"]);
+        assert!(line.reject.is_empty());
+        let prompts: Vec<_> = line
+            .prompt
+            .iter()
+            .map(|prompt| (prompt.answer.text.as_str(), prompt.text.as_deref()))
+            .collect();
+        assert_eq!(
+            prompts,
+            vec![
+                ("scherzos", Some("PROMPT (for the English translation)")),
+                ("scherzi", Some("PROMPT (for the English translation)")),
+                ("Spass", Some("PROMPT (for the English translation)")),
+                ("divertimento", None),
+            ]
+        );
+        assert_eq!(
+            line.prompt[3].answer.condition,
+            Some(Condition::Before(Some("divertimento".into())))
+        );
+    }
+
+    #[test]
+    fn parses_underlining() {
+        let line = AnswerLine::parse(ZIDANE);
+        assert_eq!(line.accept[0].required, vec!["Zidane headbutt"]);
+        assert_eq!(
+            texts(&line.accept),
+            vec![
+                "Zinedine Zidane headbutting Marco Materazzi in the 2006 FIFA World Cup Final",
+                "Zinedine Zidane's ejection",
+            ]
+        );
+        assert_eq!(line.prompt[0].answer.text, "2006 FIFA World Cup Final");
+        assert_eq!(
+            line.prompt[0].answer.required,
+            vec!["2006", "World Cup Final"]
+        );
+
+        let line = AnswerLine::parse(
+            "<b><u>Washington</u></b> <b><u>Redskins</u></b> [accept either underlined part]",
+        );
+        assert_eq!(
+            texts(&line.accept),
+            vec!["Washington Redskins", "Washington", "Redskins"]
+        );
+    }
+
+    #[test]
+    fn parses_other_shapes() {
+        let line = AnswerLine::parse(
+            r#"simple harmonic oscillators (accept SHOs, prompt on "harmonic oscillators")"#,
+        );
+        assert_eq!(
+            texts(&line.accept),
+            vec!["simple harmonic oscillators", "SHOs"]
+        );
+        assert_eq!(line.prompt[0].answer.text, "harmonic oscillators");
+
+        let line = AnswerLine::parse(
+            r#"Henry <b><u>Ford</u></b> [do not accept or prompt on "Gerald Ford" or "Ford Motor Company"]"#,
+        );
+        assert_eq!(
+            texts(&line.reject),
+            vec!["Gerald Ford", "Ford Motor Company"]
+        );
+
+        let line = AnswerLine::parse("index of <b>refraction</b> [or n until it is read]");
+        assert_eq!(line.accept[0].required, vec!["refraction"]);
+        assert_eq!(line.accept[1].condition, Some(Condition::Before(None)));
+
+        // Notes in parentheses aren't clauses
+        let line = AnswerLine::parse("(The) <b><u>Beatles</u></b> (accept the Fab Four)");
+        assert_eq!(texts(&line.accept), vec!["Beatles", "the Fab Four"]);
+
+        let line = AnswerLine::parse("Mona Lisa [anti-prompt on La Gioconda]");
+        assert_eq!(texts(&line.accept), vec!["Mona Lisa", "La Gioconda"]);
+    }
+
+    #[test]
+    fn judges() {
+        let zidane = AnswerLine::parse(ZIDANE);
+        let plasma = AnswerLine::parse(PLASMA);
+        let jokes = AnswerLine::parse(JOKES);
+        let ford = AnswerLine::parse(
+            r#"Henry <b><u>Ford</u></b> [do not accept or prompt on "Gerald Ford" or "Ford Motor Company"]"#,
+        );
+        let cases = [
+            (&zidane, "Zidane headbutt", Some(Verdict::Accept)),
+            (&zidane, "zidane's ejection", Some(Verdict::Accept)),
+            (
+                &zidane,
+                "Zidane headbutting Materazzi",
+                Some(Verdict::Accept),
+            ),
+            (&zidane, "Headbutt", Some(Verdict::Prompt(None))),
+            (&zidane, "World Cup Final", None),
+            (&plasma, "plasma waves", Some(Verdict::Accept)),
+            (&plasma, "Waves in plasma", Some(Verdict::Accept)),
+            (
+                &plasma,
+                "waves",
+                Some(Verdict::Prompt(Some("In what setting?".into()))),
+            ),
+            // Nothing's been read yet, so this is before "oscillations"
+            (&plasma, "plasma oscillations", Some(Verdict::Accept)),
+            (&plasma, "Radiation", None),
+            (&jokes, "Jokes!", Some(Verdict::Accept)),
+            (&jokes, "divertimento", Some(Verdict::Prompt(None))),
+            (&ford, "Henry Ford", Some(Verdict::Accept)),
+            (&ford, "ford", Some(Verdict::Accept)),
+            // Close to the answer, but explicitly wrong
+            (&ford, "Ford Motor Company", Some(Verdict::Reject)),
+            (&ford, "gerald ford", Some(Verdict::Reject)),
+            (&ford, "", None),
+        ];
+        for (line, response, expected) in cases {
+            assert_eq!(line.judge(response, ""), expected, "{response}");
+        }
+        let mao = AnswerLine::parse("<b><u>Mao</u></b> Zedong [accept Mao Tse-tung (Mao Zedong)]");
+        assert_eq!(mao.judge("Mao Tse-tung", ""), Some(Verdict::Accept));
+    }
+
+    #[test]
+    fn parses_conditions() {
+        let condition = |key: &str| AnswerLine::parse(key).prompt[0].answer.condition.clone();
+        assert_eq!(
+            condition("Louis XVI [prompt on Louis before 'Bourbon' is read]"),
+            Some(Condition::Before(Some("Bourbon".into())))
+        );
+        assert_eq!(
+            condition(r#"Napoleon [prompt on Bonaparte after “emperor” is mentioned]"#),
+            Some(Condition::After(Some("emperor".into())))
+        );
+        assert_eq!(
+            condition("Ur [prompt on Sumer until the word Sumerian is read]"),
+            Some(Condition::Before(Some("Sumerian".into())))
+        );
+        // Lowercasing "İ" makes it longer, which mustn't throw off where the cut goes
+        assert_eq!(
+            condition("Nicaea [prompt on council before the word İİznik IS READ]"),
+            Some(Condition::Before(Some("İİznik".into())))
+        );
+        assert_eq!(
+            condition("Sèvres [prompt on porcelain before The Word Sèvres is read]"),
+            Some(Condition::Before(Some("Sèvres".into())))
+        );
+        assert_eq!(
+            condition("Jupiter [prompt on gas giant before mention]"),
+            Some(Condition::Before(None))
+        );
+        // Not a condition, just an answer with "before" in it
+        let line = AnswerLine::parse("Before Sunrise [prompt on Before trilogy]");
+        assert_eq!(line.prompt[0].answer.text, "Before trilogy");
+        assert!(!line.is_conditional());
+    }
+
+    #[test]
+    fn judges_by_buzz_position() {
+        let louis = AnswerLine::parse(
+            "Louis <b><u>XVI</u></b> [prompt on Louis before 'Bourbon' is read; accept Louis Capet after \"Capet\" is mentioned]",
+        );
+        let early = "This king was executed";
+        let late = "This Bourbon king was executed";
+        assert_eq!(louis.judge("Louis", early), Some(Verdict::Prompt(None)));
+        assert_eq!(louis.judge("Louis", late), None);
+        assert_eq!(louis.judge("Louis Capet", late), None);
+        assert_eq!(
+            louis.judge("Louis Capet", "Known as Citizen Capet, this king"),
+            Some(Verdict::Accept)
+        );
+
+        let plasma = AnswerLine::parse(PLASMA);
+        let read = "Particles exhibit these oscillations in this setting";
+        assert_eq!(plasma.judge("plasma oscillations", read), None);
+        assert_eq!(
+            plasma.judge("plasma oscillations", "Particles"),
+            Some(Verdict::Accept)
+        );
+
+        // "until it is read" is about the answer itself
+        let refraction = AnswerLine::parse("index of <b>refraction</b> [or n until it is read]");
+        assert_eq!(
+            refraction.judge("n", "Snell's law relates"),
+            Some(Verdict::Accept)
+        );
+        assert_eq!(refraction.judge("n", "Snell's law relates n"), None);
+        // Only the whole word counts, not words that start with it
+        assert_eq!(
+            refraction.judge("n", "Newton noted this quantity"),
+            Some(Verdict::Accept)
+        );
+        let art = Condition::Before(Some("art".to_string()));
+        assert!(art.holds("", "This article"));
+        assert!(!art.holds("", "These arts"));
+        assert!(!art.holds("", "This art"));
+
+        let jokes = AnswerLine::parse(JOKES);
+        assert_eq!(
+            jokes.judge("divertimento", "The K. 522 \"Divertimento\""),
+            None
+        );
+    }
+
+    #[test]
+    fn judges_normalized() {
+        let henry = AnswerLine::parse("Henry <b><u>VIII</u></b> [or Henry Tudor]");
+        assert_eq!(henry.judge("Henry the Eighth", ""), Some(Verdict::Accept));
+        assert_eq!(henry.judge("henry 8", ""), Some(Verdict::Accept));
+        assert_eq!(henry.judge("Henry VII", ""), None);
+        let war = AnswerLine::parse("War of <b><u>1812</u></b>");
+        assert_eq!(
+            war.judge("the War of Eighteen Twelve", ""),
+            Some(Verdict::Accept)
+        );
+        let zola = AnswerLine::parse("<b><u>Émile Zola</u></b>");
+        assert_eq!(zola.judge("emile zola", ""), Some(Verdict::Accept));
+    }
+}
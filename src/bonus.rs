@@ -0,0 +1,294 @@
+use std::collections::HashSet;
+
+use ::serenity::all::{Mentionable, Message, UserId};
+use poise::serenity_prelude as serenity;
+use tokio::time::Duration;
+use tracing::{debug, error, info, warn};
+
+use crate::check::{Pipeline, Response};
+use crate::matches::MatchTeam;
+use crate::qb::Bonus;
+use crate::utils::*;
+use crate::{Context, Error};
+
+/// How long the channel gets to answer each part (conferring included)
+const PART_TIMEOUT: Duration = Duration::from_secs(20);
+/// Same as the prompt timeout for tossups
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The other team has already heard the part, so they get less time
+const BOUNCE_BACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a single part went
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PartResult {
+    Correct,
+    /// The other team got it on a bounce-back
+    BouncedBack,
+    Missed,
+}
+
+/// Everything that happened on a single bonus
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BonusReport {
+    /// (value, result) for each part that was read
+    pub parts: Vec<(u32, PartResult)>,
+}
+
+impl BonusReport {
+    fn points_for(&self, wanted: PartResult) -> u32 {
+        self.parts
+            .iter()
+            .filter(|(_, result)| *result == wanted)
+            .map(|(value, _)| value)
+            .sum()
+    }
+
+    /// Points for the team (or channel) the bonus was read to
+    pub fn points(&self) -> u32 {
+        self.points_for(PartResult::Correct)
+    }
+
+    pub fn bounce_back_points(&self) -> u32 {
+        self.points_for(PartResult::BouncedBack)
+    }
+
+    /// How much the parts that were read were worth
+    pub fn total(&self) -> u32 {
+        self.parts.iter().map(|(value, _)| value).sum()
+    }
+}
+
+/// What came of waiting for an answer
+enum Wait {
+    Answer(Box<Message>),
+    TimedOut,
+    Stopped,
+}
+
+/// How a team's attempt at a part went
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Attempt {
+    Correct,
+    /// Judged wrong
+    Incorrect,
+    /// Nobody answered in time, or the judge couldn't be reached (which we've already said)
+    Unjudged,
+    /// Someone used `/stop`
+    Stopped,
+}
+
+/// Resolves once the session in this channel is stopped
+async fn stopped(ctx: &Context<'_>) {
+    let data = ctx.data();
+    loop {
+        // Listen before checking, so a `/stop` in between isn't missed
+        let notified = data.session_stopped.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if !data.sessions.lock().await.contains(&ctx.channel_id()) {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Wait for the next (non-bot) message in the channel, optionally only from certain people
+async fn next_answer(ctx: &Context<'_>, from: Option<&HashSet<UserId>>, limit: Duration) -> Wait {
+    let from = from.cloned();
+    let collector = serenity::MessageCollector::new(ctx.serenity_context())
+        .channel_id(ctx.channel_id())
+        .filter(move |message| {
+            !message.author.bot
+                && from
+                    .as_ref()
+                    .is_none_or(|from| from.contains(&message.author.id))
+        })
+        .timeout(limit)
+        .next();
+    tokio::select! {
+        message = collector => match message {
+            Some(message) => Wait::Answer(Box::new(message)),
+            None => Wait::TimedOut,
+        },
+        _ = stopped(ctx) => Wait::Stopped,
+    }
+}
+
+/// Wait for an answer and judge it, following up on prompts once
+async fn answer_part(
+    ctx: &Context<'_>,
+    judges: &Pipeline,
+    question_so_far: &str,
+    answer_key: &(String, String),
+    from: Option<&HashSet<UserId>>,
+    limit: Duration,
+) -> Result<Attempt, Error> {
+    let data = ctx.data();
+    let mut from = from.cloned();
+    let mut limit = limit;
+    let mut prompted = false;
+    loop {
+        let message = match next_answer(ctx, from.as_ref(), limit).await {
+            Wait::Answer(message) => message,
+            Wait::TimedOut => {
+                ctx.channel_id().say(&ctx.http(), "Time's up!").await?;
+                return Ok(Attempt::Unjudged);
+            }
+            Wait::Stopped => return Ok(Attempt::Stopped),
+        };
+        message.reply(&ctx.http(), "Judging...").await?;
+        let response = judges
+            .judge(
+                data.llm.as_ref(),
+                &data.reqwest,
+                question_so_far,
+                &message.content,
+                answer_key,
+                prompted,
+            )
+            .await;
+        match response {
+            Ok(Response::Correct(_)) => return Ok(Attempt::Correct),
+            Ok(Response::Prompt(prompt, _)) if !prompted => {
+                ctx.channel_id()
+                    .say(
+                        &ctx.http(),
+                        format!("{} {}", prompt, message.author.mention()),
+                    )
+                    .await?;
+                // Only whoever was prompted gets to answer it
+                from = Some(HashSet::from([message.author.id]));
+                limit = PROMPT_TIMEOUT;
+                prompted = true;
+            }
+            Ok(Response::Incorrect(..) | Response::Prompt(..)) => return Ok(Attempt::Incorrect),
+            // Like with tossups, the part's a miss and the bonus goes on
+            Err(err) => {
+                error!("Failed to judge answer: {}", err);
+                ctx.channel_id()
+                    .say(&ctx.http(), "Couldn't reach the judge!")
+                    .await?;
+                return Ok(Attempt::Unjudged);
+            }
+        }
+    }
+}
+
+/// Read a bonus part by part
+///
+/// Unlike tossups, there's no buzzing: the first message after a part is
+/// read is taken as the answer for that part. In a match, only the
+/// `controlling` team can answer, and the `bounce_back` team (if any) gets
+/// a shot at whatever they miss.
+pub async fn read_bonus(
+    ctx: &Context<'_>,
+    bonus: &Bonus,
+    controlling: Option<&MatchTeam>,
+    bounce_back: Option<&MatchTeam>,
+) -> Result<BonusReport, Error> {
+    let channel = ctx.channel_id();
+    let parts = bonus.parts_sanitized.len();
+    let complete = bonus.answers.len() == parts && bonus.answers_sanitized.len() == parts;
+    let leadin = format_question(&bonus.leadin_sanitized);
+    let opening = if complete {
+        leadin.clone()
+    } else {
+        warn!("Bonus {} doesn't have an answer for every part", bonus.id);
+        "⚠️ This bonus doesn't have an answer for every part, so it's been skipped".to_string()
+    };
+    // In a match, the tossup's already been read, so we can't reply to the command
+    if controlling.is_some() {
+        channel.say(&ctx.http(), opening).await?;
+    } else {
+        ctx.say(opening).await?;
+    }
+    if !complete {
+        return Ok(BonusReport::default());
+    }
+
+    let judges = crate::judge_pipeline(*ctx).await;
+    let mut report = BonusReport::default();
+    let answers = bonus.answers.iter().zip(&bonus.answers_sanitized);
+    for (index, (part, (answer, answer_sanitized))) in
+        bonus.parts_sanitized.iter().zip(answers).enumerate()
+    {
+        if !ctx.data().sessions.lock().await.contains(&channel) {
+            debug!("Bonus stopped before part {}", index + 1);
+            break;
+        }
+        let value = bonus.value(index);
+        let part = format_question(part);
+        channel
+            .say(&ctx.http(), format!("**[{}]** {}", value, part))
+            .await?;
+
+        let answer_key = (answer.clone(), answer_sanitized.clone());
+        let question_so_far = format!("{} {}", leadin, part);
+        let members = controlling.map(|team| &team.members);
+        let mut attempt = answer_part(
+            ctx,
+            &judges,
+            &question_so_far,
+            &answer_key,
+            members,
+            PART_TIMEOUT,
+        )
+        .await?;
+        let mut result = if attempt == Attempt::Correct {
+            PartResult::Correct
+        } else {
+            PartResult::Missed
+        };
+        if let (Attempt::Incorrect | Attempt::Unjudged, Some(other)) = (attempt, bounce_back) {
+            let bounce = format!("Bounce back to **{}**", other.name);
+            let bounce = if attempt == Attempt::Incorrect {
+                format!("incorrect! {}", bounce)
+            } else {
+                bounce
+            };
+            channel.say(&ctx.http(), bounce).await?;
+            let members = Some(&other.members);
+            attempt = answer_part(
+                ctx,
+                &judges,
+                &question_so_far,
+                &answer_key,
+                members,
+                BOUNCE_BACK_TIMEOUT,
+            )
+            .await?;
+            if attempt == Attempt::Correct {
+                result = PartResult::BouncedBack;
+            }
+        }
+        if attempt == Attempt::Stopped {
+            debug!("Bonus stopped during part {}", index + 1);
+            break;
+        }
+        info!("Bonus part {} judged: {:?}", index + 1, result);
+        report.parts.push((value, result));
+        // Time running out and judge errors have already been announced
+        match attempt {
+            Attempt::Correct => {
+                channel.say(&ctx.http(), "Correct").await?;
+            }
+            Attempt::Incorrect => {
+                channel.say(&ctx.http(), "incorrect!").await?;
+            }
+            Attempt::Unjudged | Attempt::Stopped => {}
+        }
+        // reveal correct answer
+        channel.say(&ctx.http(), render_html(answer)).await?;
+    }
+
+    let mut summary = format!(
+        "**{}/{}** points on this bonus",
+        report.points(),
+        report.total()
+    );
+    if report.bounce_back_points() > 0 {
+        summary.push_str(&format!(" ({} bounced back)", report.bounce_back_points()));
+    }
+    channel.say(&ctx.http(), summary).await?;
+    Ok(report)
+}
@@ -1,31 +1,59 @@
-use std::sync::LazyLock;
+/// Judging answers, one layer at a time
+///
+/// Each layer is an `AnswerJudge` that can decide (correct, incorrect or prompt) or
+/// abstain and leave it to the next one. Which layers run, and in what order, is a
+/// `Pipeline`, set with `JUDGE_PIPELINE` (e.g. `answer_line, levenshtein, llm`) and
+/// overridable per server with `/judges`.
+use std::sync::{Arc, LazyLock};
 
-use llm::{chat::ChatMessage, error::LLMError, LLMProvider};
-use rapidfuzz::distance::levenshtein;
+use llm::{chat::ChatMessage, LLMProvider};
 use serde::{Deserialize, Serialize};
+use serenity::async_trait;
 use tera::Tera;
 use tracing::{error, info};
+
+use crate::answer::{AnswerLine, Verdict};
+use crate::fuzzy::{fuzzy_match, similarity};
+use crate::normalize::normalize;
+
+/// What the judges decided, and which of them decided it
 #[derive(Debug, Clone, PartialEq)]
 pub enum Response {
+    Correct(&'static str),
+    Incorrect(String, &'static str),
+    Prompt(String, &'static str),
+}
+
+impl Response {
+    /// The name of the judge that decided
+    pub fn judge(&self) -> &'static str {
+        match self {
+            Response::Correct(judge)
+            | Response::Incorrect(_, judge)
+            | Response::Prompt(_, judge) => judge,
+        }
+    }
+}
+
+/// What a single judge thinks
+#[derive(Debug, Clone, PartialEq)]
+pub enum Judgement {
     Correct,
     Incorrect(String),
     Prompt(String),
+    /// Not sure, so it's up to the next judge
+    Abstain,
 }
 static PROMPT_RE: LazyLock<regex::Regex> = LazyLock::new(|| {
     regex::Regex::new(r"(?s)<think>.+</think>\s+").expect("Failed to compile regex")
 });
 static ANSWER_RE: LazyLock<regex::Regex> =
     LazyLock::new(|| regex::Regex::new(r"\s+(\(|\[).+$").expect("Failed to compile regex"));
-static EXTRACT_SUB: LazyLock<regex::Regex> =
-    LazyLock::new(|| regex::Regex::new(r"<\w>(.+?)</\w>").expect("Failed to compile regex"));
 static TEMPLATER: LazyLock<Tera> =
     LazyLock::new(|| Tera::new("templates/latest/*.jinja").expect("Failed to parse templates"));
-// Threshold for fuzzy matching
-// intentionally separate from its appearance in the other file
-const FUZZY_THRESHOLD: usize = 5;
 const COSINE_UPPER_THRESHOLD: f64 = 0.9;
 const COSINE_PROMPT_THRESHOLD: f64 = 0.8;
-fn cosine_similarity(a: &Vec<f32>, b: &Vec<f32>) -> f64 {
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
     let dot_product = a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
     let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
     let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
@@ -85,129 +113,169 @@ fn env_var_is_true(v: &str) -> bool {
     v.to_lowercase().as_bytes()[0] == b't' || v == "1"
 }
 
-const ENABLE_LEVENSHTEIN_DISTANCE: LazyLock<bool> = LazyLock::new(|| {
-    if let Ok(v) = std::env::var("ENABLE_LEVENSHTEIN_DISTANCE") {
-        env_var_is_true(&v)
-    } else {
-        true
+fn env_flag(name: &str, default: bool) -> bool {
+    std::env::var(name).map_or(default, |v| env_var_is_true(&v))
+}
+
+/// Everything a judge gets to look at
+pub struct Attempt<'a> {
+    pub llm: &'a dyn LLMProvider,
+    pub http: &'a reqwest::Client,
+    // TODO: maybe input the whole question with a mark of where we left off
+    pub question_so_far: &'a str,
+    /// What the player said
+    pub response: &'a str,
+    /// (answer, answer_sanitized)
+    pub answer_key: &'a (String, String),
+    pub line: &'a AnswerLine,
+    /// Whether this is the answer to a prompt
+    pub prompted: bool,
+}
+
+impl Attempt<'_> {
+    /// The main answer, without any of the bracketed directions
+    fn main_answer(&self) -> String {
+        ANSWER_RE.replace(&self.answer_key.1, "").into_owned()
     }
-});
-const ENABLE_EMBEDDING_DISTANCE: LazyLock<bool> = LazyLock::new(|| {
-    if let Ok(v) = std::env::var("ENABLE_EMBEDDING_DISTANCE") {
-        env_var_is_true(&v)
-    } else {
-        false
+}
+
+/// One layer of judging
+#[async_trait]
+pub trait AnswerJudge: Send + Sync {
+    /// What it's called in `JUDGE_PIPELINE` and `/judges`
+    fn name(&self) -> &'static str;
+    async fn judge(&self, attempt: &Attempt<'_>) -> Result<Judgement, String>;
+}
+
+/// Follows the answer line's accept, prompt and reject directions to the letter
+pub struct AnswerLineJudge;
+
+#[async_trait]
+impl AnswerJudge for AnswerLineJudge {
+    fn name(&self) -> &'static str {
+        "answer_line"
     }
-});
-const ENABLE_LLM_CHECKS: LazyLock<bool> = LazyLock::new(|| {
-    if let Ok(v) = std::env::var("ENABLE_LLM_CHECKS") {
-        env_var_is_true(&v)
-    } else {
-        true
+
+    async fn judge(&self, attempt: &Attempt<'_>) -> Result<Judgement, String> {
+        Ok(
+            match attempt
+                .line
+                .judge(attempt.response, attempt.question_so_far)
+            {
+                Some(Verdict::Accept) => Judgement::Correct,
+                Some(Verdict::Reject) => {
+                    Judgement::Incorrect("The answer line says not to accept that".into())
+                }
+                // Prompting on the same thing twice doesn't get anywhere
+                Some(Verdict::Prompt(_)) if attempt.prompted => {
+                    Judgement::Incorrect("That's still not specific enough".into())
+                }
+                Some(Verdict::Prompt(text)) => Judgement::Prompt(text.unwrap_or("PROMPT".into())),
+                None => Judgement::Abstain,
+            },
+        )
     }
-});
-#[allow(clippy::borrowed_box)]
-pub async fn check_correct_answer(
-    llm: &Box<dyn LLMProvider>,
-    http: &reqwest::Client,
-    // TODO: maybe input the whole question with a mark of where we left off
-    question_so_far: &str,
-    answer: &str,
-    // (answer, answer_sanitized)
-    answer_key: &(String, String),
-    // TODO: account for this in the prompt
-    prompted: bool,
-) -> Result<Response, String> {
-    if !*ENABLE_LLM_CHECKS && !*ENABLE_EMBEDDING_DISTANCE && !*ENABLE_LEVENSHTEIN_DISTANCE {
-        return Err("No checks enabled".into());
+}
+
+/// Accepts answers within a few typos of an accepted answer (or its underlined parts)
+///
+/// How many typos depends on how long the answer is, and words can come in any order.
+pub struct LevenshteinJudge;
+
+#[async_trait]
+impl AnswerJudge for LevenshteinJudge {
+    fn name(&self) -> &'static str {
+        "levenshtein"
     }
-    info!("Enabled answer checks:");
-    info!("LLM checks: {}", *ENABLE_LLM_CHECKS);
-    info!(
-        "Embedding distance checks: {}, thresholds: ({}, {})",
-        *ENABLE_EMBEDDING_DISTANCE, COSINE_UPPER_THRESHOLD, COSINE_PROMPT_THRESHOLD
-    );
-    info!(
-        "Levenshtein distance checks: {}, threshold: {}",
-        *ENABLE_LEVENSHTEIN_DISTANCE, FUZZY_THRESHOLD
-    );
-    // TODO: normalize digits
-    let mut context = tera::Context::new();
-    context.insert(
-        "question",
-        &if answer_key.1.contains("read")|| answer_key.1.contains("before") || answer_key.1.contains("mention") {
-            format!(
-                "Since deciding on whether to prompt or mark as incorrect depends on how far we've read, I will also provide the question. Here is the question read so far:
-```
-{}
-```",
-                question_so_far
-            )
-        } else {
-            "Remember, don't think about the question but simply compare the user's answer to the correct answer.".into()
-        },
-    );
-    context.insert("response", answer);
-    context.insert("answer", &answer_key.0);
-    // Basic levenshtein distance
-    let normalized_answer = ANSWER_RE.replace(&answer_key.1, "").into_owned();
-    info!("Checking answer for question: {}", question_so_far);
-    info!("Answer: {:?}", answer_key);
-    info!("Normalized Answer: {}", normalized_answer);
-    info!("User answer: {}", answer);
-    if *ENABLE_LEVENSHTEIN_DISTANCE {
-        let distance = levenshtein::distance(
-            normalized_answer.to_lowercase().chars(),
-            answer.to_lowercase().chars(),
-        );
-        if distance < FUZZY_THRESHOLD {
-            info!("Levenshtein distance is below threshold");
-            return Ok(Response::Correct);
-        };
-        info!("Initial distance: {} | too high", distance);
-        // Levenshtein distance on extracted subword
-        for (_, [sub_normalized_answer]) in EXTRACT_SUB
-            .captures_iter(&answer_key.0.replace("<b>", "").replace("</b>", ""))
-            .map(|capture| capture.extract())
-        {
-            // TODO: make sure this wasn't being surrounded by "prompt on"
-            // (use the LLM)
-            let distance = levenshtein::distance(
-                sub_normalized_answer.to_lowercase().chars(),
-                answer.to_lowercase().chars(),
-            );
-            if distance < FUZZY_THRESHOLD {
-                info!(
-                    "Checked sub answer {} and levenshtein distance is below threshold",
-                    sub_normalized_answer
-                );
-                return Ok(Response::Correct);
+
+    async fn judge(&self, attempt: &Attempt<'_>) -> Result<Judgement, String> {
+        let response = normalize(attempt.response);
+        // The main answer, then everything else that's accepted (and their underlined parts)
+        let main_answer = attempt.main_answer();
+        let accepted = attempt
+            .line
+            .accept
+            .iter()
+            .filter(|accepted| accepted.applies(attempt.question_so_far));
+        let candidates =
+            std::iter::once(&main_answer)
+                .chain(accepted.flat_map(|accepted| {
+                    std::iter::once(&accepted.text).chain(&accepted.required)
+                }));
+        for candidate in candidates {
+            let candidate = normalize(candidate);
+            if fuzzy_match(&candidate, &response) {
+                info!("Close enough to {}", candidate);
+                return Ok(Judgement::Correct);
             }
             info!(
-                "Checked sub answer {} and levenshtein distance {} is too high",
-                sub_normalized_answer, distance
+                "Not close enough to {} (similarity {:.2})",
+                candidate,
+                similarity(&candidate, &response)
             );
         }
+        Ok(Judgement::Abstain)
     }
-    if *ENABLE_EMBEDDING_DISTANCE {
+}
+
+/// Accepts (or prompts on) answers that mean nearly the same thing, using Ollama embeddings
+pub struct EmbeddingJudge;
+
+#[async_trait]
+impl AnswerJudge for EmbeddingJudge {
+    fn name(&self) -> &'static str {
+        "embedding"
+    }
+
+    async fn judge(&self, attempt: &Attempt<'_>) -> Result<Judgement, String> {
         let similarity = cosine_similarity(
-            &get_embedding(&http, &answer)
+            &get_embedding(attempt.http, attempt.response)
                 .await
                 .map_err(|e| format!("{:?}", e))?,
-            &get_embedding(&http, &normalized_answer)
+            &get_embedding(attempt.http, &attempt.main_answer())
                 .await
                 .map_err(|e| format!("{:?}", e))?,
         );
         if similarity >= COSINE_UPPER_THRESHOLD {
             info!("It's semantically similar enough");
-            return Ok(Response::Correct);
+            return Ok(Judgement::Correct);
         }
         if similarity >= COSINE_PROMPT_THRESHOLD {
-            return Ok(Response::Prompt("PROMPT".to_string()));
+            return Ok(Judgement::Prompt("PROMPT".to_string()));
         }
         info!("Similarity: {} | insufficient", similarity);
+        Ok(Judgement::Abstain)
+    }
+}
+
+/// Asks the LLM, which always decides one way or the other
+pub struct LlmJudge;
+
+#[async_trait]
+impl AnswerJudge for LlmJudge {
+    fn name(&self) -> &'static str {
+        "llm"
     }
-    if *ENABLE_LLM_CHECKS {
+
+    async fn judge(&self, attempt: &Attempt<'_>) -> Result<Judgement, String> {
+        let prompted = attempt.prompted;
+        let mut context = tera::Context::new();
+        context.insert(
+            "question",
+            &if attempt.line.is_conditional() {
+                format!(
+                    "Since deciding on whether to prompt or mark as incorrect depends on how far we've read, I will also provide the question. Here is the question read so far:
+```
+{}
+```",
+                    attempt.question_so_far
+                )
+            } else {
+                "Remember, don't think about the question but simply compare the user's answer to the correct answer.".into()
+            },
+        );
+        context.insert("response", attempt.response);
+        context.insert("answer", &attempt.answer_key.0);
         // if answer_key.0.contains("prompt") {
         let messages = vec![ChatMessage::user()
             .content(
@@ -224,7 +292,7 @@ pub async fn check_correct_answer(
             )
             .build()];
 
-        llm.chat(&messages)
+        attempt.llm.chat(&messages)
         .await
         .map(|response| response.text().expect("LLM did not respond"))
         .map(|text| {
@@ -238,18 +306,18 @@ pub async fn check_correct_answer(
 
 
             match response {
-                "CORRECT" => Response::Correct,
-                "INCORRECT" => Response::Incorrect(raw),
+                "CORRECT" => Judgement::Correct,
+                "INCORRECT" => Judgement::Incorrect(raw),
                 text => {
                     if prompted {
                         error!("Judge did not respond with 'INCORRECT' or 'CORRECT' to prompt, but instead: {}", text);
-                        Response::Incorrect(raw)
+                        Judgement::Incorrect(raw)
                     } else {
                         let trimmed_text = text.trim();
-                        if trimmed_text.len() == 0 {
-                            Response::Prompt("PROMPT".to_string())
+                        if trimmed_text.is_empty() {
+                            Judgement::Prompt("PROMPT".to_string())
                         } else {
-                            Response::Prompt(trimmed_text.to_string())
+                            Judgement::Prompt(trimmed_text.to_string())
                         }
                     }
                     // If the response starts with "PROMPT: ", we extract the prompt
@@ -264,9 +332,152 @@ pub async fn check_correct_answer(
             }
         })
         .map_err(|x| format!("{}", x))
-    } else {
+    }
+}
+
+/// The judges that come built in, with what they do (for `/judges`)
+pub const JUDGES: &[(&str, &str)] = &[
+    (
+        "answer_line",
+        "Follows the answer line's accept, prompt and reject directions",
+    ),
+    (
+        "levenshtein",
+        "Accepts answers within a few typos, in any word order",
+    ),
+    (
+        "embedding",
+        "Accepts answers that mean nearly the same thing (needs Ollama)",
+    ),
+    ("llm", "Asks the LLM, which always decides"),
+];
+
+/// A built-in judge, by name
+pub fn judge_named(name: &str) -> Option<Arc<dyn AnswerJudge>> {
+    Some(match name {
+        "answer_line" => Arc::new(AnswerLineJudge),
+        "levenshtein" => Arc::new(LevenshteinJudge),
+        "embedding" => Arc::new(EmbeddingJudge),
+        "llm" => Arc::new(LlmJudge),
+        _ => return None,
+    })
+}
+
+/// The judges to ask, in order, until one of them decides
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    judges: Vec<Arc<dyn AnswerJudge>>,
+}
+
+impl Pipeline {
+    /// A pipeline from a list of built-in judges, e.g. `answer_line, levenshtein, llm`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut pipeline = Pipeline::default();
+        for name in spec.split([',', ' ', '>']).filter(|name| !name.is_empty()) {
+            let name = name.to_lowercase();
+            let judge = judge_named(&name).ok_or_else(|| {
+                let names: Vec<_> = JUDGES
+                    .iter()
+                    .map(|(name, _)| format!("`{}`", name))
+                    .collect();
+                format!(
+                    "There's no judge called `{}` (try {})",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+            pipeline = pipeline.with(judge);
+        }
+        if pipeline.judges.is_empty() {
+            return Err("A pipeline needs at least one judge".into());
+        }
+        Ok(pipeline)
+    }
+
+    /// Ask `judge` after everything already in the pipeline
+    pub fn with(mut self, judge: Arc<dyn AnswerJudge>) -> Self {
+        self.judges.push(judge);
+        self
+    }
+
+    /// The judges' names, in order, the way `parse` takes them
+    pub fn spec(&self) -> String {
+        self.judges
+            .iter()
+            .map(|judge| judge.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub async fn judge(
+        &self,
+        llm: &dyn LLMProvider,
+        http: &reqwest::Client,
+        question_so_far: &str,
+        answer: &str,
+        // (answer, answer_sanitized)
+        answer_key: &(String, String),
+        // TODO: account for this in the prompt
+        prompted: bool,
+    ) -> Result<Response, String> {
+        if self.judges.is_empty() {
+            return Err("No checks enabled".into());
+        }
+        info!("Judging with: {}", self.spec());
+        info!("Checking answer for question: {}", question_so_far);
+        info!("Answer: {:?}", answer_key);
+        info!("User answer: {}", answer);
+        let line = AnswerLine::parse(&answer_key.0);
+        let attempt = Attempt {
+            llm,
+            http,
+            question_so_far,
+            response: answer,
+            answer_key,
+            line: &line,
+            prompted,
+        };
+        for judge in &self.judges {
+            let name = judge.name();
+            let response = match judge.judge(&attempt).await? {
+                Judgement::Correct => Response::Correct(name),
+                Judgement::Incorrect(reason) => Response::Incorrect(reason, name),
+                Judgement::Prompt(prompt) => Response::Prompt(prompt, name),
+                Judgement::Abstain => continue,
+            };
+            info!("Decided by {}: {:?}", name, response);
+            return Ok(response);
+        }
         Ok(Response::Incorrect(
-            "All other checks failed (LLM is disabled)".into(),
+            "None of the checks could decide".into(),
+            "pipeline",
         ))
     }
 }
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Pipeline({})", self.spec())
+    }
+}
+
+/// The pipeline for servers that haven't picked their own
+///
+/// `JUDGE_PIPELINE` if it's set, otherwise the layers switched on by the older
+/// `ENABLE_LEVENSHTEIN_DISTANCE`, `ENABLE_EMBEDDING_DISTANCE` and `ENABLE_LLM_CHECKS`.
+pub static DEFAULT_PIPELINE: LazyLock<Pipeline> = LazyLock::new(|| {
+    if let Ok(spec) = std::env::var("JUDGE_PIPELINE") {
+        return Pipeline::parse(&spec).expect("Invalid JUDGE_PIPELINE");
+    }
+    let mut names = vec!["answer_line"];
+    for (name, variable, default) in [
+        ("levenshtein", "ENABLE_LEVENSHTEIN_DISTANCE", true),
+        ("embedding", "ENABLE_EMBEDDING_DISTANCE", false),
+        ("llm", "ENABLE_LLM_CHECKS", true),
+    ] {
+        if env_flag(variable, default) {
+            names.push(name);
+        }
+    }
+    Pipeline::parse(&names.join(", ")).expect("Built-in judges are all valid")
+});
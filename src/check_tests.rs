@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, LazyLock};
+
+    use llm::LLMProvider;
+    use serenity::async_trait;
+
+    use crate::{check::*, utils::get_llm_no_healthcheck};
+    static HTTP: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+    // Never actually asked, since none of these pipelines end in `llm`
+    static LLM: LazyLock<Box<dyn LLMProvider>> = LazyLock::new(get_llm_no_healthcheck);
+
+    /// Always says the same thing
+    struct Fixed(&'static str, Judgement);
+
+    #[async_trait]
+    impl AnswerJudge for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        async fn judge(&self, _attempt: &Attempt<'_>) -> Result<Judgement, String> {
+            Ok(self.1.clone())
+        }
+    }
+
+    async fn judge(pipeline: &Pipeline, response: &str) -> Response {
+        let key = (
+            "<b><u>Henry Ford</u></b> [prompt on Ford; do not accept Gerald Ford]".to_string(),
+            "Henry Ford [prompt on Ford; do not accept Gerald Ford]".to_string(),
+        );
+        pipeline
+            .judge(
+                LLM.as_ref(),
+                &HTTP,
+                "This industrialist",
+                response,
+                &key,
+                false,
+            )
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_pipelines() {
+        assert_eq!(
+            Pipeline::parse("answer_line, levenshtein, llm")
+                .unwrap()
+                .spec(),
+            "answer_line, levenshtein, llm"
+        );
+        // Any order, and a few ways of writing the list
+        assert_eq!(
+            Pipeline::parse("LLM > answer_line").unwrap().spec(),
+            "llm, answer_line"
+        );
+        assert_eq!(
+            Pipeline::parse("levenshtein embedding").unwrap().spec(),
+            "levenshtein, embedding"
+        );
+        assert!(Pipeline::parse("answer_line, vibes")
+            .unwrap_err()
+            .contains("`vibes`"));
+        assert!(Pipeline::parse(" , ").is_err());
+        for (name, _) in JUDGES {
+            assert!(judge_named(name).is_some(), "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn first_decision_wins() {
+        let pipeline = Pipeline::parse("answer_line, levenshtein").unwrap();
+        assert_eq!(
+            judge(&pipeline, "henry ford").await,
+            Response::Correct("answer_line")
+        );
+        assert!(matches!(
+            judge(&pipeline, "Ford").await,
+            Response::Prompt(_, "answer_line")
+        ));
+        assert!(matches!(
+            judge(&pipeline, "Gerald Ford").await,
+            Response::Incorrect(_, "answer_line")
+        ));
+        // The answer line has nothing to say about typos
+        assert_eq!(
+            judge(&pipeline, "Henri Frod").await,
+            Response::Correct("levenshtein")
+        );
+        assert_eq!(
+            judge(&pipeline, "Edison").await,
+            Response::Incorrect("None of the checks could decide".into(), "pipeline")
+        );
+    }
+
+    #[tokio::test]
+    async fn order_and_custom_judges() {
+        let strict = Arc::new(Fixed("strict", Judgement::Incorrect("No".into())));
+        let lenient = Arc::new(Fixed("lenient", Judgement::Correct));
+        let abstains = Arc::new(Fixed("abstains", Judgement::Abstain));
+        let pipeline = Pipeline::default()
+            .with(abstains.clone())
+            .with(strict.clone())
+            .with(lenient.clone());
+        assert_eq!(pipeline.spec(), "abstains, strict, lenient");
+        assert_eq!(
+            judge(&pipeline, "Henry Ford").await,
+            Response::Incorrect("No".into(), "strict")
+        );
+        let pipeline = Pipeline::default().with(lenient).with(strict);
+        assert_eq!(judge(&pipeline, "Gerald Ford").await.judge(), "lenient");
+        let pipeline = Pipeline::parse("answer_line").unwrap().with(abstains);
+        assert_eq!(judge(&pipeline, "Gerald Ford").await.judge(), "answer_line");
+    }
+}
@@ -0,0 +1,78 @@
+/// Fuzzy matching for typos in answers
+///
+/// A fixed edit distance is too loose for short answers ("Ur" is two edits from "Uruk")
+/// and too strict for long ones, so how close is close enough scales with the answer's
+/// length. Answers are also compared word by word, so "Haydn, Franz Joseph" or an extra
+/// middle name still count. Everything here expects `normalize`d text.
+use rapidfuzz::distance::osa;
+
+/// How alike two answers have to be (1 minus the edits over the length of the longer one)
+pub const SIMILARITY_THRESHOLD: f64 = 0.8;
+/// Shorter than this, and it has to be spelled exactly ("Manet" isn't "Monet")
+pub const MIN_FUZZY_LENGTH: usize = 6;
+
+/// Words that don't change what an answer is
+const FILLER: &[&str] = &[
+    "the", "a", "an", "of", "and", "in", "on", "at", "to", "for", "by", "de", "la", "le", "von",
+    "van", "der", "da", "di", "du",
+];
+
+/// How alike two strings are, from 0 to 1, counting a swap of two letters as one edit
+pub fn similarity(a: &str, b: &str) -> f64 {
+    osa::normalized_similarity(a.chars(), b.chars())
+}
+
+/// Whether `response` could be `answer` with a typo or two
+///
+/// Typos rarely hit the first letter, so a different one ("Russia" for "Prussia") isn't
+/// treated as one.
+pub fn close(answer: &str, response: &str) -> bool {
+    if answer == response {
+        return true;
+    }
+    answer.chars().count() >= MIN_FUZZY_LENGTH
+        && answer.chars().next() == response.chars().next()
+        && similarity(answer, response) >= SIMILARITY_THRESHOLD
+}
+
+/// The same word, allowing for typos and plurals
+fn same_word(answer: &str, response: &str) -> bool {
+    let plural = |a: &str, b: &str| {
+        a.strip_prefix(b)
+            .is_some_and(|suffix| suffix == "s" || suffix == "es")
+    };
+    plural(answer, response) || plural(response, answer) || close(answer, response)
+}
+
+fn significant(text: &str) -> Vec<&str> {
+    text.split_whitespace()
+        .filter(|word| !FILLER.contains(word))
+        .collect()
+}
+
+/// Whether the response has the answer's words, in any order and with filler words ignored
+///
+/// Each word gets its own typo tolerance. Long answers (three or more words) can take one
+/// extra word, like a middle name, but short ones can't ("John Quincy Adams" isn't
+/// "John Adams").
+pub fn same_words(answer: &str, response: &str) -> bool {
+    let answer = significant(answer);
+    let response = significant(response);
+    if answer.is_empty() || response.is_empty() {
+        return false;
+    }
+    let mut unused = response.clone();
+    for word in &answer {
+        let Some(index) = unused.iter().position(|other| same_word(word, other)) else {
+            return false;
+        };
+        unused.remove(index);
+    }
+    let extra_allowed = if answer.len() >= 3 { 1 } else { 0 };
+    unused.len() <= extra_allowed
+}
+
+/// Whether the response matches the answer closely enough to accept without asking anyone
+pub fn fuzzy_match(answer: &str, response: &str) -> bool {
+    close(answer, response) || same_words(answer, response)
+}
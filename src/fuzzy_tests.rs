@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use crate::fuzzy::*;
+    use crate::normalize::normalize;
+
+    fn matches(answer: &str, response: &str) -> bool {
+        fuzzy_match(&normalize(answer), &normalize(response))
+    }
+
+    /// Answers the old check (an edit distance under 5) got wrong, and what they should be
+    const REGRESSIONS: &[(&str, &str, bool)] = &[
+        // False positives: short answers a couple of letters from something else
+        ("Ur", "Uruk", false),
+        ("Iran", "Iraq", false),
+        ("Mars", "Mark", false),
+        ("Io", "Ion", false),
+        ("Manet", "Monet", false),
+        ("Plato", "Pluto", false),
+        ("Niger", "Nigeria", false),
+        ("Austria", "Australia", false),
+        ("Prussia", "Russia", false),
+        ("Thomas Mann", "Thomas Paine", false),
+        ("Mendel", "Mendeleev", false),
+        ("John Adams", "John Quincy Adams", false),
+        ("Henry James", "William James", false),
+        // False negatives: long answers with the words moved around or one extra
+        ("Franz Joseph Haydn", "Haydn, Franz Joseph", true),
+        ("Treaty of Westphalia", "Westphalia Treaty", true),
+        (
+            "Gabriel Garcia Marquez",
+            "Gabriel José García Márquez",
+            true,
+        ),
+        ("Wolfgang Amadeus Mozart", "Wolfgang Amadeus Mozart's", true),
+        ("Pyotr Ilyich Tchaikovsky", "Tchaikovsky, Pyotr Ilich", true),
+        ("Wars of the Roses", "war of roses", true),
+        ("Battle of the Bulge", "the bulge battle", true),
+        ("Federalist Papers", "The Federalist Papers", true),
+        // Typos that still count
+        ("Mozart", "Mozrat", true),
+        ("Tchaikovsky", "Tchaikovski", true),
+        ("Schrodinger", "Shrodinger", true),
+        ("Ferdinand Magellan", "Ferdinand Magelan", true),
+        ("photosynthesis", "photosynthsis", true),
+        // And the obvious ones
+        ("Iran", "iran", true),
+        ("Ur", "UR", true),
+        ("Henry VIII", "Henry the Eighth", true),
+        ("Émile Zola", "Emile Zola", true),
+    ];
+
+    #[test]
+    fn regressions() {
+        let wrong: Vec<_> = REGRESSIONS
+            .iter()
+            .filter(|(answer, response, expected)| matches(answer, response) != *expected)
+            .collect();
+        assert!(wrong.is_empty(), "{wrong:#?}");
+    }
+
+    #[test]
+    fn tolerance_scales_with_length() {
+        // Too short for any typos
+        assert!(!close("monet", "manet"));
+        // One typo in six letters, two in ten
+        assert!(close("mozart", "mozert"));
+        assert!(!close("mozart", "mizert"));
+        assert!(close("copernicus", "copernikis"));
+        assert!(!close("copernicus", "coprenikis"));
+        // A swap is one typo, not two
+        assert!(close("newton", "nweton"));
+        assert_eq!(similarity("abc", "abc"), 1.0);
+    }
+
+    #[test]
+    fn word_sets() {
+        assert!(same_words("battle of hastings", "hastings battle"));
+        // Every word of the answer has to be there
+        assert!(!same_words("battle of hastings", "hastings"));
+        assert!(!same_words("john quincy adams", "john adams"));
+        // Only long answers get an extra word
+        assert!(same_words("john quincy adams", "john quincy adams sr"));
+        assert!(!same_words("john adams", "john quincy adams"));
+        assert!(!same_words("john quincy adams", "john quincy adams sr jr"));
+        // Filler alone isn't an answer
+        assert!(!same_words("of the", "of the"));
+        assert!(!same_words("", "anything"));
+    }
+}
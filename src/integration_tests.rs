@@ -86,10 +86,9 @@ mod tests {
 
     #[test]
     fn test_subtraction_edge_cases() {
-        // Test subtracting from empty set
+        // Biology is part of Science, so nothing is left
         let result = parse_query("Biology - Science");
-        // Biology is part of Science, so this should result in empty or minimal set
-        assert!(result.is_ok()); // Should parse successfully even if logically minimal
+        assert!(matches!(result, Err(QueryError::ImpossibleBranch(_))));
 
         // Test subtracting non-existent category
         let result = parse_query("Science - Literature").unwrap();
@@ -1,26 +1,56 @@
-use llm::builder::{LLMBackend, LLMBuilder};
 use llm::LLMProvider;
-use poise::{send_reply, serenity_prelude as serenity, CreateReply};
-use tracing::debug;
-
-use crate::check::healthcheck;
-use crate::qb::{random_tossup, Tossup};
-use crate::query::{parse_query, ApiQuery, QueryError, CATEGORIES};
-use crate::read::{event_handler, read_question};
-use crate::utils::get_llm;
+use poise::{send_reply, serenity_prelude as serenity, ChoiceParameter, CreateReply};
+use tracing::{debug, info, warn};
+
+use crate::bonus::read_bonus;
+use crate::check::{Pipeline, DEFAULT_PIPELINE, JUDGES};
+use crate::matches::{MatchScore, MatchTeam, DEFAULT_TOSSUPS, MAX_TOSSUPS};
+use crate::qb::{num_packets, packet_tossups, sample_bonuses, sample_tossups, set_list, Tossup};
+use crate::query::{
+    aliases_for, did_you_mean, is_saved_query_name, parse_query_with, ApiQuery, Expr, QueryError,
+    SavedQueries, ALIASES, CATEGORIES,
+};
+use crate::read::{
+    event_handler, read_question, session_summary, still_going_after, GameEvent, Games,
+    QuestionOutcome, QuestionReport,
+};
+use crate::score::{score_buzz, BuzzKind, Scores};
+use crate::simplify::normalize;
+use crate::storage::{render_standings, saved_query_table, QueryOwner, Ranking, Storage};
+use crate::teams::{Roster, Teams};
+use crate::utils::{format_question, get_llm, nth_chunk};
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::sync::Arc;
 
-use serenity::all::{ChannelId, UserId};
-use tokio::sync::{watch, Mutex};
+use serenity::all::{ChannelId, GuildId, UserId};
+use tokio::sync::{Mutex, Notify, OnceCell};
 
 // #[cfg(test)]
 // mod buzzing_test;
+mod answer;
+#[cfg(test)]
+mod answer_tests;
+mod bonus;
 mod check;
 #[cfg(test)]
+mod check_tests;
+mod fuzzy;
+#[cfg(test)]
+mod fuzzy_tests;
+#[cfg(test)]
 mod integration_tests;
 #[cfg(test)]
 mod judge_tests;
+mod matches;
+#[cfg(test)]
+mod matches_tests;
+mod normalize;
+#[cfg(test)]
+mod normalize_tests;
+mod planner;
+#[cfg(test)]
+mod planner_tests;
 mod qb;
 #[cfg(test)]
 mod qb_tests;
@@ -28,6 +58,20 @@ mod query;
 #[cfg(test)]
 mod query_tests;
 mod read;
+#[cfg(test)]
+mod read_tests;
+mod score;
+#[cfg(test)]
+mod score_tests;
+mod simplify;
+#[cfg(test)]
+mod simplify_tests;
+mod storage;
+#[cfg(test)]
+mod storage_tests;
+mod teams;
+#[cfg(test)]
+mod teams_tests;
 mod utils;
 #[cfg(test)]
 mod utils_tests;
@@ -47,145 +91,1158 @@ pub enum QuestionState {
     // OPTIMIZE: Idle state rather than deleting it from the map?
     // I'll need to figure out which is more performant
 }
-pub type ChannelState = (
-    QuestionState,
-    // shoot, i need to remove this... but it's gonna be a pain to change...
-    bool,
-    HashSet<UserId>,
-    watch::Sender<()>,
-    Tossup,
-    String,
-);
+
+/// An attempt to move between two states that the state diagram doesn't allow
+#[derive(Debug, Clone, PartialEq)]
+pub struct IllegalTransition {
+    pub from: QuestionState,
+    pub to: QuestionState,
+}
+
+impl fmt::Display for IllegalTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "illegal transition {:?} -> {:?}", self.from, self.to)
+    }
+}
+
+impl std::error::Error for IllegalTransition {}
+
+impl QuestionState {
+    /// The user this state is about, if any
+    pub fn user(&self) -> Option<UserId> {
+        match self {
+            QuestionState::Buzzed(user_id, _)
+            | QuestionState::Prompt(user_id, _, _)
+            | QuestionState::Invalid(user_id)
+            | QuestionState::Incorrect(user_id) => Some(*user_id),
+            QuestionState::Reading | QuestionState::Correct | QuestionState::Judging => None,
+        }
+    }
+
+    /// The transition table, i.e. the state diagram above plus judging and prompts
+    ///
+    /// ```text
+    /// Reading   -> Buzzed
+    /// Buzzed    -> Judging | Invalid     (answered or timed out)
+    /// Judging   -> Correct | Incorrect | Prompt
+    ///            | Invalid               (the judge failed or timed out)
+    /// Prompt    -> Judging | Invalid     (answered or timed out)
+    /// Invalid   -> Reading
+    /// Incorrect -> Reading
+    /// Correct   -> (nothing, the question is over)
+    /// ```
+    ///
+    /// A state that names a user can only move to another one about the same user.
+    pub fn can_transition_to(&self, next: &QuestionState) -> bool {
+        use QuestionState::*;
+        let allowed = matches!(
+            (self, next),
+            (Reading, Buzzed(..))
+                | (Buzzed(..), Judging | Invalid(_))
+                | (Judging, Correct | Incorrect(_) | Prompt(..) | Invalid(_))
+                | (Prompt(..), Judging | Invalid(_))
+                | (Invalid(_) | Incorrect(_), Reading)
+        );
+        let same_user = match (self.user(), next.user()) {
+            (Some(from), Some(to)) => from == to,
+            _ => true,
+        };
+        allowed && same_user
+    }
+
+    /// Move to `next` if the transition table allows it
+    pub fn transition(&self, next: QuestionState) -> Result<QuestionState, IllegalTransition> {
+        if self.can_transition_to(&next) {
+            Ok(next)
+        } else {
+            Err(IllegalTransition {
+                from: self.clone(),
+                to: next,
+            })
+        }
+    }
+}
+/// Everything a game keeps track of for the tossup it's reading
+#[derive(Debug, Clone)]
+pub struct ChannelState {
+    pub question_state: QuestionState,
+    /// Users who can't buzz again on this tossup
+    pub blocklist: HashSet<UserId>,
+    pub tossup: Tossup,
+    /// The question, formatted for Discord and split into words
+    pub words: Vec<String>,
+    /// How many of `words` have been read so far
+    pub words_read: usize,
+    /// Index of the word with the power mark, if there is one
+    pub power_index: Option<usize>,
+    /// How many words had been read when the current player buzzed
+    pub buzz_index: usize,
+    /// Who's on which team, in team mode
+    pub roster: Option<Roster>,
+}
+
+impl ChannelState {
+    pub fn new(tossup: Tossup) -> Self {
+        // Having it bold (or formatted in any manner) is kinda annoying
+        let words: Vec<String> = format_question(&tossup.question_sanitized)
+            .split(' ')
+            .map(str::to_string)
+            .collect();
+        // Escaping doesn't change where the words are, so this lines up with `words`
+        let power_index = tossup
+            .question_sanitized
+            .split(' ')
+            .position(|word| word.contains("(*)"));
+        ChannelState {
+            question_state: QuestionState::Reading,
+            blocklist: HashSet::new(),
+            tossup,
+            words,
+            words_read: 0,
+            power_index,
+            buzz_index: 0,
+            roster: None,
+        }
+    }
+
+    /// Play in team mode, where only players on a team can buzz
+    pub fn with_roster(mut self, roster: Roster) -> Self {
+        self.roster = Some(roster);
+        self
+    }
+
+    /// Whether `user` is allowed to buzz right now
+    pub fn can_buzz(&self, user: UserId) -> bool {
+        let on_team = self
+            .roster
+            .as_ref()
+            .is_none_or(|roster| roster.contains_key(&user));
+        on_team && !self.blocklist.contains(&user)
+    }
+
+    /// Keep `user` (and their whole team, in team mode) from buzzing again
+    pub fn lock_out(&mut self, user: UserId) {
+        self.blocklist.insert(user);
+        if let Some(team) = self.roster.as_ref().and_then(|roster| roster.get(&user)) {
+            self.blocklist.extend(
+                self.roster
+                    .iter()
+                    .flatten()
+                    .filter(|(_, other)| *other == team)
+                    .map(|(teammate, _)| *teammate),
+            );
+        }
+    }
+
+    /// Score the current buzz by where the player buzzed
+    pub fn score(&self, correct: bool) -> BuzzKind {
+        score_buzz(correct, self.buzz_index, self.power_index, self.words.len())
+    }
+
+    /// Read up to `size` more words, returning them
+    pub fn advance(&mut self, size: usize) -> String {
+        let chunk = nth_chunk(self.words[self.words_read..].iter().cloned(), size);
+        self.words_read += chunk.len();
+        chunk.join(" ")
+    }
+
+    pub fn finished_reading(&self) -> bool {
+        self.words_read >= self.words.len()
+    }
+
+    /// The question as far as it's been read
+    pub fn question_so_far(&self) -> String {
+        self.words[..self.words_read].join(" ")
+    }
+}
+
 /// User data, which is stored and accessible in all command invocations
 pub struct Data {
     pub reqwest: reqwest::Client,
-    // (channel_id, (question_state, power?, blocklist, state_change_notifier))
-    pub reading_states: Arc<Mutex<HashMap<ChannelId, ChannelState>>>,
-    pub llm: Box<dyn LLMProvider>,
+    pub games: Games,
+    /// Channels with a session (tossups or a bonus) going on
+    pub sessions: Arc<Mutex<HashSet<ChannelId>>>,
+    /// Woken whenever `/stop` ends a session, so anything waiting in a channel can check on it
+    pub session_stopped: Notify,
+    pub scores: Arc<Mutex<Scores>>,
+    pub storage: Arc<Storage>,
+    /// Each server's judges, loaded the first time they're needed and kept up to date by `/judges`
+    pub pipelines: Arc<Mutex<HashMap<GuildId, Pipeline>>>,
+    pub teams: Arc<Mutex<Teams>>,
+    /// Every set QBReader has, fetched the first time someone needs it
+    pub set_list: OnceCell<Vec<String>>,
+    pub llm: Arc<dyn LLMProvider>,
 }
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a> = poise::Context<'a, Data, Error>;
 
+/// A code block pointing at where the problem is in the query (or nothing)
+fn query_error_caret(query: &str, err: &QueryError) -> String {
+    match err.caret(query) {
+        Some(caret) => format!("\n```\n{}\n```", caret),
+        None => String::new(),
+    }
+}
+
+/// "Invalid category: Sycology (did you mean Psychology or Sociology?)"
+fn invalid_category_message(category: &str, suggestions: &[String]) -> String {
+    match did_you_mean(suggestions) {
+        Some(suggestion) => format!(
+            "'{}' is not a recognized category ({})",
+            category, suggestion
+        ),
+        None => format!("'{}' is not a recognized category", category),
+    }
+}
+
+/// "Saved queries can't use themselves: @a → @b → @a"
+fn cycle_message(cycle: &[String]) -> String {
+    let cycle: Vec<_> = cycle.iter().map(|name| format!("@{}", name)).collect();
+    format!("Saved queries can't use themselves: {}", cycle.join(" → "))
+}
+
+/// "'@mysic' isn't a saved query (did you mean @mysci?)"
+fn unknown_saved_query_message(name: &str, suggestions: &[String]) -> String {
+    match did_you_mean(suggestions) {
+        Some(suggestion) => format!("'@{}' isn't a saved query ({})", name, suggestion),
+        None => format!("'@{}' isn't a saved query", name),
+    }
+}
+
+async fn report_query_error(ctx: Context<'_>, query: &str, err: QueryError) -> Result<(), Error> {
+    let caret = query_error_caret(query, &err);
+    ctx.say(format!("{}{}", query_error_message(err), caret))
+        .await?;
+    Ok(())
+}
+
+fn query_error_message(err: QueryError) -> String {
+    match err {
+        QueryError::UnexpectedToken(message, _) => message,
+        QueryError::InvalidCategory(category, _, suggestions) => {
+            invalid_category_message(&category, &suggestions)
+        }
+        QueryError::ImpossibleBranch(issue) => {
+            format!("The query can't match any questions: {}", issue)
+        }
+        QueryError::UnexpectedEOF => "Unexpected end of input".to_string(),
+        QueryError::InvalidFilter(filter) => format!("Invalid filter: {}", filter),
+        QueryError::InvalidWeight(weight) => format!("Invalid weight: {}", weight),
+        QueryError::UnknownSavedQuery(name, _, suggestions) => {
+            unknown_saved_query_message(&name, &suggestions)
+        }
+        QueryError::CyclicSavedQuery(cycle) => cycle_message(&cycle),
+        QueryError::InSavedQuery(name, _, err) => {
+            format!("In @{}: {}", name, query_error_message(*err))
+        }
+    }
+}
+
+/// The saved queries the author can use here (their own, and the server's)
+async fn saved_queries(ctx: Context<'_>) -> Result<SavedQueries, Error> {
+    let (user, guild) = (ctx.author().id, ctx.guild_id());
+    let queries = ctx
+        .data()
+        .storage
+        .run(move |storage| storage.saved_queries(user, guild))
+        .await?;
+    Ok(saved_query_table(&queries))
+}
+
+/// The judges for answers here: the server's own pipeline, or the default
+///
+/// Only the first lookup for a server goes to the database, so this is cheap to call.
+pub async fn judge_pipeline(ctx: Context<'_>) -> Pipeline {
+    let Some(guild) = ctx.guild_id() else {
+        return DEFAULT_PIPELINE.clone();
+    };
+    if let Some(pipeline) = ctx.data().pipelines.lock().await.get(&guild) {
+        return pipeline.clone();
+    }
+    let pipeline = match ctx
+        .data()
+        .storage
+        .run(move |storage| storage.judge_pipeline(guild))
+        .await
+    {
+        Ok(Some(spec)) => Pipeline::parse(&spec).unwrap_or_else(|err| {
+            warn!("Ignoring {}'s judge pipeline `{}`: {}", guild, spec, err);
+            DEFAULT_PIPELINE.clone()
+        }),
+        Ok(None) => DEFAULT_PIPELINE.clone(),
+        Err(err) => {
+            // Not cached, so we try again next time
+            warn!("Couldn't look up {}'s judge pipeline: {}", guild, err);
+            return DEFAULT_PIPELINE.clone();
+        }
+    };
+    ctx.data()
+        .pipelines
+        .lock()
+        .await
+        .insert(guild, pipeline.clone());
+    pipeline
+}
+
+/// Parse the query for a command, telling the user what's wrong if it doesn't parse
+async fn api_query(ctx: Context<'_>, query: Option<String>) -> Result<Option<ApiQuery>, Error> {
+    let Some(query) = query else {
+        return Ok(Some(ApiQuery::default()));
+    };
+    let parsed_results = parse_query_with(&query, &saved_queries(ctx).await?);
+    debug!("Query requested: {:?}", query);
+    debug!("Parsed query results: {:?}", parsed_results);
+    match parsed_results {
+        Ok(api_params) => Ok(Some(api_params)),
+        Err(err) => {
+            report_query_error(ctx, &query, err).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// " for `Biology - Math`", or nothing if it's everything
+fn searching_for(api_params: &ApiQuery) -> String {
+    match api_params.to_expr() {
+        Expr::All => String::new(),
+        expr => format!(" for `{}`", expr),
+    }
+}
+
+/// Claim the channel for a session, telling the user if something's already going on
+async fn start_session(ctx: Context<'_>) -> Result<bool, Error> {
+    if !ctx.data().sessions.lock().await.insert(ctx.channel_id()) {
+        send_reply(
+            ctx,
+            CreateReply::default()
+                .ephemeral(true)
+                .content("Already reading a question"),
+        )
+        .await?;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Record a tossup's buzzes in the session scores and the stats database
+async fn record_tossup(ctx: Context<'_>, tossup: &Tossup, report: &QuestionReport) {
+    ctx.data()
+        .scores
+        .lock()
+        .await
+        .record(ctx.channel_id(), &report.buzzes);
+    let (guild, channel, session) = (ctx.guild_id(), ctx.channel_id(), ctx.id());
+    let (tossup, length, buzzes) = (
+        tossup.clone(),
+        report.question_length,
+        report.buzzes.clone(),
+    );
+    let recorded = ctx
+        .data()
+        .storage
+        .run(move |storage| {
+            storage.record_tossup(guild, channel, session, &tossup, length, &buzzes)
+        })
+        .await;
+    if let Err(err) = recorded {
+        // Losing stats isn't worth ending the session over
+        warn!("Failed to record buzzes: {}", err);
+    }
+}
+
+/// Whether the session in this channel is still going (i.e. nobody used `/stop`)
+async fn session_active(ctx: Context<'_>) -> bool {
+    ctx.data().sessions.lock().await.contains(&ctx.channel_id())
+}
+
+/// Announce the next question and pause a moment, returning whether to go ahead and read it
+async fn next_question(ctx: Context<'_>, announcement: &str) -> Result<bool, Error> {
+    let pause = async {
+        ctx.channel_id().say(&ctx.http(), announcement).await?;
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        Ok(())
+    };
+    still_going_after(&ctx.data().sessions, ctx.channel_id(), pause).await
+}
+
 /// Type "buzz" to buzz in. Once buzzed in, you have 10 seconds to answer.
 #[poise::command(slash_command)]
 async fn tossup(
     ctx: Context<'_>,
     #[description = "Query for selecting the category"] query: Option<String>,
-    // #[description = "Number of questions to read (1-10)"]
-    // #[min = 1]
-    // #[max = 10]
-    // number: Option<u32>,
+    #[description = "Number of questions to read (1-10)"]
+    #[min = 1]
+    #[max = 10]
+    number: Option<u32>,
+    #[description = "Play with the teams in this channel (a wrong answer locks out the whole team)"]
+    team_mode: Option<bool>,
 ) -> Result<(), Error> {
-    if ctx
-        .data()
-        .reading_states
+    let roster = if team_mode.unwrap_or(false) {
+        let roster = ctx.data().teams.lock().await.roster(ctx.channel_id());
+        if roster.is_empty() {
+            ctx.say(
+                "Nobody is on a team in this channel! Use `/team create` and `/team join` first.",
+            )
+            .await?;
+            return Ok(());
+        }
+        Some(roster)
+    } else {
+        None
+    };
+    if !start_session(ctx).await? {
+        return Ok(());
+    }
+    let result = tossup_session(ctx, query, number.unwrap_or(1).clamp(1, 10), roster).await;
+    ctx.data().sessions.lock().await.remove(&ctx.channel_id());
+    result
+}
+
+async fn tossup_session(
+    ctx: Context<'_>,
+    query: Option<String>,
+    number_of_questions: u32,
+    roster: Option<Roster>,
+) -> Result<(), Error> {
+    let Some(mut api_params) = api_query(ctx, query).await? else {
+        return Ok(());
+    };
+    // Fetch the whole session up front
+    api_params.number = number_of_questions;
+    let tossups = sample_tossups(&ctx.data().reqwest, &api_params).await?;
+
+    if tossups.is_empty() {
+        ctx.say(format!("No tossups found{}", searching_for(&api_params)))
+            .await?;
+        return Ok(());
+    }
+    read_tossups(ctx, &tossups, roster).await?;
+    Ok(())
+}
+
+/// Read tossups one after another (until someone uses `/stop`), returning how each went
+async fn read_tossups(
+    ctx: Context<'_>,
+    tossups: &[Tossup],
+    roster: Option<Roster>,
+) -> Result<Vec<QuestionOutcome>, Error> {
+    let channel = ctx.channel_id();
+    ctx.data()
+        .scores
         .lock()
         .await
-        .contains_key(&ctx.channel_id())
+        .start_session(channel, roster.clone());
+
+    // Read questions one by one
+    let mut outcomes = Vec::with_capacity(tossups.len());
+    for (index, question) in tossups.iter().enumerate() {
+        if index > 0 && !next_question(ctx, "🔄 **Next question**").await? {
+            break;
+        }
+
+        // Resolves once the question is over, so there's nothing to wait on
+        let report = read_question(&ctx, question, index == 0, roster.clone()).await?;
+        record_tossup(ctx, question, &report).await;
+        outcomes.push(report.outcome);
+        if report.outcome == QuestionOutcome::Stopped {
+            break;
+        }
+    }
+
+    if tossups.len() > 1 {
+        let summary = {
+            let scores = ctx.data().scores.lock().await;
+            session_summary(&outcomes, &scores.channels[&channel])
+        };
+        channel.say(&ctx.http(), summary).await?;
+    }
+
+    Ok(outcomes)
+}
+
+/// Reads a packet's tossups in order. Use `start` to pick up partway through.
+#[poise::command(slash_command)]
+async fn packet(
+    ctx: Context<'_>,
+    #[description = "Name of the set"]
+    #[autocomplete = "autocomplete_set"]
+    set: String,
+    #[description = "Packet number"]
+    #[min = 1]
+    number: u32,
+    #[description = "Tossup to start from (default: 1)"]
+    #[min = 1]
+    start: Option<u32>,
+) -> Result<(), Error> {
+    if !start_session(ctx).await? {
+        return Ok(());
+    }
+    let result = packet_session(ctx, set.trim(), number, start.unwrap_or(1)).await;
+    ctx.data().sessions.lock().await.remove(&ctx.channel_id());
+    result
+}
+
+async fn packet_session(ctx: Context<'_>, set: &str, number: u32, start: u32) -> Result<(), Error> {
+    let reqwest = &ctx.data().reqwest;
+    let packets = num_packets(reqwest, set).await?;
+    if packets == 0 {
+        ctx.say(format!(
+            "Couldn't find a set called **{}**. Try picking one from the suggestions.",
+            set
+        ))
+        .await?;
+        return Ok(());
+    }
+    if number > packets {
+        ctx.say(format!("**{}** only has {} packets", set, packets))
+            .await?;
+        return Ok(());
+    }
+
+    let tossups: Vec<Tossup> = packet_tossups(reqwest, set, number)
+        .await?
+        .tossups
+        .into_iter()
+        .filter(|tossup| tossup.number >= start)
+        .collect();
+    let (Some(first), Some(last)) = (tossups.first(), tossups.last()) else {
+        ctx.say(format!(
+            "Packet {} of **{}** doesn't have a tossup {}",
+            number, set, start
+        ))
+        .await?;
+        return Ok(());
+    };
+    ctx.say(format!(
+        "📦 **{}**, packet {}: tossups {} to {}",
+        set, number, first.number, last.number
+    ))
+    .await?;
+
+    let outcomes = read_tossups(ctx, &tossups, None).await?;
+    // Everything but a stopped question was read all the way through
+    let finished = outcomes
+        .iter()
+        .filter(|outcome| **outcome != QuestionOutcome::Stopped)
+        .count();
+    if let Some(next) = tossups.get(finished) {
+        ctx.channel_id()
+            .say(
+                &ctx.http(),
+                format!(
+                    "⏸️ Stopped before finishing tossup {}. Use `/packet set:{} number:{} start:{}` to pick up where you left off.",
+                    next.number, set, number, next.number
+                ),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+async fn autocomplete_set(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let data = ctx.data();
+    let sets = match data
+        .set_list
+        .get_or_try_init(|| set_list(&data.reqwest))
+        .await
     {
+        Ok(sets) => sets,
+        Err(err) => {
+            warn!("Failed to fetch the set list: {}", err);
+            return Vec::new();
+        }
+    };
+    let partial = partial.to_lowercase();
+    sets.iter()
+        .filter(|set| set.to_lowercase().contains(&partial))
+        // Discord only shows 25 suggestions
+        .take(25)
+        .cloned()
+        .collect()
+}
+
+/// Stops the current session in this channel
+#[poise::command(slash_command)]
+async fn stop(ctx: Context<'_>) -> Result<(), Error> {
+    let channel = ctx.channel_id();
+    if !ctx.data().sessions.lock().await.remove(&channel) {
         send_reply(
             ctx,
             CreateReply::default()
                 .ephemeral(true)
-                .content("Already reading a question"),
+                .content("Nothing is being read"),
         )
         .await?;
         return Ok(());
     }
+    ctx.data().session_stopped.notify_waiters();
+    if let Some(game) = ctx.data().games.lock().await.get(&channel) {
+        // The game might have just ended, in which case there's nothing to stop
+        let _ = game.send(GameEvent::Stop);
+    }
+    ctx.say("⏹️ Stopped").await?;
+    Ok(())
+}
 
-    let number_of_questions = 1;
-    // let number_of_questions = number.unwrap_or(1);
+/// Plays a full match between two teams in this channel, with bonuses for whoever gets each tossup
+#[poise::command(slash_command, rename = "match")]
+async fn match_command(
+    ctx: Context<'_>,
+    #[description = "The first team"]
+    #[autocomplete = "autocomplete_team"]
+    team_a: String,
+    #[description = "The second team"]
+    #[autocomplete = "autocomplete_team"]
+    team_b: String,
+    #[description = "Number of tossups (1-24, default: 20)"]
+    #[min = 1]
+    #[max = 24]
+    tossups: Option<u32>,
+    #[description = "Let the other team answer bonus parts that get missed"] bounce_backs: Option<
+        bool,
+    >,
+    #[description = "Query for selecting the category"] query: Option<String>,
+) -> Result<(), Error> {
+    let teams = {
+        let teams = ctx.data().teams.lock().await;
+        [&team_a, &team_b].map(|name| teams.members(ctx.channel_id(), name.trim()))
+    };
+    let [Some((name_a, members_a)), Some((name_b, members_b))] = teams else {
+        ctx.say("Both teams need to exist in this channel! Use `/team create` first.")
+            .await?;
+        return Ok(());
+    };
+    if name_a == name_b {
+        ctx.say("A team can't play itself!").await?;
+        return Ok(());
+    }
+    if members_a.is_empty() || members_b.is_empty() {
+        ctx.say("Both teams need players! Use `/team join` first.")
+            .await?;
+        return Ok(());
+    }
+    let score = MatchScore::new(
+        MatchTeam {
+            name: name_a,
+            members: members_a.into_iter().collect(),
+        },
+        MatchTeam {
+            name: name_b,
+            members: members_b.into_iter().collect(),
+        },
+    );
 
-    let tossups = if let Some(query) = query {
-        let mut parsed_results = parse_query(&query);
-        debug!("Query requested: {:?}", query);
-        debug!("Parsed query results: {:?}", parsed_results);
+    if !start_session(ctx).await? {
+        return Ok(());
+    }
+    let number = tossups.unwrap_or(DEFAULT_TOSSUPS).clamp(1, MAX_TOSSUPS);
+    let result = match_session(ctx, score, number, bounce_backs.unwrap_or(false), query).await;
+    ctx.data().sessions.lock().await.remove(&ctx.channel_id());
+    result
+}
 
-        match parsed_results {
-            Ok(ref mut api_params) => {
-                // Set the number of questions to fetch
-                api_params.number = number_of_questions;
-                let reqwest = &ctx.data().reqwest;
-                let get_tossup = random_tossup(reqwest, api_params).await?;
-                get_tossup.tossups
-            }
-            Err(err) => {
-                match err {
-                    QueryError::UnexpectedToken(message) => {
-                        ctx.say(message).await?;
-                    }
-                    QueryError::InvalidCategory(category) => {
-                        ctx.say(format!("Invalid category: {}", category)).await?;
-                    }
-                    QueryError::ImpossibleBranch(issue) => {
-                        ctx.say(format!(
-                            "The query is impossible (conflicting categories): {}",
-                            issue
-                        ))
-                        .await?;
-                    }
-                    QueryError::UnexpectedEOF => {
-                        ctx.say("Unexpected end of input").await?;
-                    }
-                };
-                return Ok(());
-            }
-        }
-    } else {
-        let reqwest = &ctx.data().reqwest;
-        let api_query = ApiQuery {
-            number: number_of_questions,
-            ..ApiQuery::default()
-        };
-        let get_tossup = random_tossup(reqwest, &api_query).await?;
-        get_tossup.tossups
+async fn match_session(
+    ctx: Context<'_>,
+    mut score: MatchScore,
+    number_of_tossups: u32,
+    bounce_backs: bool,
+    query: Option<String>,
+) -> Result<(), Error> {
+    let Some(mut api_params) = api_query(ctx, query).await? else {
+        return Ok(());
     };
-
+    api_params.number = number_of_tossups;
+    let reqwest = &ctx.data().reqwest;
+    let tossups = sample_tossups(reqwest, &api_params).await?;
+    let mut bonuses = sample_bonuses(reqwest, &api_params).await?.into_iter();
     if tossups.is_empty() {
-        ctx.say("No tossups found").await?;
+        ctx.say(format!("No tossups found{}", searching_for(&api_params)))
+            .await?;
         return Ok(());
     }
 
-    // Read questions one by one
+    let channel = ctx.channel_id();
+    let roster = score.roster();
+    ctx.data()
+        .scores
+        .lock()
+        .await
+        .start_session(channel, Some(roster.clone()));
+    ctx.say(format!(
+        "🏆 **{}** vs **{}**: {} tossups{}, bounce-backs {}",
+        score.teams[0].name,
+        score.teams[1].name,
+        tossups.len(),
+        searching_for(&api_params),
+        if bounce_backs { "on" } else { "off" }
+    ))
+    .await?;
+
     for (index, question) in tossups.iter().enumerate() {
-        if index > 0 {
-            // Wait for previous question to finish
-            while ctx
-                .data()
-                .reading_states
-                .lock()
-                .await
-                .contains_key(&ctx.channel_id())
-            {
-                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            }
+        let announcement = format!("🔄 **Tossup {}/{}**", index + 1, tossups.len());
+        if !next_question(ctx, &announcement).await? {
+            break;
+        }
+
+        let report = read_question(&ctx, question, false, Some(roster.clone())).await?;
+        record_tossup(ctx, question, &report).await;
+        score.record_tossup(&report.buzzes);
+        if report.outcome == QuestionOutcome::Stopped {
+            break;
+        }
 
-            // Announce next question
-            ctx.channel_id()
-                .say(&ctx.http(), "🔄 **Next question**")
+        if let QuestionOutcome::Correct(user_id) = report.outcome {
+            // Only players on a team can buzz, so this is always someone's
+            let Some(team) = score.team_of(user_id) else {
+                continue;
+            };
+            let Some(bonus) = bonuses.next() else {
+                channel
+                    .say(&ctx.http(), "Out of bonuses, so this one's tossups only")
+                    .await?;
+                continue;
+            };
+            if !session_active(ctx).await {
+                break;
+            }
+            channel
+                .say(
+                    &ctx.http(),
+                    format!("🎁 **Bonus for {}**", score.teams[team].name),
+                )
                 .await?;
-            // Small delay before starting next question
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            let bounce_back = bounce_backs.then(|| &score.teams[1 - team]);
+            let report = read_bonus(&ctx, &bonus, Some(&score.teams[team]), bounce_back).await?;
+            score.record_bonus(team, &report);
         }
+        channel.say(&ctx.http(), score.line()).await?;
+    }
+
+    channel.say(&ctx.http(), score.render_final()).await?;
+    channel.say(&ctx.http(), score.box_score()).await?;
+    Ok(())
+}
+
+/// Reads a three-part bonus. The first message after each part is taken as the answer.
+#[poise::command(slash_command)]
+async fn bonus(
+    ctx: Context<'_>,
+    #[description = "Query for selecting the category"] query: Option<String>,
+) -> Result<(), Error> {
+    if !start_session(ctx).await? {
+        return Ok(());
+    }
+    let result = bonus_session(ctx, query).await;
+    ctx.data().sessions.lock().await.remove(&ctx.channel_id());
+    result
+}
+
+async fn bonus_session(ctx: Context<'_>, query: Option<String>) -> Result<(), Error> {
+    let Some(api_params) = api_query(ctx, query).await? else {
+        return Ok(());
+    };
+    let bonuses = sample_bonuses(&ctx.data().reqwest, &api_params).await?;
 
-        read_question(&ctx, vec![question.clone()], index == 0).await?;
+    let Some(bonus) = bonuses.first() else {
+        ctx.say(format!("No bonuses found{}", searching_for(&api_params)))
+            .await?;
+        return Ok(());
+    };
+    read_bonus(&ctx, bonus, None, None).await?;
 
-        // If this is not the last question, wait for it to complete
-        if index < tossups.len() - 1 {
-            // Wait for the question reading to complete
-            while ctx
-                .data()
-                .reading_states
+    Ok(())
+}
+
+/// Shows the scores for this channel's session, or a player's running totals
+#[poise::command(slash_command, prefix_command)]
+async fn score(
+    ctx: Context<'_>,
+    #[description = "Player to show running totals for"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let response = {
+        let scores = ctx.data().scores.lock().await;
+        if let Some(user) = user {
+            match scores.players.get(&user.id) {
+                Some(score) => format!("**{}**: {}", user.name, score.line()),
+                None => format!("**{}** hasn't buzzed yet.", user.name),
+            }
+        } else {
+            match scores.channels.get(&ctx.channel_id()) {
+                Some(scoreboard) => format!(
+                    "📊 **Scores** ({} tossup{} heard)\n{}",
+                    scoreboard.tossups_heard,
+                    if scoreboard.tossups_heard == 1 {
+                        ""
+                    } else {
+                        "s"
+                    },
+                    scoreboard.render()
+                ),
+                None => "No tossups have been read in this channel yet.".to_string(),
+            }
+        }
+    };
+    ctx.say(response).await?;
+    Ok(())
+}
+
+/// Shows a player's accuracy and buzz depth in each category (in this server)
+#[poise::command(slash_command, prefix_command)]
+async fn stats(
+    ctx: Context<'_>,
+    #[description = "Player to show stats for (defaults to you)"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+    let (id, guild) = (user.id, ctx.guild_id());
+    let stats = ctx
+        .data()
+        .storage
+        .run(move |storage| storage.player_stats(id, guild))
+        .await?;
+    ctx.say(format!(
+        "📈 **Stats for {}**\n{}",
+        user.name,
+        stats.render()
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Manage the teams in this channel
+#[poise::command(
+    slash_command,
+    subcommands("team_create", "team_join", "team_leave", "team_list"),
+    subcommand_required
+)]
+async fn team(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Creates a team in this channel (and puts you on it)
+#[poise::command(slash_command, rename = "create")]
+async fn team_create(
+    ctx: Context<'_>,
+    #[description = "Name of the team"] name: String,
+) -> Result<(), Error> {
+    let channel = ctx.channel_id();
+    let response = {
+        let mut teams = ctx.data().teams.lock().await;
+        match teams.create(channel, name.trim()) {
+            Ok(()) => {
+                let team = teams.join(channel, ctx.author().id, name.trim())?;
+                format!("Created team **{}**", team)
+            }
+            Err(err) => err.to_string(),
+        }
+    };
+    ctx.say(response).await?;
+    Ok(())
+}
+
+/// Joins a team in this channel, leaving your old one
+#[poise::command(slash_command, rename = "join")]
+async fn team_join(
+    ctx: Context<'_>,
+    #[description = "Name of the team"]
+    #[autocomplete = "autocomplete_team"]
+    name: String,
+) -> Result<(), Error> {
+    let joined = ctx
+        .data()
+        .teams
+        .lock()
+        .await
+        .join(ctx.channel_id(), ctx.author().id, name.trim());
+    match joined {
+        Ok(team) => ctx.say(format!("You're on team **{}**", team)).await?,
+        Err(err) => ctx.say(err.to_string()).await?,
+    };
+    Ok(())
+}
+
+/// Leaves your team in this channel
+#[poise::command(slash_command, rename = "leave")]
+async fn team_leave(ctx: Context<'_>) -> Result<(), Error> {
+    let left = ctx
+        .data()
+        .teams
+        .lock()
+        .await
+        .leave(ctx.channel_id(), ctx.author().id);
+    match left {
+        Some(team) => ctx.say(format!("You left team **{}**", team)).await?,
+        None => ctx.say("You're not on a team in this channel").await?,
+    };
+    Ok(())
+}
+
+/// Shows the teams in this channel
+#[poise::command(slash_command, rename = "list")]
+async fn team_list(ctx: Context<'_>) -> Result<(), Error> {
+    let teams = ctx.data().teams.lock().await.render(ctx.channel_id());
+    ctx.say(format!("👥 **Teams**\n{}", teams)).await?;
+    Ok(())
+}
+
+async fn autocomplete_team(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    ctx.data()
+        .teams
+        .lock()
+        .await
+        .team_names(ctx.channel_id())
+        .into_iter()
+        .filter(|team| team.to_lowercase().starts_with(&partial.to_lowercase()))
+        .collect()
+}
+
+/// Ranks players in this server (or just this channel) for the current season
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn leaderboard(
+    ctx: Context<'_>,
+    #[description = "What to rank players by (defaults to points)"] rank_by: Option<Ranking>,
+    #[description = "Only count tossups read in this channel"] channel_only: Option<bool>,
+    #[description = "An earlier season to look at"]
+    #[min = 1]
+    season: Option<u32>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let channel = channel_only.unwrap_or(false).then(|| ctx.channel_id());
+    let rank_by = rank_by.unwrap_or_default();
+    let (season, standings) = ctx
+        .data()
+        .storage
+        .run(move |storage| {
+            let season = match season {
+                Some(season) => season,
+                None => storage.current_season(guild)?,
+            };
+            Ok((season, storage.standings(guild, channel, season, rank_by)?))
+        })
+        .await?;
+    ctx.say(format!(
+        "🏆 **{}leaderboard, season {}** (by {})\n{}",
+        if channel.is_some() { "Channel " } else { "" },
+        season,
+        rank_by.name().to_lowercase(),
+        render_standings(&standings)
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Starts a new season, archiving the current standings
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn newseason(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let (finished, standings, season) = ctx
+        .data()
+        .storage
+        .run(move |storage| {
+            let finished = storage.current_season(guild)?;
+            let standings = storage.standings(guild, None, finished, Ranking::Points)?;
+            Ok((finished, standings, storage.new_season(guild)?))
+        })
+        .await?;
+    ctx.say(format!(
+        "🏁 **Season {} is over!** Final standings:\n{}\n\n\
+        Season {} starts now. Use `/leaderboard season:{}` to look back at the old standings.",
+        finished,
+        render_standings(&standings),
+        season,
+        finished
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Shows or changes how answers are judged in this server
+#[poise::command(slash_command, prefix_command, guild_only)]
+async fn judges(
+    ctx: Context<'_>,
+    #[description = "Judges to ask in order, e.g. answer_line, levenshtein, llm (or default)"]
+    pipeline: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let Some(pipeline) = pipeline else {
+        let available: Vec<_> = JUDGES
+            .iter()
+            .map(|(name, description)| format!("• `{}`: {}", name, description))
+            .collect();
+        ctx.say(format!(
+            "⚖️ **Judges here:** `{}`\n\
+            Each one decides or passes to the next.\n\n\
+            **Available judges**\n{}",
+            judge_pipeline(ctx).await.spec(),
+            available.join("\n")
+        ))
+        .await?;
+        return Ok(());
+    };
+    let can_manage = ctx
+        .author_member()
+        .await
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.manage_guild());
+    if !can_manage {
+        ctx.say("You need the Manage Server permission to change the judges.")
+            .await?;
+        return Ok(());
+    }
+    let storage = &ctx.data().storage;
+    if pipeline.trim().eq_ignore_ascii_case("default") {
+        storage
+            .run(move |storage| storage.set_judge_pipeline(guild, None))
+            .await?;
+        ctx.data()
+            .pipelines
+            .lock()
+            .await
+            .insert(guild, DEFAULT_PIPELINE.clone());
+        ctx.say(format!(
+            "⚖️ Back to the default judges: `{}`",
+            DEFAULT_PIPELINE.spec()
+        ))
+        .await?;
+        return Ok(());
+    }
+    match Pipeline::parse(&pipeline) {
+        Ok(pipeline) => {
+            let spec = pipeline.spec();
+            storage
+                .run(move |storage| storage.set_judge_pipeline(guild, Some(&spec)))
+                .await?;
+            ctx.data()
+                .pipelines
                 .lock()
                 .await
-                .contains_key(&ctx.channel_id())
-            {
-                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            }
+                .insert(guild, pipeline.clone());
+            ctx.say(format!(
+                "⚖️ Answers here are now judged by `{}`",
+                pipeline.spec()
+            ))
+            .await?;
+        }
+        Err(err) => {
+            ctx.say(format!("❌ {}", err)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Saves a query so you can use it in others as `@name`
+#[poise::command(slash_command, prefix_command)]
+async fn savequery(
+    ctx: Context<'_>,
+    #[description = "What to call it (letters, numbers and _)"] name: String,
+    #[description = "The query, e.g. (Biology + Chemistry) - Math"] expression: String,
+    #[description = "Share it with everyone in the server (needs Manage Server)"] shared: Option<
+        bool,
+    >,
+) -> Result<(), Error> {
+    let name = name.trim_start_matches('@');
+    if !is_saved_query_name(name) {
+        ctx.say("Saved query names are up to 32 letters, numbers and underscores, like `mysci`.")
+            .await?;
+        return Ok(());
+    }
+    let owner = if shared.unwrap_or(false) {
+        let Some(guild) = ctx.guild_id() else {
+            ctx.say("Shared queries are for servers, so save this one without `shared`.")
+                .await?;
+            return Ok(());
+        };
+        let can_manage = ctx
+            .author_member()
+            .await
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_guild());
+        if !can_manage {
+            ctx.say("You need the Manage Server permission to share queries with everyone.")
+                .await?;
+            return Ok(());
         }
+        QueryOwner::Guild(guild)
+    } else {
+        QueryOwner::User(ctx.author().id)
+    };
+    // Shared queries can only use other shared queries, since nobody else has the admin's own
+    let (user, guild) = (ctx.author().id, ctx.guild_id());
+    let mut queries = ctx
+        .data()
+        .storage
+        .run(move |storage| storage.saved_queries(user, guild))
+        .await?;
+    if let QueryOwner::Guild(_) = owner {
+        queries.retain(|query| query.shared);
     }
+    // Check it as it was typed, with the new version in place so it can't end up using itself
+    let mut saved = saved_query_table(&queries);
+    saved.insert(name.to_lowercase(), expression.clone());
+    if let Err(err) = parse_query_with(&expression, &saved) {
+        report_query_error(ctx, &expression, err).await?;
+        return Ok(());
+    }
+    // Saved queries are stored simplified, however they were typed
+    let expression = match normalize(&expression) {
+        Ok(expression) => expression,
+        Err(err) => {
+            report_query_error(ctx, &expression, err).await?;
+            return Ok(());
+        }
+    };
+    let (query_name, query) = (name.to_string(), expression.clone());
+    ctx.data()
+        .storage
+        .run(move |storage| storage.save_query(owner, &query_name, &query))
+        .await?;
+    let whose = match owner {
+        QueryOwner::User(_) => "",
+        QueryOwner::Guild(_) => " for everyone in this server",
+    };
+    ctx.say(format!(
+        "💾 Saved `@{}`{}: `{}`\nUse it like `/tossup query:@{} + History`.",
+        name, whose, expression, name
+    ))
+    .await?;
+    Ok(())
+}
 
+/// Lists the saved queries you can use here
+#[poise::command(slash_command, prefix_command)]
+async fn queries(ctx: Context<'_>) -> Result<(), Error> {
+    let (user, guild) = (ctx.author().id, ctx.guild_id());
+    let queries = ctx
+        .data()
+        .storage
+        .run(move |storage| storage.saved_queries(user, guild))
+        .await?;
+    if queries.is_empty() {
+        ctx.say("*No saved queries yet.* Use `/savequery` to save one.")
+            .await?;
+        return Ok(());
+    }
+    let mut response = String::new();
+    for (shared, heading) in [
+        (false, "**Your queries**"),
+        (true, "**This server's queries**"),
+    ] {
+        let lines: Vec<_> = queries
+            .iter()
+            .filter(|query| query.shared == shared)
+            .map(|query| format!("• `@{}`: `{}`", query.name, query.expression))
+            .collect();
+        if !lines.is_empty() {
+            response.push_str(&format!("{}\n{}\n\n", heading, lines.join("\n")));
+        }
+    }
+    ctx.say(response.trim_end()).await?;
     Ok(())
 }
 
@@ -196,7 +1253,11 @@ async fn categories(
     #[description = "A specific category to see subcategories for"] parent_category: Option<String>,
 ) -> Result<(), Error> {
     if let Some(category) = parent_category {
-        // Search for the category case-insensitively
+        // Search for the category case-insensitively (or by an alias like "trash")
+        let category = match ALIASES.get(category.to_lowercase().as_str()) {
+            Some([name]) => name.to_string(),
+            _ => category,
+        };
         let category_key = CATEGORIES
             .keys()
             .find(|&key| key.to_lowercase() == category.to_lowercase());
@@ -204,12 +1265,12 @@ async fn categories(
         if let Some(key) = category_key {
             let (subcategories, alternate_subcategories) = CATEGORIES.get(key).unwrap();
 
-            let mut response = format!("**{}**\n", key);
+            let mut response = format!("{}\n", with_aliases(&format!("**{}**", key), key));
 
             if !subcategories.is_empty() {
                 response.push_str("**Subcategories:**\n");
                 for subcat in subcategories.iter() {
-                    response.push_str(&format!("• {}\n", subcat));
+                    response.push_str(&format!("• {}\n", with_aliases(subcat, subcat)));
                 }
                 response.push('\n');
             }
@@ -217,7 +1278,7 @@ async fn categories(
             if !alternate_subcategories.is_empty() {
                 response.push_str("**Alternate Subcategories:**\n");
                 for alt_subcat in alternate_subcategories.iter() {
-                    response.push_str(&format!("• {}\n", alt_subcat));
+                    response.push_str(&format!("• {}\n", with_aliases(alt_subcat, alt_subcat)));
                 }
             }
 
@@ -247,11 +1308,15 @@ async fn categories(
             if total_subcats > 0 {
                 response.push_str(&format!(" ({} subcategories)", total_subcats));
             }
+            let aliases = aliases_for(category);
+            if !aliases.is_empty() {
+                response.push_str(&format!(" - also `{}`", aliases.join("`, `")));
+            }
             response.push('\n');
         }
 
         response.push_str(
-            "\n💡 Use `/categories <category_name>` to see subcategories for a specific category.",
+            "\n💡 Use `/categories <category_name>` to see subcategories (and their aliases) for a specific category.",
         );
 
         ctx.say(response).await?;
@@ -259,6 +1324,16 @@ async fn categories(
     Ok(())
 }
 
+/// e.g. "Biology (`bio`)", for a name with aliases
+fn with_aliases(label: &str, name: &str) -> String {
+    let aliases = aliases_for(name);
+    if aliases.is_empty() {
+        label.to_string()
+    } else {
+        format!("{} (`{}`)", label, aliases.join("`, `"))
+    }
+}
+
 /// Displays help information about the bot and its commands
 #[poise::command(slash_command, prefix_command)]
 async fn help(
@@ -275,16 +1350,45 @@ async fn help(
             }
             "tossup" => {
                 let help_text = "**📚 /tossup Command**\n\n\
-                    **Usage:** `/tossup [query] [number]`\n\n\
+                    **Usage:** `/tossup [query] [number] [team_mode]`\n\n\
                     **Parameters:**\n\
                     • `query` (optional): Filter questions using the query language\n\
-                    • `number` (optional): Number of questions to read (1-10, default: 1)\n\n\
+                    • `number` (optional): Number of questions to read (1-10, default: 1)\n\
+                    • `team_mode` (optional): Play with the teams in this channel\n\n\
                     **Examples:**\n\
                     • `/tossup` - Random question from any category\n\
                     • `/tossup query:Biology` - Random biology question\n\
                     • `/tossup query:Science + History number:3` - 3 questions from Science or History\n\
                     • `/tossup number:5` - 5 random questions\n\n\
-                    When reading multiple questions, the bot will say \"Next question\" between each one.";
+                    When reading multiple questions, the bot will say \"Next question\" between each one \
+                    and post a summary at the end. Use `/stop` to end the session early.\n\n\
+                    In team mode, only players on a team (see `/team`) can buzz, \
+                    and a wrong answer locks out everyone on that team.";
+                ctx.say(help_text).await?;
+            }
+            "bonus" => {
+                let help_text = "**🎁 /bonus Command**\n\n\
+                    **Usage:** `/bonus [query]`\n\n\
+                    **Parameters:**\n\
+                    • `query` (optional): Filter questions using the query language\n\n\
+                    **Examples:**\n\
+                    • `/bonus` - Random bonus from any category\n\
+                    • `/bonus query:Biology` - Random biology bonus\n\n\
+                    The leadin and each of the three parts are read in turn. \
+                    The first message after a part is taken as the answer, and the bonus is worth up to 30 points.";
+                ctx.say(help_text).await?;
+            }
+            "match" => {
+                let help_text = "**🏆 /match Command**\n\n\
+                    **Usage:** `/match <team_a> <team_b> [tossups] [bounce_backs] [query]`\n\n\
+                    **Parameters:**\n\
+                    • `team_a`, `team_b`: Two teams in this channel (see `/team`)\n\
+                    • `tossups` (optional): Number of tossups (1-24, default: 20)\n\
+                    • `bounce_backs` (optional): Let the other team answer bonus parts that get missed\n\
+                    • `query` (optional): Filter questions using the query language\n\n\
+                    Tossups are played in team mode, so a wrong answer locks out the whole team. \
+                    Getting a tossup earns your team a bonus, which only your team can answer. \
+                    The final score and a box score are posted at the end.";
                 ctx.say(help_text).await?;
             }
             "categories" => {
@@ -310,7 +1414,7 @@ async fn help(
                 ctx.say(help_text).await?;
             }
             _ => {
-                ctx.say(format!("❌ Unknown help topic: '{}'. Available topics: `query`, `commands`, `tossup`, `categories`, `query-test`", topic)).await?;
+                ctx.say(format!("❌ Unknown help topic: '{}'. Available topics: `query`, `commands`, `tossup`, `bonus`, `match`, `categories`, `query-test`", topic)).await?;
             }
         }
     } else {
@@ -324,8 +1428,17 @@ async fn show_general_help(ctx: Context<'_>) -> Result<(), Error> {
         A Discord bot for quiz bowl question practice with advanced query language support.\n\n\
         ## 📋 Available Commands\n\n\
         • **`/tossup`** - Get quiz bowl questions (supports filtering and multiple questions)\n\
+        • **`/bonus`** - Play a three-part bonus (supports filtering)\n\
+        • **`/packet`** - Read a specific packet's tossups in order\n\
+        • **`/stop`** - Stop the current session early\n\
+        • **`/team`** - Create, join or leave a team for team mode\n\
+        • **`/match`** - Play a full match between two teams, with bonuses\n\
+        • **`/score`** - Show session scores or a player's running totals\n\
+        • **`/stats`** - Show a player's accuracy and buzz depth by category\n\
+        • **`/leaderboard`** - Rank players in this server for the season\n\
         • **`/categories`** - View available question categories and subcategories\n\
         • **`/query`** - Test query language expressions\n\
+        • **`/savequery`**, **`/queries`** - Save queries to reuse as `@name`\n\
         • **`/help`** - Get help (you're here!)\n\n\
         ## 🔍 Quick Start\n\n\
         1. Use `/tossup` to get a random question\n\
@@ -336,6 +1449,8 @@ async fn show_general_help(ctx: Context<'_>) -> Result<(), Error> {
         • `/help query` - Learn the query language syntax\n\
         • `/help commands` - Detailed command reference\n\
         • `/help tossup` - Learn about the tossup command options\n\
+        • `/help bonus` - Learn about the bonus command\n\
+        • `/help match` - Learn about playing matches\n\
         • `/help categories` - Learn about browsing categories";
 
     ctx.say(help_text).await?;
@@ -344,10 +1459,43 @@ async fn show_general_help(ctx: Context<'_>) -> Result<(), Error> {
 
 async fn show_commands_help(ctx: Context<'_>) -> Result<(), Error> {
     let help_text = "## 📋 Detailed Command Reference\n\n\
-        **`/tossup [query] [number]`**\n\
+        **`/tossup [query] [number] [team_mode]`**\n\
         Get quiz bowl questions with optional filtering and quantity.\n\
         • `query`: Use query language to filter by categories\n\
-        • `number`: Number of questions (1-10)\n\n\
+        • `number`: Number of questions (1-10)\n\
+        • `team_mode`: Play with this channel's teams\n\n\
+        **`/team create|join|leave|list`**\n\
+        Set up teams in this channel. In team mode, a wrong answer locks out your whole team, \
+        and team scores are kept separately.\n\n\
+        **`/match <team_a> <team_b> [tossups] [bounce_backs] [query]`**\n\
+        Play a match between two teams in this channel. Getting a tossup earns your team a bonus.\n\
+        • `tossups`: Number of tossups (1-24, default: 20)\n\
+        • `bounce_backs`: Let the other team answer bonus parts that get missed\n\n\
+        **`/packet <set> <number> [start]`**\n\
+        Read the tossups from a packet of a set, in order.\n\
+        • `set`: Name of the set (suggestions show up as you type)\n\
+        • `start`: Tossup to start from, for picking up where you left off\n\n\
+        **`/stop`**\n\
+        Stop the tossups or bonus being read in this channel.\n\n\
+        **`/score [user]`**\n\
+        Show scores (15 for a power, 10 for a correct buzz, -5 for a neg).\n\
+        • Without parameters: The current session in this channel\n\
+        • With a user: That player's running totals\n\n\
+        **`/stats [user]`**\n\
+        Show accuracy per category and how early correct buzzes come, for every tossup ever read in this server.\n\
+        • `user`: Whose stats to show (defaults to you)\n\n\
+        **`/leaderboard [rank_by] [channel_only] [season]`**\n\
+        Rank players in this server by points, powers, negs or points per tossup heard.\n\
+        • `channel_only`: Only count tossups read in this channel\n\
+        • `season`: Look back at an earlier season\n\n\
+        **`/newseason`** (needs Manage Server)\n\
+        Archive the current standings and start the leaderboard over.\n\n\
+        **`/judges [pipeline]`**\n\
+        Show which judges decide answers here, in order, or change them (needs Manage Server).\n\
+        • `pipeline`: e.g. `answer_line, levenshtein, llm`, or `default`\n\n\
+        **`/bonus [query]`**\n\
+        Play a three-part bonus, answering each part in the channel.\n\
+        • `query`: Use query language to filter by categories\n\n\
         **`/categories [parent_category]`**\n\
         Browse available question categories and subcategories.\n\
         • Without parameters: Shows all main categories\n\
@@ -356,6 +1504,11 @@ async fn show_commands_help(ctx: Context<'_>) -> Result<(), Error> {
         Test query language expressions to see what they would match.\n\
         • Shows which categories/subcategories would be included\n\
         • Helpful for building complex queries\n\n\
+        **`/savequery <name> <expression> [shared]`**\n\
+        Save a query to use in others as `@name`, e.g. `@mysci + History`.\n\
+        • `shared`: Let everyone in this server use it (needs Manage Server)\n\n\
+        **`/queries`**\n\
+        List your saved queries and this server's.\n\n\
         **`/help [topic]`**\n\
         Get help about the bot or specific topics.\n\
         • Without parameters: General overview\n\
@@ -371,18 +1524,35 @@ async fn show_query_language_help(ctx: Context<'_>) -> Result<(), Error> {
         ### Basic Syntax\n\
         • **Categories**: `Science`, `History`, `Literature`, etc.\n\
         • **Subcategories**: `Biology`, `Chemistry`, `American History`, etc.\n\
-        • **Multi-word**: Use quotes or just spaces: `American Literature`\n\n\
+        • **Multi-word**: Just use spaces: `American Literature`\n\
+        • **Aliases**: `bio`, `chem`, `cs`, `am lit`, `euro`, `rmp`, `trash`, `geo`, `ss`...\n\n\
         ### Operators (by precedence)\n\
         1. **`-` (Minus/Exclusion)** - Remove categories: `Science - Math`\n\
         2. **`&` (And/Intersection)** - Must match both: `Science & Biology`\n\
         3. **`+` (Or/Union)** - Match either: `Science + History`\n\
-        4. **`()` (Parentheses)** - Override precedence: `(Science + History) - Math`\n\n\
+        4. **`()` (Parentheses)** - Override precedence: `(Science + History) - Math`\n\
+        • A leading **`-`** or **`!`** means everything except: `-Pop Culture`\n\
+        • **`*`** or **`All`** means every category\n\n\
+        ### Weights\n\
+        • `Science*3 + History*2` - Draw 3 Science for every 2 History\n\
+        • `preset:acf`, `preset:naqt` - A tournament's category mix\n\n\
+        ### Saved Queries\n\
+        • `/savequery mysci (Biology + Chemistry) - Math` - Save a query\n\
+        • `@mysci + History` - Use it in another one (see `/queries`)\n\n\
         ### Examples\n\
         • `Biology` - All biology questions\n\
         • `Science + History` - Science OR history questions\n\
-        • `Biology & Chemistry` - Questions tagged as both\n\
+        • `Science & (Biology + Math)` - Science questions in both\n\
         • `Science - Math` - Science questions excluding math\n\
         • `(Biology + Chemistry) - Math` - Biology or chemistry, but no math\n\n\
+        ### Filters\n\
+        Combine these with the rest of the query using `&`:\n\
+        • `difficulty:3-5` (also `difficulty:4`, `difficulty:2,4`, `difficulty:>=6`)\n\
+        • `year:>=2018` (also `year:2015-2018`, `year:<2010`)\n\
+        • `standard:true` - Only standard sets\n\
+        • `\"photosynthesis\"` - Question text contains it\n\
+        • `answer:\"Napoleon\"` - Answer line contains it\n\
+        • e.g. `Biology & difficulty:1-3 & year:>=2018`\n\n\
         💡 Use `/query <expression>` to test your queries!\n\
         📂 Use `/categories` to see available categories!";
 
@@ -398,11 +1568,12 @@ async fn query(
 ) -> Result<(), Error> {
     debug!("Testing query: {}", query_string);
 
-    match parse_query(&query_string) {
+    match parse_query_with(&query_string, &saved_queries(ctx).await?) {
         Ok(api_params) => {
             let mut response = format!(
-                "✅ **Query parsed successfully!**\n\n**Input:** `{}`\n\n",
-                query_string
+                "✅ **Query parsed successfully!**\n\n**Input:** `{}`\n**Searching for:** `{}`\n\n",
+                query_string,
+                api_params.to_expr()
             );
 
             if !api_params.categories.is_empty() {
@@ -436,6 +1607,45 @@ async fn query(
                 response.push_str("*No specific categories matched - would return questions from all categories.*\n\n");
             }
 
+            if !api_params.parts.is_empty() {
+                let total: u32 = api_params.parts.iter().map(|part| part.weight).sum();
+                let shares = api_params.plan_shares();
+                response.push_str(&format!("**Plan:** {} QBReader requests\n", shares.len()));
+                for part in &api_params.parts {
+                    // Unweighted queries only have the one part, so there's nothing to weigh
+                    if api_params.parts.len() > 1 {
+                        response.push_str(&format!(
+                            "• `{}` ×{} ({:.0}%)\n",
+                            part.label,
+                            part.weight,
+                            100.0 * part.weight as f64 / total as f64
+                        ));
+                    }
+                    for (_, request, share) in shares
+                        .iter()
+                        .filter(|(owner, _, _)| std::ptr::eq(*owner, part))
+                    {
+                        response.push_str(&format!(
+                            "  • {} ({:.0}%)\n",
+                            request.describe(),
+                            100.0 * share
+                        ));
+                    }
+                }
+                response.push('\n');
+            } else {
+                response.push_str("**Plan:** 1 QBReader request\n\n");
+            }
+
+            let filters = api_params.describe_filters();
+            if !filters.is_empty() {
+                response.push_str("**Filters:**\n");
+                for filter in &filters {
+                    response.push_str(&format!("• {}\n", filter));
+                }
+                response.push('\n');
+            }
+
             response.push_str(&format!(
                 "**Number of questions:** {}\n\n",
                 api_params.number
@@ -448,18 +1658,34 @@ async fn query(
             ctx.say(response).await?;
         }
         Err(err) => {
+            let caret = query_error_caret(&query_string, &err);
             let error_msg = match err {
-                QueryError::UnexpectedToken(message) => {
-                    format!("❌ **Syntax Error**\n\n{}\n\n💡 Check your operator placement and parentheses.", message)
+                QueryError::UnexpectedToken(message, _) => {
+                    format!("❌ **Syntax Error**\n\n{}{}\n\n💡 Check your operator placement and parentheses.", message, caret)
                 }
-                QueryError::InvalidCategory(category) => {
-                    format!("❌ **Invalid Category**\n\n'{}' is not a recognized category.\n\n💡 Use `/categories` to see available categories.", category)
+                QueryError::InvalidCategory(category, _, suggestions) => {
+                    format!("❌ **Invalid Category**\n\n{}.{}\n\n💡 Use `/categories` to see available categories.", invalid_category_message(&category, &suggestions), caret)
                 }
                 QueryError::ImpossibleBranch(issue) => {
-                    format!("❌ **Impossible Query**\n\nThe query has conflicting categories: {}\n\n💡 Check for contradictory AND conditions.", issue)
+                    format!("❌ **Impossible Query**\n\nThe query can't match any questions: {}\n\n💡 A question only has one subcategory, so join subcategories with `+` rather than `&`.", issue)
+                }
+                QueryError::InvalidFilter(filter) => {
+                    format!("❌ **Invalid Filter**\n\n`{}` isn't a filter I understand.\n\n💡 Try `difficulty:3-5`, `year:>=2018`, `standard:true` or `answer:\"Napoleon\"`, combined with `&` (only one text search per query).", filter)
+                }
+                QueryError::InvalidWeight(weight) => {
+                    format!("❌ **Invalid Weight**\n\n`{}`\n\n💡 Weights go on the parts of a `+`, like `Science*3 + History*2`.", weight)
+                }
+                QueryError::UnknownSavedQuery(name, _, suggestions) => {
+                    format!("❌ **Unknown Saved Query**\n\n{}.{}\n\n💡 Use `/queries` to see the saved queries you can use.", unknown_saved_query_message(&name, &suggestions), caret)
+                }
+                QueryError::CyclicSavedQuery(cycle) => {
+                    format!("❌ **Saved Query Loop**\n\n{}.\n\n💡 Use `/savequery` to change one of them.", cycle_message(&cycle))
+                }
+                QueryError::InSavedQuery(name, _, err) => {
+                    format!("❌ **Broken Saved Query**\n\nIn `@{}`: {}{}\n\n💡 Use `/queries` to see what it is, and `/savequery` to fix it.", name, query_error_message(*err), caret)
                 }
                 QueryError::UnexpectedEOF => {
-                    "❌ **Incomplete Query**\n\nThe query ended unexpectedly. Check for unclosed parentheses.\n\n💡 Make sure all parentheses are properly closed.".to_string()
+                    format!("❌ **Incomplete Query**\n\nThe query ended unexpectedly. Check for unclosed parentheses or quotes.{}\n\n💡 Make sure all parentheses and quotes are properly closed.", caret)
                 }
             };
 
@@ -474,15 +1700,33 @@ async fn query(
 async fn main() {
     tracing_subscriber::fmt::init();
     let token = std::env::var("DISCORD_TOKEN").expect("missing DISCORD_TOKEN");
-    let gemini_api_key = std::env::var("GEMINI_API_KEY").ok();
-    let ollama_base_url = std::env::var("OLLAMA_URL").unwrap_or("http://127.0.0.1:11434".into());
     let intents =
         serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::MESSAGE_CONTENT;
     let reqwest = reqwest::Client::new();
-    let llm = get_llm(&reqwest).await;
+    let llm = Arc::from(get_llm(&reqwest).await);
+    // Catch a bad JUDGE_PIPELINE now rather than at the first buzz
+    info!("Default judges: {}", DEFAULT_PIPELINE.spec());
+    let storage = Arc::new(Storage::open_from_env().expect("failed to open the database"));
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![tossup(), categories(), help(), query()],
+            commands: vec![
+                tossup(),
+                bonus(),
+                packet(),
+                stop(),
+                score(),
+                stats(),
+                leaderboard(),
+                team(),
+                match_command(),
+                newseason(),
+                judges(),
+                categories(),
+                help(),
+                query(),
+                savequery(),
+                queries(),
+            ],
             event_handler: |ctx, event, framework, data| {
                 Box::pin(event_handler(ctx, event, framework, data))
             },
@@ -493,7 +1737,14 @@ async fn main() {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                 Ok(Data {
                     reqwest,
-                    reading_states: Arc::new(Mutex::new(HashMap::new())),
+                    games: Arc::new(Mutex::new(HashMap::new())),
+                    sessions: Arc::new(Mutex::new(HashSet::new())),
+                    session_stopped: Notify::new(),
+                    scores: Arc::new(Mutex::new(Scores::default())),
+                    storage,
+                    pipelines: Arc::new(Mutex::new(HashMap::new())),
+                    teams: Arc::new(Mutex::new(Teams::default())),
+                    set_list: OnceCell::new(),
                     llm,
                 })
             })
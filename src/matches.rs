@@ -0,0 +1,172 @@
+/// Full matches: two teams, a set number of tossups, and bonuses for whoever gets them
+use std::collections::HashSet;
+
+use ::serenity::all::{Mentionable, UserId};
+
+use crate::bonus::BonusReport;
+use crate::score::{PlayerScore, Scoreboard, ScoredBuzz};
+use crate::teams::Roster;
+
+/// The usual length of a packet
+pub const DEFAULT_TOSSUPS: u32 = 20;
+pub const MAX_TOSSUPS: u32 = 24;
+
+/// One side of a match
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchTeam {
+    pub name: String,
+    pub members: HashSet<UserId>,
+}
+
+/// How a team did on the bonuses
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BonusTotals {
+    /// Bonuses the team earned
+    pub heard: u32,
+    pub points: u32,
+    /// Points picked up from the other team's bonuses
+    pub bounce_back_points: u32,
+}
+
+impl BonusTotals {
+    /// Points per bonus heard
+    pub fn ppb(&self) -> Option<f64> {
+        (self.heard > 0).then(|| self.points as f64 / self.heard as f64)
+    }
+}
+
+/// The running score of a match
+#[derive(Debug, Clone)]
+pub struct MatchScore {
+    pub teams: [MatchTeam; 2],
+    pub bonuses: [BonusTotals; 2],
+    /// Tossup points, for both teams and every player
+    pub tossups: Scoreboard,
+}
+
+impl MatchScore {
+    pub fn new(first: MatchTeam, second: MatchTeam) -> Self {
+        let roster: Roster = [&first, &second]
+            .iter()
+            .flat_map(|team| {
+                team.members
+                    .iter()
+                    .map(|member| (*member, team.name.clone()))
+            })
+            .collect();
+        MatchScore {
+            teams: [first, second],
+            bonuses: Default::default(),
+            tossups: Scoreboard::with_teams(roster),
+        }
+    }
+
+    pub fn roster(&self) -> Roster {
+        self.tossups.roster.clone().unwrap_or_default()
+    }
+
+    /// Index (into `teams`) of the user's team
+    pub fn team_of(&self, user: UserId) -> Option<usize> {
+        self.teams
+            .iter()
+            .position(|team| team.members.contains(&user))
+    }
+
+    pub fn record_tossup(&mut self, buzzes: &[ScoredBuzz]) {
+        self.tossups.record(buzzes);
+    }
+
+    /// Record a bonus earned by `team`, with any bounce-backs going to the other team
+    ///
+    /// Bonuses that never got read (skipped, or stopped before the first part) don't count
+    pub fn record_bonus(&mut self, team: usize, report: &BonusReport) {
+        if report.parts.is_empty() {
+            return;
+        }
+        self.bonuses[team].heard += 1;
+        self.bonuses[team].points += report.points();
+        self.bonuses[1 - team].bounce_back_points += report.bounce_back_points();
+    }
+
+    pub fn tossup_score(&self, team: usize) -> PlayerScore {
+        self.tossups
+            .teams
+            .get(&self.teams[team].name)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn total(&self, team: usize) -> i32 {
+        let bonuses = self.bonuses[team];
+        self.tossup_score(team).points + (bonuses.points + bonuses.bounce_back_points) as i32
+    }
+
+    /// e.g. "**Red** 135, **Blue** 80"
+    pub fn line(&self) -> String {
+        format!(
+            "**{}** {}, **{}** {}",
+            self.teams[0].name,
+            self.total(0),
+            self.teams[1].name,
+            self.total(1)
+        )
+    }
+
+    pub fn render_final(&self) -> String {
+        let result = match self.total(0).cmp(&self.total(1)) {
+            std::cmp::Ordering::Greater => format!("**{}** wins!", self.teams[0].name),
+            std::cmp::Ordering::Less => format!("**{}** wins!", self.teams[1].name),
+            std::cmp::Ordering::Equal => "It's a tie!".to_string(),
+        };
+        format!("🏁 **Final score**\n{}\n{}", self.line(), result)
+    }
+
+    /// Tossup and bonus breakdowns for each team, then each of its players
+    pub fn box_score(&self) -> String {
+        let mut lines = vec!["📋 **Box score**".to_string()];
+        for (index, team) in self.teams.iter().enumerate() {
+            let tossups = self.tossup_score(index);
+            let bonuses = self.bonuses[index];
+            lines.push(format!("**{}**: {} points", team.name, self.total(index)));
+            lines.push(format!(
+                "• Tossups: {} ({}/{}/{})",
+                tossups.points, tossups.powers, tossups.tens, tossups.negs
+            ));
+            let ppb = match bonuses.ppb() {
+                Some(ppb) => format!(" ({:.2} PPB)", ppb),
+                None => String::new(),
+            };
+            lines.push(format!(
+                "• Bonuses: {} points on {} heard{}",
+                bonuses.points, bonuses.heard, ppb
+            ));
+            if bonuses.bounce_back_points > 0 {
+                lines.push(format!(
+                    "• Bounce-backs: {} points",
+                    bonuses.bounce_back_points
+                ));
+            }
+            // Everyone on the team, even if they never buzzed
+            let mut players: Vec<_> = team
+                .members
+                .iter()
+                .map(|member| {
+                    let score = self
+                        .tossups
+                        .players
+                        .get(member)
+                        .copied()
+                        .unwrap_or_default();
+                    (*member, score)
+                })
+                .collect();
+            players.sort_by(|a, b| b.1.points.cmp(&a.1.points).then(a.0.cmp(&b.0)));
+            lines.extend(
+                players
+                    .iter()
+                    .map(|(member, score)| format!("  • {}: {}", member.mention(), score.line())),
+            );
+        }
+        lines.join("\n")
+    }
+}
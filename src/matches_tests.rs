@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod tests {
+    use crate::bonus::{BonusReport, PartResult};
+    use crate::matches::*;
+    use crate::score::{BuzzKind, ScoredBuzz};
+    use serenity::all::UserId;
+    use std::collections::HashSet;
+
+    fn team(name: &str, members: &[u64]) -> MatchTeam {
+        MatchTeam {
+            name: name.to_string(),
+            members: members
+                .iter()
+                .map(|id| UserId::new(*id))
+                .collect::<HashSet<_>>(),
+        }
+    }
+
+    fn buzz(user: u64, kind: BuzzKind) -> ScoredBuzz {
+        ScoredBuzz {
+            user_id: UserId::new(user),
+            word_index: 0,
+            kind,
+        }
+    }
+
+    fn bonus(parts: &[PartResult]) -> BonusReport {
+        BonusReport {
+            parts: parts.iter().map(|result| (10, *result)).collect(),
+        }
+    }
+
+    fn score() -> MatchScore {
+        MatchScore::new(team("Red", &[1, 2]), team("Blue", &[3]))
+    }
+
+    #[test]
+    fn test_bonus_report_points() {
+        let report = bonus(&[
+            PartResult::Correct,
+            PartResult::BouncedBack,
+            PartResult::Missed,
+        ]);
+        assert_eq!(report.points(), 10);
+        assert_eq!(report.bounce_back_points(), 10);
+        assert_eq!(report.total(), 30);
+    }
+
+    #[test]
+    fn test_team_of() {
+        let score = score();
+        assert_eq!(score.team_of(UserId::new(2)), Some(0));
+        assert_eq!(score.team_of(UserId::new(3)), Some(1));
+        assert_eq!(score.team_of(UserId::new(4)), None);
+        assert_eq!(score.roster().len(), 3);
+    }
+
+    #[test]
+    fn test_totals() {
+        let mut score = score();
+        // Blue negs, Red powers it and gets two parts, Blue bounces back the third
+        score.record_tossup(&[buzz(3, BuzzKind::Neg), buzz(1, BuzzKind::Power)]);
+        score.record_bonus(
+            0,
+            &bonus(&[
+                PartResult::Correct,
+                PartResult::Correct,
+                PartResult::BouncedBack,
+            ]),
+        );
+        assert_eq!(score.total(0), 35);
+        assert_eq!(score.total(1), 5);
+        assert_eq!(score.bonuses[0].heard, 1);
+        assert_eq!(score.bonuses[0].ppb(), Some(20.0));
+        assert_eq!(score.bonuses[1].heard, 0);
+        assert_eq!(score.bonuses[1].ppb(), None);
+        assert_eq!(score.bonuses[1].bounce_back_points, 10);
+        assert_eq!(score.line(), "**Red** 35, **Blue** 5");
+        // A bonus that got skipped doesn't drag the PPB down
+        score.record_bonus(0, &bonus(&[]));
+        assert_eq!(score.bonuses[0].heard, 1);
+        assert_eq!(score.bonuses[0].ppb(), Some(20.0));
+    }
+
+    #[test]
+    fn test_render_final() {
+        let mut score = score();
+        assert!(score.render_final().ends_with("It's a tie!"));
+        score.record_tossup(&[buzz(3, BuzzKind::Ten)]);
+        assert_eq!(
+            score.render_final(),
+            "🏁 **Final score**\n**Red** 0, **Blue** 10\n**Blue** wins!"
+        );
+    }
+
+    #[test]
+    fn test_box_score() {
+        let mut score = score();
+        score.record_tossup(&[buzz(2, BuzzKind::Ten)]);
+        score.record_bonus(0, &bonus(&[PartResult::Correct, PartResult::Missed]));
+        assert_eq!(
+            score.box_score(),
+            "📋 **Box score**\n\
+            **Red**: 20 points\n\
+            • Tossups: 10 (0/1/0)\n\
+            • Bonuses: 10 points on 1 heard (10.00 PPB)\n  \
+            • <@2>: 10 points (0/1/0)\n  \
+            • <@1>: 0 points (0/0/0)\n\
+            **Blue**: 0 points\n\
+            • Tossups: 0 (0/0/0)\n\
+            • Bonuses: 0 points on 0 heard\n  \
+            • <@3>: 0 points (0/0/0)"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+/// Putting answers in a standard form before comparing them
+///
+/// Both the response and the answer key go through `normalize`, so "Émile Zola" and
+/// "emile zola", "Henry VIII" and "henry the eighth", or "1812" and "eighteen twelve"
+/// come out the same.
+use std::sync::LazyLock;
+
+use regex::Regex;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Ordinals written with digits, e.g. "4th" or "22nd"
+static DIGIT_ORDINAL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d+)(?:st|nd|rd|th)$").expect("Failed to compile regex"));
+/// Roman numerals written the usual way (so "IIII" or "IC" aren't)
+static ROMAN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^m{0,3}(cm|cd|d?c{0,3})(xc|xl|l?x{0,3})(ix|iv|v?i{0,3})$")
+        .expect("Failed to compile regex")
+});
+
+const ARTICLES: &[&str] = &["the", "a", "an"];
+
+/// Words that happen to be roman numerals, but usually aren't meant as them
+const NOT_ROMAN: &[&str] = &["mix", "dc", "cd", "cv", "mi", "li", "xi", "di", "mm", "cc"];
+
+const ONES: &[&str] = &[
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const ORDINAL_ONES: &[&str] = &[
+    "zeroth",
+    "first",
+    "second",
+    "third",
+    "fourth",
+    "fifth",
+    "sixth",
+    "seventh",
+    "eighth",
+    "ninth",
+    "tenth",
+    "eleventh",
+    "twelfth",
+    "thirteenth",
+    "fourteenth",
+    "fifteenth",
+    "sixteenth",
+    "seventeenth",
+    "eighteenth",
+    "nineteenth",
+];
+const TENS: &[&str] = &[
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const ORDINAL_TENS: &[&str] = &[
+    "",
+    "",
+    "twentieth",
+    "thirtieth",
+    "fortieth",
+    "fiftieth",
+    "sixtieth",
+    "seventieth",
+    "eightieth",
+    "ninetieth",
+];
+
+enum NumberWord {
+    /// 0 to 99, which can be added to
+    Small(u64),
+    Hundred,
+    /// A thousand, a million, ...
+    Scale(u64),
+}
+
+/// A number word, and whether it's an ordinal ("fourth")
+fn number_word(word: &str) -> Option<(NumberWord, bool)> {
+    let position = |list: &[&str]| list.iter().position(|w| *w == word).map(|n| n as u64);
+    if let Some(n) = position(ONES) {
+        return Some((NumberWord::Small(n), false));
+    }
+    if let Some(n) = position(ORDINAL_ONES) {
+        return Some((NumberWord::Small(n), true));
+    }
+    if let Some(n) = position(TENS).filter(|n| *n >= 2) {
+        return Some((NumberWord::Small(n * 10), false));
+    }
+    if let Some(n) = position(ORDINAL_TENS).filter(|n| *n >= 2) {
+        return Some((NumberWord::Small(n * 10), true));
+    }
+    Some(match word {
+        "hundred" => (NumberWord::Hundred, false),
+        "hundredth" => (NumberWord::Hundred, true),
+        "thousand" => (NumberWord::Scale(1_000), false),
+        "thousandth" => (NumberWord::Scale(1_000), true),
+        "million" => (NumberWord::Scale(1_000_000), false),
+        "millionth" => (NumberWord::Scale(1_000_000), true),
+        _ => return None,
+    })
+}
+
+/// Read a number written out in words from the start of `words`
+///
+/// Returns the number and how many words it took. Years work the way they're said, so
+/// "eighteen twelve" is 1812 and "nineteen eighty four" is 1984.
+fn read_number(words: &[&str]) -> Option<(u64, usize)> {
+    let mut total: u64 = 0;
+    // The part below a thousand, and the two-digit group being built
+    let mut current: u64 = 0;
+    let mut group: Option<u64> = None;
+    // The first half of a year like "eighteen twelve"
+    let mut century: Option<u64> = None;
+    // The number so far, and how many words it took
+    let mut read = None;
+    for (index, word) in words.iter().enumerate() {
+        // "one hundred and five"
+        if *word == "and" && group.is_none() && current > 0 && index + 1 < words.len() {
+            continue;
+        }
+        let Some((number, ordinal)) = number_word(word) else {
+            break;
+        };
+        // Anything too big for a u64 ends the number ("hundred hundred hundred ...")
+        match number {
+            NumberWord::Small(n) => match group {
+                // "twenty" + "four"
+                Some(tens) if tens >= 20 && tens % 10 == 0 && n < 10 => group = Some(tens + n),
+                // Another group straight after one: "eighteen" "twelve"
+                Some(first) if first >= 10 && current == 0 && total == 0 && century.is_none() => {
+                    century = Some(first);
+                    group = Some(n);
+                }
+                Some(_) => break,
+                None => group = Some(n),
+            },
+            NumberWord::Hundred if century.is_none() => {
+                let Some(hundreds) = current
+                    .checked_add(group.take().unwrap_or(1))
+                    .and_then(|n| n.checked_mul(100))
+                else {
+                    break;
+                };
+                current = hundreds;
+            }
+            NumberWord::Scale(scale) if century.is_none() => {
+                let Some(sum) = current
+                    .checked_add(group.take().unwrap_or(1))
+                    .and_then(|n| n.checked_mul(scale))
+                    .and_then(|n| total.checked_add(n))
+                else {
+                    break;
+                };
+                total = sum;
+                current = 0;
+            }
+            _ => break,
+        }
+        let number = match century {
+            Some(century) => Some(century * 100 + group.unwrap_or(0)),
+            None => total
+                .checked_add(current)
+                .and_then(|n| n.checked_add(group.unwrap_or(0))),
+        };
+        let Some(number) = number else {
+            break;
+        };
+        read = Some((number, index + 1));
+        if ordinal {
+            break;
+        }
+    }
+    read
+}
+
+fn roman_value(c: char) -> u64 {
+    match c {
+        'i' => 1,
+        'v' => 5,
+        'x' => 10,
+        'l' => 50,
+        'c' => 100,
+        'd' => 500,
+        'm' => 1000,
+        _ => 0,
+    }
+}
+
+/// The value of a (lowercase) roman numeral, if that's what the word is
+fn roman(word: &str) -> Option<u64> {
+    if word.is_empty() || !ROMAN.is_match(word) {
+        return None;
+    }
+    let values: Vec<u64> = word.chars().map(roman_value).collect();
+    let mut total: i64 = 0;
+    for (i, value) in values.iter().enumerate() {
+        // A smaller numeral before a bigger one is taken away from it, like the I in IV
+        match values.get(i + 1) {
+            Some(next) if next > value => total -= *value as i64,
+            _ => total += *value as i64,
+        }
+    }
+    Some(total as u64)
+}
+
+/// Lowercase, without accents, punctuation or leading articles, and with numbers as digits
+///
+/// - "Émile" is "emile", and "Maxwell's" is "maxwells"
+/// - "The Republic" is "republic" (only leading articles go, so "Of Mice and Men" keeps its "and")
+/// - "eighteen twelve", "1,812" and "MDCCCXII" (after the first word) are all "1812"
+/// - Ordinals are plain numbers: "4th" and "fourth" are "4", and "Henry the Eighth" is "henry 8"
+pub fn normalize(text: &str) -> String {
+    let folded: String = text
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+        .replace('&', " and ");
+    // Apostrophes and thousands separators join things up, everything else splits them
+    let chars: Vec<char> = folded.chars().collect();
+    let mut cleaned = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        let between_digits = i > 0
+            && chars[i - 1].is_ascii_digit()
+            && chars.get(i + 1).is_some_and(char::is_ascii_digit);
+        match c {
+            '\'' | '’' | '‘' => {}
+            ',' if between_digits => {}
+            c if c.is_alphanumeric() => cleaned.push(*c),
+            _ => cleaned.push(' '),
+        }
+    }
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    let mut normalized: Vec<String> = Vec::new();
+    let mut index = 0;
+    while index < words.len() {
+        let word = words[index];
+        if let Some((number, used)) = read_number(&words[index..]) {
+            // "Henry the Eighth" is "Henry 8", like "Henry VIII"
+            if normalized.last().is_some_and(|last| last == "the") && normalized.len() > 1 {
+                normalized.pop();
+            }
+            normalized.push(number.to_string());
+            index += used;
+            continue;
+        }
+        if let Some(capture) = DIGIT_ORDINAL.captures(word) {
+            normalized.push(capture[1].trim_start_matches('0').to_string());
+        } else if let Some(number) = roman(word).filter(|_| index > 0 && !NOT_ROMAN.contains(&word))
+        {
+            normalized.push(number.to_string());
+        } else if word.chars().all(|c| c.is_ascii_digit()) {
+            let trimmed = word.trim_start_matches('0');
+            normalized.push(if trimmed.is_empty() { "0" } else { trimmed }.to_string());
+        } else {
+            normalized.push(word.to_string());
+        }
+        index += 1;
+    }
+    // Leading articles, as long as something's left
+    while normalized.len() > 1 && ARTICLES.contains(&normalized[0].as_str()) {
+        normalized.remove(0);
+    }
+    normalized.join(" ")
+}
@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use crate::normalize::*;
+
+    #[test]
+    fn folds_case_accents_and_punctuation() {
+        assert_eq!(normalize("Émile Zola"), "emile zola");
+        assert_eq!(normalize("Dvořák"), "dvorak");
+        assert_eq!(normalize("Maxwell's Demon"), "maxwells demon");
+        assert_eq!(normalize("Jean-Paul  Sartre!"), "jean paul sartre");
+        assert_eq!(normalize("Ｆｕｌｌｗｉｄｔｈ"), "fullwidth");
+        assert_eq!(normalize("Romeo & Juliet"), "romeo and juliet");
+        assert_eq!(normalize(""), "");
+    }
+
+    #[test]
+    fn drops_leading_articles() {
+        assert_eq!(normalize("The Republic"), "republic");
+        assert_eq!(normalize("a Tale of Two Cities"), "tale of 2 cities");
+        assert_eq!(normalize("An American in Paris"), "american in paris");
+        assert_eq!(normalize("Of Mice and Men"), "of mice and men");
+        assert_eq!(normalize("Catcher in the Rye"), "catcher in the rye");
+        // Something has to be left
+        assert_eq!(normalize("The"), "the");
+    }
+
+    #[test]
+    fn numbers() {
+        let same = [
+            ("1812", "eighteen twelve"),
+            ("War of 1812", "war of eighteen twelve"),
+            ("1984", "Nineteen Eighty-Four"),
+            ("2006", "two thousand six"),
+            ("2020", "twenty twenty"),
+            ("1900", "nineteen hundred"),
+            ("105", "one hundred and five"),
+            ("1,000,000", "one million"),
+            ("Henry VIII", "Henry 8"),
+            ("Henry VIII", "Henry the Eighth"),
+            ("Louis XIV", "louis 14"),
+            ("World War I", "World War One"),
+            ("World War II", "world war 2"),
+            ("4th", "fourth"),
+            ("Fourth Amendment", "4th Amendment"),
+            ("21st century", "twenty-first century"),
+            ("22nd", "twenty second"),
+            ("Beethoven's 9th Symphony", "Beethoven's Ninth Symphony"),
+            ("007", "7"),
+        ];
+        for (a, b) in same {
+            assert_eq!(normalize(a), normalize(b), "{a} = {b}");
+        }
+        assert_eq!(normalize("Henry VIII"), "henry 8");
+        assert_eq!(normalize("the fourth estate"), "4 estate");
+    }
+
+    #[test]
+    fn huge_numbers() {
+        // Ten hundreds is more than a u64 holds, so the number stops before the last one
+        let hundreds = ["hundred"; 10].join(" ");
+        assert_eq!(normalize(&hundreds), "1010101010101010100 100");
+        let millions = format!("{} million", ["hundred"; 9].join(" "));
+        assert_eq!(normalize(&millions), "1010101010101010100 1000000");
+        // However many there are
+        normalize(&vec!["hundred"; 1000].join(" "));
+        normalize(&vec!["ninety nine million"; 1000].join(" "));
+    }
+
+    #[test]
+    fn leaves_other_words_alone() {
+        // Roman numerals only count after the first word, and not for words like these
+        assert_eq!(normalize("I Robot"), "i robot");
+        assert_eq!(normalize("mix tape"), "mix tape");
+        assert_eq!(normalize("Washington DC"), "washington dc");
+        assert_eq!(normalize("Xi Jinping"), "xi jinping");
+        assert_eq!(normalize("mild civil"), "mild civil");
+        assert_eq!(normalize("Someone"), "someone");
+        assert_eq!(normalize("Twelve Angry Men"), "12 angry men");
+        // Separate numbers stay separate
+        assert_eq!(normalize("one two three"), "1 2 3");
+        assert_eq!(normalize("1812, 1813"), "1812 1813");
+    }
+}
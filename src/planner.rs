@@ -0,0 +1,198 @@
+/// Query planning: turning a set of leaves into QBReader requests
+///
+/// QBReader takes three independent lists (categories, subcategories and alternate
+/// subcategories), so not every set of leaves fits in one request. `Biology + Math`
+/// needs `alternateSubcategories=Math`, which would also knock out Biology (it has no
+/// alternate subcategory). The planner splits a set into the fewest requests that
+/// each pick out exactly their leaves, whether or not QBReader lets questions without
+/// a subcategory (or alternate) through a list they're not in.
+use std::collections::BTreeSet;
+
+use rand::Rng;
+
+use crate::query::{api_categories, CategorySet, Leaf, UNIVERSE};
+
+/// One QBReader request's worth of categories
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CategoryRequest {
+    pub categories: Vec<String>,
+    pub subcategories: Vec<String>,
+    pub alternate_subcategories: Vec<String>,
+    /// How many leaves it covers, which is how often it's drawn from
+    pub leaves: usize,
+}
+
+impl CategoryRequest {
+    fn new(leaves: &CategorySet) -> Self {
+        let (categories, subcategories, alternate_subcategories) = api_categories(leaves);
+        CategoryRequest {
+            categories,
+            subcategories,
+            alternate_subcategories,
+            leaves: leaves.len(),
+        }
+    }
+
+    /// The leaves a question has to be in to get through all three lists
+    pub fn covers(&self) -> CategorySet {
+        let allows = |names: &[String], name: Option<&str>| {
+            names.is_empty() || name.is_some_and(|name| names.iter().any(|n| n == name))
+        };
+        UNIVERSE
+            .iter()
+            .filter(|leaf| {
+                allows(&self.categories, Some(leaf.category))
+                    && allows(&self.subcategories, leaf.subcategory)
+                    && allows(&self.alternate_subcategories, leaf.alternate)
+            })
+            .copied()
+            .collect()
+    }
+
+    /// e.g. "Science › Other Science › Astronomy, Math"
+    pub fn describe(&self) -> String {
+        if self.categories.is_empty() {
+            return "Everything".to_string();
+        }
+        [
+            &self.categories,
+            &self.subcategories,
+            &self.alternate_subcategories,
+        ]
+        .iter()
+        .filter(|names| !names.is_empty())
+        .map(|names| names.join(", "))
+        .collect::<Vec<_>>()
+        .join(" › ")
+    }
+}
+
+/// Which of the lists a leaf needs to be picked out by, given everything else in `leaves`
+///
+/// Leaves needing the same lists can share a request. A request with subcategories can't
+/// safely include a leaf without one, and the same goes for alternate subcategories. An
+/// alternate isn't needed when the rest of its subcategory is coming along too.
+fn needs(leaf: &Leaf, leaves: &CategorySet) -> (bool, bool) {
+    let whole_subcategory = UNIVERSE
+        .iter()
+        .filter(|other| other.category == leaf.category && other.subcategory == leaf.subcategory)
+        .all(|other| leaves.contains(other));
+    (
+        leaf.subcategory.is_some(),
+        leaf.alternate.is_some() && !(leaf.subcategory.is_some() && whole_subcategory),
+    )
+}
+
+/// Every leaf in a category
+fn category_leaves(category: &str) -> impl Iterator<Item = &'static Leaf> + '_ {
+    UNIVERSE
+        .iter()
+        .filter(move |leaf| leaf.category == category)
+}
+
+/// The fewest requests whose questions are exactly `leaves`
+pub fn plan(leaves: &CategorySet) -> Vec<CategoryRequest> {
+    if leaves.is_empty() {
+        return Vec::new();
+    }
+    // QBReader picks from everything when it isn't given any categories
+    if *leaves == *UNIVERSE {
+        return vec![CategoryRequest {
+            leaves: leaves.len(),
+            ..Default::default()
+        }];
+    }
+    // Whole categories only need their name, whatever's inside them
+    let categories: BTreeSet<&str> = leaves.iter().map(|leaf| leaf.category).collect();
+    let (whole, partial): (Vec<&str>, Vec<&str>) = categories
+        .into_iter()
+        .partition(|category| category_leaves(category).all(|leaf| leaves.contains(leaf)));
+    let mut groups: Vec<((bool, bool), CategorySet)> = Vec::new();
+    for wanted in [(true, false), (true, true), (false, true)] {
+        let group: CategorySet = leaves
+            .iter()
+            .filter(|leaf| partial.contains(&leaf.category) && needs(leaf, leaves) == wanted)
+            .copied()
+            .collect();
+        if !group.is_empty() {
+            groups.push((wanted, group));
+        }
+    }
+    let request = |(wanted, group): &((bool, bool), CategorySet)| {
+        let request = CategoryRequest::new(group);
+        if wanted.1 {
+            request
+        } else {
+            CategoryRequest {
+                alternate_subcategories: Vec::new(),
+                ..request
+            }
+        }
+    };
+    let mut requests: Vec<_> = groups.iter().map(request).collect();
+    let whole_leaves: CategorySet = whole
+        .iter()
+        .flat_map(|category| category_leaves(category))
+        .copied()
+        .collect();
+    if whole_leaves.is_empty() {
+        return requests;
+    }
+    // Whole categories with subcategories can ride along with the partial ones that only
+    // need subcategories, by listing all of theirs
+    let fits = whole_leaves.iter().all(|leaf| leaf.subcategory.is_some());
+    match groups.first_mut() {
+        Some(((true, false), group)) if fits => {
+            group.extend(whole_leaves);
+            requests[0] = request(&groups[0]);
+        }
+        _ => {
+            let (categories, _, _) = api_categories(&whole_leaves);
+            requests.insert(
+                0,
+                CategoryRequest {
+                    categories,
+                    leaves: whole_leaves.len(),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+    requests
+}
+
+/// Split `number` questions by `weights`, as evenly as possible
+///
+/// Each gets its share rounded down, and whatever's left over goes to ones picked at
+/// random (weighted by how much they were rounded down). So 20 questions split 4/4/4/...
+/// is exactly that, and a single question goes to each one in proportion.
+pub fn apportion(weights: &[u64], number: u32, rng: &mut impl Rng) -> Vec<u32> {
+    let total: u64 = weights.iter().sum();
+    if total == 0 {
+        return vec![0; weights.len()];
+    }
+    let number = number as u64;
+    let mut counts: Vec<u32> = weights
+        .iter()
+        .map(|weight| (number * weight / total) as u32)
+        .collect();
+    let left_over = number as usize - counts.iter().map(|count| *count as usize).sum::<usize>();
+    if left_over > 0 {
+        let remainders: Vec<u64> = weights
+            .iter()
+            .map(|weight| number * weight % total)
+            .collect();
+        // There are always more non-zero remainders than questions left over
+        let extra = rand::seq::index::sample_weighted(
+            rng,
+            remainders.len(),
+            |index| remainders[index] as f64,
+            left_over,
+        )
+        .expect("remainders are valid weights");
+        for index in extra {
+            counts[index] += 1;
+        }
+    }
+    counts
+}
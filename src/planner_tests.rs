@@ -0,0 +1,153 @@
+#[cfg(test)]
+mod tests {
+    use crate::planner::*;
+    use crate::query::{
+        evaluate, parse_expr, split_filters, tokenize, CategorySet, Leaf, UNIVERSE,
+    };
+    use rand::SeedableRng;
+
+    fn set(s: &str) -> CategorySet {
+        let (categories, _) = split_filters(&parse_expr(&mut tokenize(s)).unwrap()).unwrap();
+        evaluate(&categories.unwrap()).unwrap()
+    }
+
+    /// Whether QBReader would send a question from `leaf` for `request`
+    ///
+    /// We don't rely on what it does with questions that don't have a subcategory (or
+    /// alternate) when it's given a list of them, so `lenient` lets them through and
+    /// strict doesn't. Plans have to be right either way.
+    fn passes(request: &CategoryRequest, leaf: &Leaf, lenient: bool) -> bool {
+        let allows = |names: &[String], name: Option<&str>| match name {
+            _ if names.is_empty() => true,
+            Some(name) => names.iter().any(|allowed| allowed == name),
+            None => lenient,
+        };
+        allows(&request.categories, Some(leaf.category))
+            && allows(&request.subcategories, leaf.subcategory)
+            && allows(&request.alternate_subcategories, leaf.alternate)
+    }
+
+    /// What a plan would actually get, under either model
+    fn covered(requests: &[CategoryRequest], lenient: bool) -> CategorySet {
+        UNIVERSE
+            .iter()
+            .filter(|leaf| {
+                requests
+                    .iter()
+                    .any(|request| passes(request, leaf, lenient))
+            })
+            .copied()
+            .collect()
+    }
+
+    const TERMS: &[&str] = &[
+        "Science",
+        "Biology",
+        "Other Science",
+        "Math",
+        "Literature",
+        "Poetry",
+        "History",
+        "Social Science",
+        "Economics",
+        "Religion",
+        "Current Events",
+    ];
+
+    #[test]
+    fn plans_are_exact() {
+        let mut queries: Vec<String> = TERMS.iter().map(|a| a.to_string()).collect();
+        for a in TERMS {
+            for b in TERMS {
+                queries.push(format!("{a} + {b}"));
+                queries.push(format!("{a} - {b}"));
+                queries.push(format!("-{a} - {b}"));
+            }
+        }
+        for query in queries {
+            let leaves = set(&query);
+            let requests = plan(&leaves);
+            for lenient in [false, true] {
+                assert_eq!(covered(&requests, lenient), leaves, "{query}");
+            }
+            // Nothing gets drawn twice
+            for leaf in &leaves {
+                let count = requests
+                    .iter()
+                    .filter(|request| passes(request, leaf, false))
+                    .count();
+                assert_eq!(count, 1, "{query}: {leaf:?}");
+            }
+            assert_eq!(
+                requests.iter().map(|request| request.leaves).sum::<usize>(),
+                leaves.len(),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn plans_are_small() {
+        let count = |s: &str| plan(&set(s)).len();
+        assert_eq!(count("Science"), 1);
+        assert_eq!(count("Science + History + Current Events"), 1);
+        assert_eq!(count("Biology + History"), 1);
+        assert_eq!(count("Biology + Other Science"), 1);
+        assert_eq!(count("Math + Astronomy"), 1);
+        assert_eq!(count("Economics + Psychology"), 1);
+        assert_eq!(count("*"), 1);
+        // Math needs the alternates list, which Biology isn't in
+        assert_eq!(count("Biology + Math"), 2);
+        assert_eq!(count("Science - Math"), 2);
+        // Religion has no subcategories to list alongside Biology
+        assert_eq!(count("Religion + Biology"), 2);
+        assert!(plan(&CategorySet::new()).is_empty());
+    }
+
+    #[test]
+    fn whole_categories_only_need_names() {
+        let requests = plan(&set("Science + History"));
+        assert_eq!(requests[0].categories, vec!["History", "Science"]);
+        assert!(requests[0].subcategories.is_empty());
+        assert!(requests[0].alternate_subcategories.is_empty());
+        // Riding along with a subcategory, they list theirs (but no alternates)
+        let requests = plan(&set("Science + European Literature"));
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0]
+            .subcategories
+            .contains(&"Other Science".to_string()));
+        assert!(requests[0].alternate_subcategories.is_empty());
+    }
+
+    #[test]
+    fn describe() {
+        let requests = plan(&set("Science - Math"));
+        assert_eq!(
+            requests
+                .iter()
+                .map(CategoryRequest::describe)
+                .collect::<Vec<_>>(),
+            vec![
+                "Science › Biology, Chemistry, Physics",
+                "Science › Other Science › Astronomy, Computer Science, Earth Science, Engineering, Misc Science",
+            ]
+        );
+        assert_eq!(plan(&set("All"))[0].describe(), "Everything");
+    }
+
+    #[test]
+    fn apportion_splits_evenly() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        assert_eq!(apportion(&[4, 4, 2], 20, &mut rng), vec![8, 8, 4]);
+        assert_eq!(apportion(&[1, 1], 0, &mut rng), vec![0, 0]);
+        assert_eq!(apportion(&[0, 0], 5, &mut rng), vec![0, 0]);
+        for number in 0..30 {
+            let counts = apportion(&[5, 3, 1], number, &mut rng);
+            assert_eq!(counts.iter().sum::<u32>(), number);
+            for (count, weight) in counts.iter().zip([5, 3, 1]) {
+                let share = number * weight / 9;
+                assert!(*count == share || *count == share + 1, "{counts:?}");
+            }
+        }
+    }
+}
@@ -1,8 +1,11 @@
 /// QBReader API client
+use std::future::Future;
+
+use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
-use crate::query::ApiQuery;
+use crate::query::{ApiQuery, TextSearch};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Packet {
@@ -45,11 +48,70 @@ pub struct Tossup {
 pub struct Tossups {
     pub tossups: Vec<Tossup>,
 }
-pub async fn random_tossup(
-    reqwest: &reqwest::Client,
-    api_params: &ApiQuery,
-) -> Result<Tossups, reqwest::Error> {
-    let mut url = Url::parse("https://www.qbreader.org/api/random-tossup").unwrap();
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Bonus {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub leadin: String,
+    pub parts: Vec<String>,
+    pub answers: Vec<String>,
+    pub category: String,
+    pub subcategory: String,
+    pub packet: Packet,
+    pub set: Set,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    pub difficulty: u8,
+    pub number: u32,
+    // Only some sets record point values, otherwise every part is worth 10
+    #[serde(default)]
+    pub values: Option<Vec<u32>>,
+    #[serde(rename = "leadin_sanitized")]
+    pub leadin_sanitized: String,
+    #[serde(rename = "parts_sanitized")]
+    pub parts_sanitized: Vec<String>,
+    #[serde(rename = "answers_sanitized")]
+    pub answers_sanitized: Vec<String>,
+}
+
+impl Bonus {
+    /// Point value of the given part (defaults to 10)
+    pub fn value(&self, part: usize) -> u32 {
+        self.values
+            .as_ref()
+            .and_then(|values| values.get(part).copied())
+            .unwrap_or(10)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Bonuses {
+    pub bonuses: Vec<Bonus>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SetList {
+    #[serde(rename = "setList")]
+    pub set_list: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NumPackets {
+    #[serde(rename = "numPackets")]
+    pub num_packets: u32,
+}
+
+fn endpoint_url(endpoint: &str) -> Url {
+    Url::parse("https://www.qbreader.org/api/")
+        .unwrap()
+        .join(endpoint)
+        .unwrap()
+}
+
+/// Build a QBReader API url with the category filters applied
+pub fn api_url(endpoint: &str, api_params: &ApiQuery) -> Url {
+    let mut url = endpoint_url(endpoint);
     for category in &api_params.categories {
         url.query_pairs_mut().append_pair("categories", category);
     }
@@ -63,8 +125,185 @@ pub async fn random_tossup(
     }
     url.query_pairs_mut()
         .append_pair("number", &api_params.number.to_string());
+    if !api_params.difficulties.is_empty() {
+        let difficulties: Vec<_> = api_params
+            .difficulties
+            .iter()
+            .map(|difficulty| difficulty.to_string())
+            .collect();
+        url.query_pairs_mut()
+            .append_pair("difficulties", &difficulties.join(","));
+    }
+    if let Some(min_year) = api_params.min_year {
+        url.query_pairs_mut()
+            .append_pair("minYear", &min_year.to_string());
+    }
+    if let Some(max_year) = api_params.max_year {
+        url.query_pairs_mut()
+            .append_pair("maxYear", &max_year.to_string());
+    }
+    if api_params.standard_only {
+        url.query_pairs_mut().append_pair("standardOnly", "true");
+    }
+    url
+}
+
+/// Search results for one kind of question
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchResults<T> {
+    pub count: u32,
+    #[serde(rename = "questionArray")]
+    pub question_array: Vec<T>,
+}
+
+/// What the search endpoint (`/api/query`) sends back
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchResponse {
+    #[serde(default)]
+    pub tossups: Option<SearchResults<Tossup>>,
+    #[serde(default)]
+    pub bonuses: Option<SearchResults<Bonus>>,
+}
+
+/// Url for a random sample of the questions matching a text search
+///
+/// `question_type` is "tossup" or "bonus".
+pub fn search_url(question_type: &str, api_params: &ApiQuery, search: &TextSearch) -> Url {
+    let mut url = api_url("query", api_params);
+    url.query_pairs_mut()
+        .append_pair("queryString", &search.text)
+        .append_pair("searchType", search.field.as_str())
+        .append_pair("questionType", question_type)
+        .append_pair("randomize", "true")
+        .append_pair("maxReturnLength", &api_params.number.to_string());
+    url
+}
+
+pub async fn random_tossup(
+    reqwest: &reqwest::Client,
+    api_params: &ApiQuery,
+) -> Result<Tossups, reqwest::Error> {
+    if let Some(search) = &api_params.search {
+        let url = search_url("tossup", api_params, search);
+        let response = reqwest.get(url).send().await?;
+        let response = response.json::<SearchResponse>().await?;
+        return Ok(Tossups {
+            tossups: response
+                .tossups
+                .map(|results| results.question_array)
+                .unwrap_or_default(),
+        });
+    }
+    let url = api_url("random-tossup", api_params);
 
     let response = reqwest.get(url).send().await?;
     let response = response.json::<Tossups>().await?;
     Ok(response)
 }
+
+pub async fn random_bonus(
+    reqwest: &reqwest::Client,
+    api_params: &ApiQuery,
+) -> Result<Bonuses, reqwest::Error> {
+    if let Some(search) = &api_params.search {
+        let url = search_url("bonus", api_params, search);
+        let response = reqwest.get(url).send().await?;
+        let response = response.json::<SearchResponse>().await?;
+        return Ok(Bonuses {
+            bonuses: response
+                .bonuses
+                .map(|results| results.question_array)
+                .unwrap_or_default(),
+        });
+    }
+    let mut url = api_url("random-bonus", api_params);
+    // We only know how to read standard three-part bonuses
+    url.query_pairs_mut()
+        .append_pair("threePartBonuses", "true");
+
+    let response = reqwest.get(url).send().await?;
+    let response = response.json::<Bonuses>().await?;
+    Ok(response)
+}
+
+/// Questions for a query, drawing from each request in its plan separately
+///
+/// `fetch` gets one request's worth of questions. The requests are shuffled together
+/// afterwards so the categories come up mixed rather than in runs.
+async fn sample<T, F, Fut>(api_params: &ApiQuery, fetch: F) -> Result<Vec<T>, reqwest::Error>
+where
+    F: Fn(ApiQuery) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, reqwest::Error>>,
+{
+    if api_params.parts.is_empty() {
+        return fetch(api_params.clone()).await;
+    }
+    let counts = api_params.draw(&mut rand::thread_rng());
+    let mut questions = Vec::new();
+    for (part, counts) in api_params.parts.iter().zip(counts) {
+        for (request, count) in part.requests.iter().zip(counts) {
+            if count > 0 {
+                questions.extend(fetch(api_params.for_request(request, count)).await?);
+            }
+        }
+    }
+    questions.shuffle(&mut rand::thread_rng());
+    Ok(questions)
+}
+
+/// Random tossups, in the proportions of a weighted query
+pub async fn sample_tossups(
+    reqwest: &reqwest::Client,
+    api_params: &ApiQuery,
+) -> Result<Vec<Tossup>, reqwest::Error> {
+    sample(api_params, |params| async move {
+        Ok(random_tossup(reqwest, &params).await?.tossups)
+    })
+    .await
+}
+
+/// Random bonuses, in the proportions of a weighted query
+pub async fn sample_bonuses(
+    reqwest: &reqwest::Client,
+    api_params: &ApiQuery,
+) -> Result<Vec<Bonus>, reqwest::Error> {
+    sample(api_params, |params| async move {
+        Ok(random_bonus(reqwest, &params).await?.bonuses)
+    })
+    .await
+}
+
+/// Url for every tossup in a packet
+pub fn packet_tossups_url(set_name: &str, packet_number: u32) -> Url {
+    let mut url = endpoint_url("packet-tossups");
+    url.query_pairs_mut()
+        .append_pair("setName", set_name)
+        .append_pair("packetNumber", &packet_number.to_string());
+    url
+}
+
+/// Every tossup in a packet, in the order they're read
+pub async fn packet_tossups(
+    reqwest: &reqwest::Client,
+    set_name: &str,
+    packet_number: u32,
+) -> Result<Tossups, reqwest::Error> {
+    let url = packet_tossups_url(set_name, packet_number);
+    let mut response = reqwest.get(url).send().await?.json::<Tossups>().await?;
+    response.tossups.sort_by_key(|tossup| tossup.number);
+    Ok(response)
+}
+
+/// The names of every set QBReader has
+pub async fn set_list(reqwest: &reqwest::Client) -> Result<Vec<String>, reqwest::Error> {
+    let response = reqwest.get(endpoint_url("set-list")).send().await?;
+    Ok(response.json::<SetList>().await?.set_list)
+}
+
+/// How many packets a set has (0 if there's no such set)
+pub async fn num_packets(reqwest: &reqwest::Client, set_name: &str) -> Result<u32, reqwest::Error> {
+    let mut url = endpoint_url("num-packets");
+    url.query_pairs_mut().append_pair("setName", set_name);
+    let response = reqwest.get(url).send().await?;
+    Ok(response.json::<NumPackets>().await?.num_packets)
+}
@@ -140,6 +140,7 @@ mod tests {
             subcategories: vec![],
             alternate_subcategories: vec![],
             number: 1,
+            ..Default::default()
         };
 
         let mut url = Url::parse("https://www.qbreader.org/api/random-tossup").unwrap();
@@ -163,6 +164,7 @@ mod tests {
             subcategories: vec!["Biology".to_string(), "Chemistry".to_string()],
             alternate_subcategories: vec!["Math".to_string()],
             number: 3,
+            ..Default::default()
         };
 
         let mut url = Url::parse("https://www.qbreader.org/api/random-tossup").unwrap();
@@ -197,6 +199,7 @@ mod tests {
             subcategories: vec![],
             alternate_subcategories: vec![],
             number: 1,
+            ..Default::default()
         };
 
         let mut url = Url::parse("https://www.qbreader.org/api/random-tossup").unwrap();
@@ -218,6 +221,7 @@ mod tests {
             subcategories: vec!["American Literature".to_string()],
             alternate_subcategories: vec![],
             number: 1,
+            ..Default::default()
         };
 
         let mut url = Url::parse("https://www.qbreader.org/api/random-tossup").unwrap();
@@ -282,4 +286,172 @@ mod tests {
         assert_eq!(tossup.set.year, 2023);
         assert_eq!(tossup.packet.number, 1);
     }
+
+    #[test]
+    fn test_api_url_helper() {
+        let api_params = ApiQuery {
+            categories: vec!["Science".to_string()],
+            subcategories: vec!["Biology".to_string()],
+            alternate_subcategories: vec![],
+            number: 2,
+            ..Default::default()
+        };
+
+        let url = api_url("random-bonus", &api_params);
+        let url_str = url.as_str();
+        assert!(url_str.starts_with("https://www.qbreader.org/api/random-bonus?"));
+        assert!(url_str.contains("categories=Science"));
+        assert!(url_str.contains("subcategories=Biology"));
+        assert!(!url_str.contains("alternateSubcategories"));
+        assert!(url_str.contains("number=2"));
+    }
+
+    #[test]
+    fn test_bonus_deserialization_from_api_format() {
+        let json_response = r#"
+        {
+            "bonuses": [
+                {
+                    "_id": "64f1b2c3d4e5f6a7b8c9d0e1",
+                    "leadin": "This element is essential to life. For 10 points each:",
+                    "leadin_sanitized": "This element is essential to life. For 10 points each:",
+                    "parts": ["Name this element with atomic number 6.", "Name this allotrope of it.", "Name this other allotrope."],
+                    "parts_sanitized": ["Name this element with atomic number 6.", "Name this allotrope of it.", "Name this other allotrope."],
+                    "answers": ["<b>carbon</b>", "<b>diamond</b>", "<b>graphite</b>"],
+                    "answers_sanitized": ["carbon", "diamond", "graphite"],
+                    "category": "Science",
+                    "subcategory": "Chemistry",
+                    "packet": {
+                        "_id": "packet123",
+                        "name": "Test Packet 1",
+                        "number": 1
+                    },
+                    "set": {
+                        "_id": "set456",
+                        "name": "Test Tournament 2023",
+                        "year": 2023,
+                        "standard": true
+                    },
+                    "updatedAt": "2023-01-15T10:30:00.000Z",
+                    "difficulty": 3,
+                    "number": 4
+                }
+            ]
+        }
+        "#;
+
+        let parsed: Bonuses = serde_json::from_str(json_response).unwrap();
+        assert_eq!(parsed.bonuses.len(), 1);
+
+        let bonus = &parsed.bonuses[0];
+        assert_eq!(bonus.parts.len(), 3);
+        assert_eq!(bonus.answers_sanitized[1], "diamond");
+        assert_eq!(bonus.category, "Science");
+        assert!(bonus.values.is_none());
+        assert_eq!(bonus.value(0), 10);
+    }
+
+    #[test]
+    fn test_bonus_explicit_values() {
+        let json = r#"{
+            "_id": "id", "leadin": "", "leadin_sanitized": "",
+            "parts": ["a", "b", "c"], "parts_sanitized": ["a", "b", "c"],
+            "answers": ["a", "b", "c"], "answers_sanitized": ["a", "b", "c"],
+            "category": "History", "subcategory": "World History",
+            "packet": {"_id": "p", "name": "P", "number": 1},
+            "set": {"_id": "s", "name": "S", "year": 2020, "standard": false},
+            "updatedAt": "", "difficulty": 5, "number": 1,
+            "values": [10, 10, 15]
+        }"#;
+        let bonus: Bonus = serde_json::from_str(json).unwrap();
+        assert_eq!(bonus.value(2), 15);
+        // Out of range parts fall back to 10
+        assert_eq!(bonus.value(3), 10);
+    }
+
+    #[test]
+    fn test_packet_tossups_url() {
+        let url = packet_tossups_url("2023 ACF Regionals", 3);
+        assert_eq!(
+            url.as_str(),
+            "https://www.qbreader.org/api/packet-tossups?setName=2023+ACF+Regionals&packetNumber=3"
+        );
+    }
+
+    #[test]
+    fn test_set_list_deserialization() {
+        let json = r#"{"setList": ["2023 ACF Regionals", "2022 PACE NSC"]}"#;
+        let sets: SetList = serde_json::from_str(json).unwrap();
+        assert_eq!(sets.set_list, vec!["2023 ACF Regionals", "2022 PACE NSC"]);
+    }
+
+    #[test]
+    fn test_num_packets_deserialization() {
+        let packets: NumPackets = serde_json::from_str(r#"{"numPackets": 12}"#).unwrap();
+        assert_eq!(packets.num_packets, 12);
+    }
+
+    #[test]
+    fn test_api_url_filters() {
+        let api_params = ApiQuery {
+            difficulties: vec![3, 4, 5],
+            min_year: Some(2018),
+            max_year: Some(2020),
+            standard_only: true,
+            ..Default::default()
+        };
+        let url = api_url("random-tossup", &api_params);
+        let url_str = url.as_str();
+        assert!(url_str.contains("difficulties=3%2C4%2C5"), "{}", url_str);
+        assert!(url_str.contains("minYear=2018"));
+        assert!(url_str.contains("maxYear=2020"));
+        assert!(url_str.contains("standardOnly=true"));
+    }
+
+    #[test]
+    fn test_api_url_no_filters() {
+        let url = api_url("random-tossup", &ApiQuery::default());
+        let url_str = url.as_str();
+        assert!(!url_str.contains("difficulties"));
+        assert!(!url_str.contains("Year"));
+        assert!(!url_str.contains("standardOnly"));
+    }
+
+    #[test]
+    fn test_search_url() {
+        use crate::query::{SearchField, TextSearch};
+        let search = TextSearch {
+            text: "Henry VIII".to_string(),
+            field: SearchField::Answer,
+        };
+        let api_params = ApiQuery {
+            categories: vec!["History".to_string()],
+            number: 5,
+            search: Some(search.clone()),
+            ..Default::default()
+        };
+        let url = search_url("tossup", &api_params, &search);
+        let url_str = url.as_str();
+        assert!(url_str.starts_with("https://www.qbreader.org/api/query?"));
+        assert!(url_str.contains("categories=History"));
+        assert!(url_str.contains("queryString=Henry+VIII"));
+        assert!(url_str.contains("searchType=answer"));
+        assert!(url_str.contains("questionType=tossup"));
+        assert!(url_str.contains("randomize=true"));
+        assert!(url_str.contains("maxReturnLength=5"));
+    }
+
+    #[test]
+    fn test_search_response_deserialization() {
+        let json = r#"{
+            "tossups": {"count": 0, "questionArray": []},
+            "bonuses": {"count": 0, "questionArray": []}
+        }"#;
+        let response: SearchResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.tossups.unwrap().count, 0);
+        // Only asking for tossups might leave out bonuses entirely
+        let response: SearchResponse =
+            serde_json::from_str(r#"{"tossups": {"count": 0, "questionArray": []}}"#).unwrap();
+        assert!(response.bonuses.is_none());
+    }
 }
@@ -5,11 +5,13 @@
 ///
 /// # Supported Operators (in order of precedence, highest first):
 /// - Parentheses `()` - grouping expressions
+/// - Unary minus `-A` or `!A` - everything except `A`
 /// - Minus `-` - subtraction/exclusion (left-associative)
 /// - And `&` - intersection (left-associative)
 /// - Or `+` - union (left-associative)
 ///
 /// # Category Matching:
+/// - Aliases like `bio`, `am lit` or `rmp` are checked first (see `ALIASES`)
 /// - Categories are matched case-insensitively with automatic capitalization
 /// - Multi-word categories are supported (e.g., "American Literature")
 /// - Subcategories within the same main category can be combined
@@ -18,24 +20,49 @@
 /// # Examples:
 /// - `Biology` - All biology questions
 /// - `Science + History` - All science OR history questions
-/// - `Biology & Chemistry` - All questions that are both biology AND chemistry
+/// - `Science & (Biology + Math)` - Science questions that are biology or math
+/// - `-Pop Culture - Current Events` - Everything except pop culture and current events
+/// - `*` or `All` - Every category
 /// - `Science - Math` - All science questions EXCEPT math questions
 /// - `(Biology + Chemistry) - Math` - Biology or chemistry questions, but exclude math
 /// - `Science & (Biology + Chemistry)` - Science questions that are biology or chemistry
+/// - `Biology & difficulty:3-5 & year:>=2018` - Biology questions from recent, mid-difficulty sets
+///
+/// # Saved Queries:
+/// `@name` stands for a query saved with `/savequery` (the user's own, or one shared with
+/// the whole server), e.g. `@mysci + History`. Saved queries can use other saved queries,
+/// just not themselves (however indirectly).
+///
+/// # Filters:
+/// `key:value` terms filter on things other than category. QBReader applies them to the
+/// whole request, so they can only be combined with the rest of the query using `&`.
+/// - `difficulty:3`, `difficulty:3-5`, `difficulty:2,4`, `difficulty:>=6`
+/// - `year:2018`, `year:2015-2018`, `year:>=2018`, `year:<2010`
+/// - `standard:true` (only standard sets; `standard:false` allows any set, the default)
+/// - `"photosynthesis"` or `question:"photosynthesis"` (question text), `answer:"Napoleon"`
+///   (answer lines). Only one text search is allowed per query.
+///
+/// # Set Semantics:
+/// Every expression is evaluated as a set of (category, subcategory, alternate subcategory)
+/// leaves taken from `CATEGORIES`, so `-` always means "in the first but not the second",
+/// however the two sides overlap.
 ///
 /// # Error Handling:
 /// - Invalid categories are rejected with helpful error messages
-/// - Impossible queries (e.g., conflicting categories) are detected
+/// - Impossible queries (e.g., `Biology & Chemistry`, which no question is) are detected
 /// - Syntax errors provide context about unexpected tokens
-///
-/// I need to fix subtraction though; for how it's currently implemented, it's fundamentaly broken
 use phf::phf_map;
+use rand::Rng;
 use rapidfuzz::distance::levenshtein;
-use std::{collections::VecDeque, fmt};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::sync::LazyLock;
 use tracing::debug;
 
+use crate::planner::{apportion, plan, CategoryRequest};
+use crate::simplify::set_expr;
+
 /// category -> (subcategories, alternate subcategories)
-/// TODO: aliases
 pub static CATEGORIES: phf::Map<&'static str, (&'static [&'static str], &'static [&'static str])> = phf_map! {
     "Literature" => (&[
         "American Literature", "British Literature", "Classical Literature",
@@ -71,29 +98,337 @@ pub static CATEGORIES: phf::Map<&'static str, (&'static [&'static str], &'static
     ], &[]),
 };
 
+/// Community shorthand (lowercase) -> the categories, subcategories or alternate
+/// subcategories it stands for
+///
+/// These are checked before fuzzy matching, so short ones like "bio" don't have to be
+/// within `FUZZY_THRESHOLD` of anything.
+pub static ALIASES: phf::Map<&'static str, &'static [&'static str]> = phf_map! {
+    // Categories
+    "lit" => &["Literature"],
+    "hist" => &["History"],
+    "sci" => &["Science"],
+    "fa" => &["Fine Arts"],
+    "arts" => &["Fine Arts"],
+    "myth" => &["Mythology"],
+    "philo" => &["Philosophy"],
+    "rmp" => &["Religion", "Mythology", "Philosophy"],
+    "ss" => &["Social Science"],
+    "socsci" => &["Social Science"],
+    "ce" => &["Current Events"],
+    "geo" => &["Geography"],
+    "oa" => &["Other Academic"],
+    "trash" => &["Pop Culture"],
+    "pop" => &["Pop Culture"],
+    // Literature
+    "am lit" => &["American Literature"],
+    "brit lit" => &["British Literature"],
+    "euro lit" => &["European Literature"],
+    "world lit" => &["World Literature"],
+    "classics" => &["Classical Literature"],
+    // History
+    "am hist" => &["American History"],
+    "euro hist" => &["European History"],
+    "world hist" => &["World History"],
+    "ancient" => &["Ancient History"],
+    "euro" => &["European Literature", "European History"],
+    // Science
+    "bio" => &["Biology"],
+    "chem" => &["Chemistry"],
+    "phys" => &["Physics"],
+    "physics" => &["Physics"],
+    "cs" => &["Computer Science"],
+    "compsci" => &["Computer Science"],
+    "astro" => &["Astronomy"],
+    "earth sci" => &["Earth Science"],
+    // Fine Arts
+    "visual" => &["Visual Fine Arts"],
+    "painting" => &["Visual Fine Arts"],
+    "auditory" => &["Auditory Fine Arts"],
+    "classical music" => &["Auditory Fine Arts"],
+    // Social Science
+    "econ" => &["Economics"],
+    "psych" => &["Psychology"],
+    "anthro" => &["Anthropology"],
+    "ling" => &["Linguistics"],
+};
+
+/// Aliases that stand for `name` (or include it)
+pub fn aliases_for(name: &str) -> Vec<&'static str> {
+    let mut aliases: Vec<_> = ALIASES
+        .entries()
+        .filter(|(_, names)| names.contains(&name))
+        .map(|(alias, _)| *alias)
+        .collect();
+    aliases.sort();
+    aliases
+}
+
 /// Abstract Syntax Tree for the query language
 ///
 /// Represents the parsed structure of a query with proper operator precedence.
 /// The tree is evaluated bottom-up to produce category filters for the API.
 #[derive(Debug, Clone)]
 pub enum Expr {
-    /// A category or subcategory name (e.g., "Biology", "American Literature")
-    Token(String),
+    /// A category or subcategory name (e.g., "Biology", "American Literature"), and where it was
+    Token(String, Span),
     /// Logical AND - intersection of two expressions (higher precedence than OR)
     And(Box<Expr>, Box<Expr>),
     /// Logical OR - union of two expressions (lowest precedence)
     Or(Box<Expr>, Box<Expr>),
     /// Logical NOT - subtraction/exclusion of second expression from first (highest precedence after parentheses)
     Not(Box<Expr>, Box<Expr>), // A - B
+    /// Everything except the expression (e.g., "-Pop Culture" or "!Trash")
+    Complement(Box<Expr>),
+    /// Every category (`*` or `All`)
+    All,
+    /// A part of a weighted query (e.g., "Science*3"), drawn from that much more often
+    Weighted(Box<Expr>, u32),
+    /// A `key:value` filter (e.g., "difficulty:3-5")
+    Filter(Filter),
+    /// A reference to a saved query (e.g., "@mysci"), until it's expanded
+    Saved(String, Span),
+}
+
+/// Lowest and highest difficulty QBReader uses
+pub const DIFFICULTIES: std::ops::RangeInclusive<u8> = 1..=10;
+
+/// A filter on something other than category
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Any of these difficulties
+    Difficulty(Vec<u8>),
+    /// Sets from between these years (inclusive)
+    Year { min: Option<u32>, max: Option<u32> },
+    /// Only standard sets if true
+    Standard(bool),
+    /// Questions whose text (or answer) contains something
+    Text(TextSearch),
+}
+
+/// Which part of a question to search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchField {
+    Question,
+    Answer,
+}
+
+impl SearchField {
+    /// QBReader's name for it (`searchType`)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SearchField::Question => "question",
+            SearchField::Answer => "answer",
+        }
+    }
+}
+
+/// A full-text search (which needs QBReader's search endpoint)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSearch {
+    pub text: String,
+    pub field: SearchField,
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Difficulty(difficulties) => {
+                let contiguous = difficulties.windows(2).all(|pair| pair[1] == pair[0] + 1);
+                match (difficulties.first(), difficulties.last()) {
+                    (Some(first), Some(last)) if contiguous && first != last => {
+                        write!(f, "difficulty:{}-{}", first, last)
+                    }
+                    _ => write!(
+                        f,
+                        "difficulty:{}",
+                        difficulties
+                            .iter()
+                            .map(|difficulty| difficulty.to_string())
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ),
+                }
+            }
+            Filter::Year { min, max } => match (min, max) {
+                (Some(min), Some(max)) if min == max => write!(f, "year:{}", min),
+                (Some(min), Some(max)) => write!(f, "year:{}-{}", min, max),
+                (Some(min), None) => write!(f, "year:>={}", min),
+                (None, Some(max)) => write!(f, "year:<={}", max),
+                (None, None) => write!(f, "year:any"),
+            },
+            Filter::Standard(standard) => write!(f, "standard:{}", standard),
+            Filter::Text(TextSearch {
+                text,
+                field: SearchField::Question,
+            }) => write!(f, "\"{}\"", text),
+            Filter::Text(TextSearch {
+                text,
+                field: SearchField::Answer,
+            }) => write!(f, "answer:\"{}\"", text),
+        }
+    }
+}
+
+/// The text inside a quoted term, or `None` if the quotes don't match up
+fn unquote(term: &str) -> Option<&str> {
+    let text = term.strip_prefix('"')?.strip_suffix('"')?;
+    // Nothing to search for
+    (!text.trim().is_empty()).then(|| text.trim())
+}
+
+/// Parse a quoted text search term, like `"photosynthesis"`
+fn parse_text_search(term: &str, field: SearchField) -> Result<Filter, QueryError> {
+    let text = if term.starts_with('"') {
+        // An unclosed quote means the query ended early
+        if term.len() < 2 || !term.ends_with('"') {
+            return Err(QueryError::UnexpectedEOF);
+        }
+        unquote(term).ok_or_else(|| QueryError::InvalidFilter(term.to_string()))?
+    } else {
+        term.trim()
+    };
+    if text.is_empty() {
+        return Err(QueryError::InvalidFilter(term.to_string()));
+    }
+    Ok(Filter::Text(TextSearch {
+        text: text.to_string(),
+        field,
+    }))
+}
+
+/// A bound like `>=2018` or `<5`, as an inclusive (min, max)
+fn parse_bound(value: &str) -> Option<(Option<u32>, Option<u32>)> {
+    // Longer operators first so ">=" isn't read as ">"
+    let (op, number) = [">=", "<=", ">", "<", "="]
+        .iter()
+        .find_map(|op| Some((*op, value.strip_prefix(op)?)))
+        .unwrap_or(("=", value));
+    let number: u32 = number.trim().parse().ok()?;
+    Some(match op {
+        ">=" => (Some(number), None),
+        "<=" => (None, Some(number)),
+        ">" => (Some(number.checked_add(1)?), None),
+        "<" => (None, Some(number.checked_sub(1)?)),
+        _ => (Some(number), Some(number)),
+    })
+}
+
+/// A single value, range (`3-5`) or bound (`>=3`), as an inclusive (min, max)
+fn parse_range(value: &str) -> Option<(Option<u32>, Option<u32>)> {
+    if let Some((low, high)) = value.split_once('-') {
+        let low = low.trim().parse().ok()?;
+        let high = high.trim().parse().ok()?;
+        return (low <= high).then_some((Some(low), Some(high)));
+    }
+    parse_bound(value)
+}
+
+/// Built-in weighted queries for practicing with a realistic category mix, per 20 tossups
+pub static PRESETS: phf::Map<&'static str, &'static str> = phf_map! {
+    // ACF regular: 4/4/4 lit/hist/sci, 3 fine arts, then a question each of the rest. Geography,
+    // current events and other academic only get one between them, so they're grouped under a
+    // single weight (written flat, they'd get one each and the mix would be out of 22)
+    "acf" => "Literature*4 + History*4 + Science*4 + Fine Arts*3 + Religion + Mythology \
+        + Philosophy + Social Science + (Geography + Current Events + Other Academic)*1",
+    // NAQT: a bit less fine arts, plus current events, geography and pop culture
+    "naqt" => "Literature*4 + History*4 + Science*4 + Fine Arts*2 \
+        + (Religion + Mythology + Philosophy)*2 + Social Science + Geography + Current Events \
+        + Pop Culture",
+};
+
+/// Expand a `preset:name` term
+fn parse_preset(name: &str) -> Result<Expr, QueryError> {
+    let preset = PRESETS
+        .get(name.trim().to_lowercase().as_str())
+        .ok_or_else(|| QueryError::InvalidFilter(format!("preset:{}", name)))?;
+    parse_expr(&mut tokenize(preset))
+}
+
+/// Parse a `key:value` term
+pub fn parse_filter(term: &str) -> Result<Filter, QueryError> {
+    let invalid = || QueryError::InvalidFilter(term.to_string());
+    let (key, value) = term.split_once(':').ok_or_else(invalid)?;
+    let value = value.trim();
+    match key.trim().to_lowercase().as_str() {
+        "difficulty" | "diff" => {
+            let mut difficulties = Vec::new();
+            for part in value.split(',') {
+                let (min, max) = parse_range(part).ok_or_else(invalid)?;
+                let min = min.unwrap_or(*DIFFICULTIES.start() as u32);
+                let max = max.unwrap_or(*DIFFICULTIES.end() as u32);
+                difficulties.extend(
+                    DIFFICULTIES.filter(|difficulty| (min..=max).contains(&(*difficulty as u32))),
+                );
+            }
+            difficulties.sort();
+            difficulties.dedup();
+            if difficulties.is_empty() {
+                return Err(invalid());
+            }
+            Ok(Filter::Difficulty(difficulties))
+        }
+        "year" => {
+            let (min, max) = parse_range(value).ok_or_else(invalid)?;
+            Ok(Filter::Year { min, max })
+        }
+        "question" | "text" => parse_text_search(value, SearchField::Question),
+        "answer" => parse_text_search(value, SearchField::Answer),
+        "standard" => match value.to_lowercase().as_str() {
+            "true" | "yes" => Ok(Filter::Standard(true)),
+            "false" | "no" => Ok(Filter::Standard(false)),
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+impl Expr {
+    /// How tightly it binds, for working out where parentheses are needed
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Or(..) => 1,
+            Expr::And(..) => 2,
+            Expr::Not(..) => 3,
+            Expr::Weighted(..) => 4,
+            Expr::Complement(_) => 5,
+            Expr::Token(..) | Expr::All | Expr::Filter(_) | Expr::Saved(..) => 6,
+        }
+    }
+}
+
+/// `expr`, in parentheses if it binds looser than `precedence`
+struct Operand<'a>(&'a Expr, u8);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Operand(expr, precedence) = *self;
+        if expr.precedence() < precedence {
+            write!(f, "({})", expr)
+        } else {
+            write!(f, "{}", expr)
+        }
+    }
 }
 
+/// The query as it'd be typed, with only the parentheses it needs
+///
+/// Operators are left-associative, so the right side of one needs parentheses if it's
+/// the same operator: `A - (B - C)`.
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = self.precedence();
         match self {
-            Expr::Token(t) => write!(f, "{}", t),
-            Expr::And(a, b) => write!(f, "({} & {})", a, b),
-            Expr::Or(a, b) => write!(f, "({} + {})", a, b),
-            Expr::Not(a, b) => write!(f, "({} - {})", a, b),
+            Expr::Token(t, _) => write!(f, "{}", t),
+            Expr::And(a, b) => write!(f, "{} & {}", Operand(a, p), Operand(b, p + 1)),
+            Expr::Or(a, b) => write!(f, "{} + {}", Operand(a, p), Operand(b, p + 1)),
+            Expr::Not(a, b) => write!(f, "{} - {}", Operand(a, p), Operand(b, p + 1)),
+            // These only go on a single term (or something in parentheses)
+            Expr::Complement(a) => write!(f, "-{}", Operand(a, 5)),
+            Expr::Weighted(a, weight) => write!(f, "{}*{}", Operand(a, 5), weight),
+            Expr::All => write!(f, "All"),
+            Expr::Filter(filter) => write!(f, "{}", filter),
+            Expr::Saved(name, _) => write!(f, "@{}", name),
         }
     }
 }
@@ -102,7 +437,7 @@ impl fmt::Display for Expr {
 ///
 /// This structure maps the logical query to the specific API parameters
 /// needed by the QBReader API for filtering questions.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ApiQuery {
     /// Main categories to include (e.g., ["Science", "History"])
     pub categories: Vec<String>,
@@ -112,6 +447,28 @@ pub struct ApiQuery {
     pub alternate_subcategories: Vec<String>,
     /// Number of questions to retrieve
     pub number: u32,
+    /// Difficulties to include (empty means any)
+    pub difficulties: Vec<u8>,
+    /// Earliest set year to include
+    pub min_year: Option<u32>,
+    /// Latest set year to include
+    pub max_year: Option<u32>,
+    /// Only include standard sets
+    pub standard_only: bool,
+    /// Full-text search, which switches to QBReader's search endpoint
+    pub search: Option<TextSearch>,
+    /// The parts to draw from separately, for weighted queries and ones that don't fit
+    /// in a single request (empty otherwise, and the lists above are the request)
+    pub parts: Vec<QueryPart>,
+}
+
+/// One part of a query (e.g., `Science*3`) and the requests it takes
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryPart {
+    /// What the user wrote for this part (empty if the query wasn't weighted)
+    pub label: String,
+    pub weight: u32,
+    pub requests: Vec<CategoryRequest>,
 }
 
 impl Default for ApiQuery {
@@ -121,58 +478,354 @@ impl Default for ApiQuery {
             subcategories: Vec::new(),
             alternate_subcategories: Vec::new(),
             number: 1,
+            difficulties: Vec::new(),
+            min_year: None,
+            max_year: None,
+            standard_only: false,
+            search: None,
+            parts: Vec::new(),
+        }
+    }
+}
+
+impl ApiQuery {
+    /// The same query (filters and all), just for one request
+    pub fn for_request(&self, request: &CategoryRequest, number: u32) -> ApiQuery {
+        ApiQuery {
+            categories: request.categories.clone(),
+            subcategories: request.subcategories.clone(),
+            alternate_subcategories: request.alternate_subcategories.clone(),
+            number,
+            parts: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// How many of `self.number` questions to draw from each request of each part
+    ///
+    /// Parts are split by weight, then each part's requests by how many leaves they cover.
+    pub fn draw(&self, rng: &mut impl Rng) -> Vec<Vec<u32>> {
+        let weights: Vec<u64> = self.parts.iter().map(|part| part.weight as u64).collect();
+        let counts = apportion(&weights, self.number, rng);
+        self.parts
+            .iter()
+            .zip(counts)
+            .map(|(part, count)| {
+                let leaves: Vec<u64> = part
+                    .requests
+                    .iter()
+                    .map(|request| request.leaves as u64)
+                    .collect();
+                apportion(&leaves, count, rng)
+            })
+            .collect()
+    }
+
+    /// A query for what this actually asks QBReader for
+    ///
+    /// Parsing it again gets the same questions, so it's what the bot echoes back. It can
+    /// look quite different from what was typed: `Science & Biology` is just `Biology`.
+    pub fn to_expr(&self) -> Expr {
+        let categories = if self.parts.is_empty() {
+            let request = CategoryRequest {
+                categories: self.categories.clone(),
+                subcategories: self.subcategories.clone(),
+                alternate_subcategories: self.alternate_subcategories.clone(),
+                leaves: 0,
+            };
+            set_expr(&request.covers()).filter(|expr| !matches!(expr, Expr::All))
+        } else {
+            let parts = self.parts.iter().filter_map(|part| {
+                let leaves = part.requests.iter().flat_map(|request| request.covers());
+                let expr = set_expr(&leaves.collect())?;
+                Some(match self.parts.len() {
+                    // Unweighted, but too much for one request
+                    1 => expr,
+                    _ => Expr::Weighted(Box::new(expr), part.weight),
+                })
+            });
+            parts.reduce(|a, b| Expr::Or(Box::new(a), Box::new(b)))
+        };
+        let mut filters = Vec::new();
+        if !self.difficulties.is_empty() {
+            filters.push(Filter::Difficulty(self.difficulties.clone()));
+        }
+        if self.min_year.is_some() || self.max_year.is_some() {
+            filters.push(Filter::Year {
+                min: self.min_year,
+                max: self.max_year,
+            });
+        }
+        if self.standard_only {
+            filters.push(Filter::Standard(true));
+        }
+        if let Some(search) = &self.search {
+            filters.push(Filter::Text(search.clone()));
+        }
+        categories
+            .into_iter()
+            .chain(filters.into_iter().map(Expr::Filter))
+            .reduce(|a, b| Expr::And(Box::new(a), Box::new(b)))
+            .unwrap_or(Expr::All)
+    }
+
+    /// Every request with the share of questions it gets on average, e.g. 0.25
+    pub fn plan_shares(&self) -> Vec<(&QueryPart, &CategoryRequest, f64)> {
+        let total: u32 = self.parts.iter().map(|part| part.weight).sum();
+        self.parts
+            .iter()
+            .flat_map(|part| {
+                let leaves: usize = part.requests.iter().map(|request| request.leaves).sum();
+                part.requests.iter().map(move |request| {
+                    let share =
+                        part.weight as f64 / total as f64 * request.leaves as f64 / leaves as f64;
+                    (part, request, share)
+                })
+            })
+            .collect()
+    }
+
+    /// Human readable versions of the filters, e.g. "Years: 2018 onwards"
+    pub fn describe_filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if !self.difficulties.is_empty() {
+            let difficulties: Vec<_> = self
+                .difficulties
+                .iter()
+                .map(|difficulty| difficulty.to_string())
+                .collect();
+            filters.push(format!("Difficulty: {}", difficulties.join(", ")));
+        }
+        match (self.min_year, self.max_year) {
+            (Some(min), Some(max)) if min == max => filters.push(format!("Year: {}", min)),
+            (Some(min), Some(max)) => filters.push(format!("Years: {} to {}", min, max)),
+            (Some(min), None) => filters.push(format!("Years: {} onwards", min)),
+            (None, Some(max)) => filters.push(format!("Years: up to {}", max)),
+            (None, None) => {}
+        }
+        if self.standard_only {
+            filters.push("Standard sets only".to_string());
+        }
+        match &self.search {
+            Some(TextSearch {
+                text,
+                field: SearchField::Question,
+            }) => filters.push(format!("Question text contains \"{}\"", text)),
+            Some(TextSearch {
+                text,
+                field: SearchField::Answer,
+            }) => filters.push(format!("Answer contains \"{}\"", text)),
+            None => {}
+        }
+        filters
+    }
+
+    /// Narrow the query down with a filter, failing if nothing could match both
+    pub fn apply(&mut self, filter: &Filter) -> Result<(), QueryError> {
+        match filter {
+            Filter::Difficulty(difficulties) => {
+                if self.difficulties.is_empty() {
+                    self.difficulties = difficulties.clone();
+                } else {
+                    self.difficulties.retain(|d| difficulties.contains(d));
+                    if self.difficulties.is_empty() {
+                        return Err(QueryError::ImpossibleBranch(format!(
+                            "no difficulty matches {}",
+                            filter
+                        )));
+                    }
+                }
+            }
+            Filter::Year { min, max } => {
+                self.min_year = self.min_year.max(*min);
+                self.max_year = match (self.max_year, max) {
+                    (Some(a), Some(b)) => Some(a.min(*b)),
+                    (a, b) => a.or(*b),
+                };
+                if let (Some(min), Some(max)) = (self.min_year, self.max_year) {
+                    if min > max {
+                        return Err(QueryError::ImpossibleBranch(format!(
+                            "no year matches {}",
+                            filter
+                        )));
+                    }
+                }
+            }
+            Filter::Standard(standard) => self.standard_only |= standard,
+            Filter::Text(search) => {
+                // QBReader only takes one search string
+                if self.search.is_some() {
+                    return Err(QueryError::InvalidFilter(format!(
+                        "{} (only one text search is allowed per query)",
+                        filter
+                    )));
+                }
+                self.search = Some(search.clone());
+            }
         }
+        Ok(())
     }
 }
 
 /// Errors that can occur during query parsing and validation
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum QueryError {
     // Parse errors
     /// Unexpected token encountered during parsing (e.g., operator in wrong position)
-    UnexpectedToken(String),
+    UnexpectedToken(String, Span),
     /// Input ended unexpectedly (e.g., unclosed parentheses)
     UnexpectedEOF,
     // Validation errors
-    /// Category or subcategory name not found in the known categories
-    InvalidCategory(String),
-    /// Query results in impossible constraints (e.g., "Biology & History")
+    /// Category or subcategory name not found in the known categories, with the closest
+    /// names (if any are close)
+    InvalidCategory(String, Span, Vec<String>),
+    /// Query matches no questions at all (e.g., "Biology & History" or "Science - Science")
     ImpossibleBranch(String),
+    /// A `key:value` filter that doesn't make sense, or is combined with something other than `&`
+    InvalidFilter(String),
+    /// A weight of zero, or one that isn't on a part of a top-level `+`
+    InvalidWeight(String),
+    /// An `@name` that isn't saved, with the closest saved names (if any are close)
+    UnknownSavedQuery(String, Span, Vec<String>),
+    /// Saved queries that use each other in a loop, e.g. ["a", "b", "a"]
+    CyclicSavedQuery(Vec<String>),
+    /// Something wrong inside a saved query, pointing at where it's used
+    InSavedQuery(String, Span, Box<QueryError>),
+}
+
+impl QueryError {
+    /// Where in `input` the problem is, if it's anywhere in particular
+    pub fn span(&self, input: &str) -> Option<Span> {
+        match self {
+            QueryError::UnexpectedToken(_, span)
+            | QueryError::InvalidCategory(_, span, _)
+            | QueryError::UnknownSavedQuery(_, span, _)
+            | QueryError::InSavedQuery(_, span, _) => Some(*span),
+            // Just past the end
+            QueryError::UnexpectedEOF => {
+                let end = input.trim_end().chars().count() + 1;
+                Some(Span { start: end, end })
+            }
+            QueryError::ImpossibleBranch(_)
+            | QueryError::InvalidFilter(_)
+            | QueryError::InvalidWeight(_)
+            | QueryError::CyclicSavedQuery(_) => None,
+        }
+    }
+
+    /// The query with a caret under the problem, e.g.
+    ///
+    /// ```text
+    /// Science & & History
+    ///           ^
+    /// ```
+    pub fn caret(&self, input: &str) -> Option<String> {
+        let span = self.span(input)?;
+        Some(format!(
+            "{}\n{}{}",
+            input.trim_end(),
+            " ".repeat(span.start),
+            "^".repeat(span.end.saturating_sub(span.start).max(1))
+        ))
+    }
+}
+
+/// Where something is in the query, in characters (`end` is exclusive)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// From the start of this span to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+/// A single token, and where it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme {
+    pub text: String,
+    pub span: Span,
+}
+
+impl PartialEq<str> for Lexeme {
+    fn eq(&self, other: &str) -> bool {
+        self.text == other
+    }
+}
+
+impl PartialEq<&str> for Lexeme {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+/// Move the word in `buf` (which started at `start`) into `tokens`
+fn push_word(tokens: &mut VecDeque<Lexeme>, buf: &mut String, start: usize) {
+    let text = buf.trim();
+    if !text.is_empty() {
+        let start = start + buf.chars().take_while(|c| c.is_whitespace()).count();
+        tokens.push_back(Lexeme {
+            text: text.to_string(),
+            span: Span {
+                start,
+                end: start + text.chars().count(),
+            },
+        });
+    }
+    buf.clear();
 }
 
 /// Tokenize input string into operators and category names
 ///
 /// Handles multi-word categories by preserving spaces until operators are encountered.
-/// Operators: &, +, -, (, )
+/// Operators: &, +, -, !, *, (, )
+///
+/// Inside a `key:value` term, `-` is part of the value (e.g., `difficulty:3-5`),
+/// and everything between double quotes is kept together (e.g., `answer:"Henry VIII"`).
+/// Each token keeps its span, so errors can point at it.
 ///
 /// # Examples:
 /// - `"Biology + Chemistry"` → `["Biology", "+", "Chemistry"]`
 /// - `"American Literature & History"` → `["American", "Literature", "&", "History"]`
 #[inline]
-pub fn tokenize(input: &str) -> VecDeque<String> {
+pub fn tokenize(input: &str) -> VecDeque<Lexeme> {
     let mut tokens = VecDeque::new();
     let mut buf = String::new();
-    for c in input.chars() {
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in input.chars().enumerate() {
+        if buf.is_empty() {
+            start = i;
+        }
         match c {
-            '&' | '+' | '-' | '(' | ')' => {
-                if !buf.trim().is_empty() {
-                    tokens.push_back(buf.trim().to_string());
-                    buf.clear();
-                }
-                tokens.push_back(c.to_string());
+            '"' => {
+                quoted = !quoted;
+                buf.push(c);
             }
-            ' ' => {
-                if !buf.trim().is_empty() {
-                    tokens.push_back(buf.trim().to_string());
-                    buf.clear();
-                }
+            _ if quoted => buf.push(c),
+            '-' if buf.contains(':') => buf.push(c),
+            '&' | '+' | '-' | '!' | '*' | '(' | ')' => {
+                push_word(&mut tokens, &mut buf, start);
+                tokens.push_back(Lexeme {
+                    text: c.to_string(),
+                    span: Span {
+                        start: i,
+                        end: i + 1,
+                    },
+                });
             }
+            ' ' => push_word(&mut tokens, &mut buf, start),
             _ => buf.push(c),
         }
     }
-    if !buf.trim().is_empty() {
-        tokens.push_back(buf.trim().to_string());
-    }
+    push_word(&mut tokens, &mut buf, start);
     tokens
 }
 
@@ -180,15 +833,17 @@ pub fn tokenize(input: &str) -> VecDeque<String> {
 ///
 /// This is the main entry point for parsing. It expects the entire input
 /// to be a valid expression with no leftover tokens.
-pub fn parse_expr(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
-    let result = parse_or(tokens);
-    if !tokens.is_empty() {
-        Err(QueryError::UnexpectedToken(format!(
-            "Unexpected tokens: {:?}",
-            tokens
-        )))
+pub fn parse_expr(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, QueryError> {
+    // The error from wherever parsing stopped is more useful than whatever's left over
+    let result = parse_or(tokens)?;
+    if let Some(next) = tokens.front() {
+        let leftover: Vec<_> = tokens.iter().map(|tok| tok.text.as_str()).collect();
+        Err(QueryError::UnexpectedToken(
+            format!("Unexpected tokens: {:?}", leftover),
+            next.span,
+        ))
     } else {
-        result
+        Ok(result)
     }
 }
 
@@ -196,7 +851,7 @@ pub fn parse_expr(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
 ///
 /// Used for parsing expressions inside parentheses where there may be
 /// more tokens after the closing parenthesis.
-fn parse_subexpr(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
+fn parse_subexpr(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, QueryError> {
     parse_or(tokens)
 }
 
@@ -204,7 +859,7 @@ fn parse_subexpr(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
 ///
 /// Handles left-associative OR operations. Multiple OR operators
 /// are parsed left-to-right: `A + B + C` becomes `(A + B) + C`
-fn parse_or(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
+fn parse_or(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, QueryError> {
     let mut node = parse_and(tokens)?;
     while let Some(tok) = tokens.front() {
         if tok == "+" {
@@ -223,7 +878,7 @@ fn parse_or(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
 ///
 /// Handles left-associative AND operations. Multiple AND operators
 /// are parsed left-to-right: `A & B & C` becomes `(A & B) & C`
-fn parse_and(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
+fn parse_and(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, QueryError> {
     let mut node = parse_not(tokens)?;
     while let Some(tok) = tokens.front() {
         if tok == "&" {
@@ -242,7 +897,7 @@ fn parse_and(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
 ///
 /// Handles left-associative subtraction operations. Multiple minus operators
 /// are parsed left-to-right: `A - B - C` becomes `(A - B) - C`
-fn parse_not(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
+fn parse_not(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, QueryError> {
     let mut node = parse_primary(tokens)?;
     while let Some(tok) = tokens.front() {
         if tok == "-" {
@@ -257,47 +912,105 @@ fn parse_not(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
     Ok(node)
 }
 
+/// Parse a primary expression, with an optional weight after it (e.g., `Science*3`)
+fn parse_primary(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, QueryError> {
+    let expr = parse_atom(tokens)?;
+    // `*` on its own means everything, so it's only a weight with a number after it
+    let weight = match (tokens.front(), tokens.get(1)) {
+        (Some(star), Some(number)) if star == "*" => number.text.parse::<u32>().ok(),
+        _ => None,
+    };
+    let Some(weight) = weight else {
+        return Ok(expr);
+    };
+    tokens.drain(..2);
+    if weight == 0 {
+        return Err(QueryError::InvalidWeight(format!(
+            "{}*0 (weights have to be at least 1)",
+            expr
+        )));
+    }
+    Ok(Expr::Weighted(Box::new(expr), weight))
+}
+
 /// Parse primary expressions (categories, subcategories, and parenthesized expressions)
 ///
 /// Handles:
 /// - Category/subcategory names (including multi-word like "American Literature")
 /// - Parenthesized sub-expressions
+/// - Unary minus (binds tightest, so `-A - B` is everything except A and B) and `*`/`All`
 /// - Error detection for unexpected operators
-fn parse_primary(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
+fn parse_atom(tokens: &mut VecDeque<Lexeme>) -> Result<Expr, QueryError> {
     if let Some(tok) = tokens.pop_front() {
-        match tok.as_str() {
+        match tok.text.as_str() {
             "(" => {
                 let expr = parse_subexpr(tokens)?;
                 let Some(next_token) = tokens.front() else {
                     return Err(QueryError::UnexpectedEOF);
                 };
                 if next_token != ")" {
-                    return Err(QueryError::UnexpectedToken(format!(
-                        "Unexpected token {:?}, expected ')'",
-                        next_token
-                    )));
+                    return Err(QueryError::UnexpectedToken(
+                        format!("Unexpected token {:?}, expected ')'", next_token.text),
+                        next_token.span,
+                    ));
                 }
 
                 tokens.pop_front();
                 Ok(expr)
             }
+            // A leading minus is everything except what follows
+            "-" | "!" => Ok(Expr::Complement(Box::new(parse_atom(tokens)?))),
+            "*" => Ok(Expr::All),
             // We shouldn't be seeing punctuation here...
-            "&" | "+" | "-" | ")" => Err(QueryError::UnexpectedToken(tok)),
+            "&" | "+" | ")" => Err(QueryError::UnexpectedToken(
+                format!("Unexpected {:?}", tok.text),
+                tok.span,
+            )),
+            text if text.starts_with('@') => {
+                let name = &text[1..];
+                if !is_saved_query_name(name) {
+                    return Err(QueryError::UnexpectedToken(
+                        format!("{:?} isn't a saved query name", text),
+                        tok.span,
+                    ));
+                }
+                Ok(Expr::Saved(name.to_string(), tok.span))
+            }
+            text if text.starts_with('"') => Ok(Expr::Filter(parse_text_search(
+                text,
+                SearchField::Question,
+            )?)),
+            text if text.contains(':') => match text.split_once(':') {
+                Some((key, name)) if key.trim().eq_ignore_ascii_case("preset") => {
+                    parse_preset(name)
+                }
+                _ => Ok(Expr::Filter(parse_filter(text)?)),
+            },
             _ => {
-                let mut buf = vec![tok];
+                let mut span = tok.span;
+                let mut buf = vec![tok.text];
                 // The reason why we have this loop is so we can have support for multi-word categories
-                loop {
-                    let Some(c) = tokens.front() else { break };
-                    match c.as_str() {
-                        "&" | "+" | "-" | "(" | ")" => {
+                while let Some(c) = tokens.front() {
+                    match c.text.as_str() {
+                        "&" | "+" | "-" | "!" | "*" | "(" | ")" => {
+                            break;
+                        }
+                        // Filters, searches and saved queries stand on their own
+                        c if c.contains(':') || c.starts_with('"') || c.starts_with('@') => {
                             break;
                         }
                         _ => {
-                            buf.push(tokens.pop_front().unwrap());
+                            let word = tokens.pop_front().unwrap();
+                            span = span.to(word.span);
+                            buf.push(word.text);
                         }
                     }
                 }
-                Ok(Expr::Token(buf.join(" ")))
+                let name = buf.join(" ");
+                if name.eq_ignore_ascii_case("all") {
+                    return Ok(Expr::All);
+                }
+                Ok(Expr::Token(name, span))
             }
         }
     } else {
@@ -305,172 +1018,279 @@ fn parse_primary(tokens: &mut VecDeque<String>) -> Result<Expr, QueryError> {
     }
 }
 const FUZZY_THRESHOLD: usize = 3;
-#[allow(clippy::ptr_arg)]
-fn match_against(
-    comparator: &levenshtein::BatchComparator<char>,
-    b: &Vec<String>,
-) -> Option<String> {
-    let mut distances: Vec<(String, usize)> = b
-        .iter()
-        .map(|item| {
-            (
-                item.clone(),
-                comparator.distance(item.to_lowercase().chars()),
-            )
+
+/// Every category, subcategory and alternate subcategory name
+fn names() -> impl Iterator<Item = &'static str> {
+    CATEGORIES
+        .entries()
+        .flat_map(|(key, (subcategories, alternates))| {
+            std::iter::once(*key)
+                .chain(subcategories.iter().copied())
+                .chain(alternates.iter().copied())
         })
+}
+
+/// Every name with its distance from `t`, closest first
+fn ranked_names(comparator: &levenshtein::BatchComparator<char>) -> Vec<(usize, &'static str)> {
+    let mut distances: Vec<_> = names()
+        .map(|name| (comparator.distance(name.to_lowercase().chars()), name))
         .collect();
+    distances.sort();
+    distances
+}
+
+/// "Geology", "Geology or Biology", "Geology, Biology or Ecology"
+pub fn did_you_mean(suggestions: &[String]) -> Option<String> {
+    let (last, rest) = suggestions.split_last()?;
+    if rest.is_empty() {
+        return Some(format!("did you mean {}?", last));
+    }
+    Some(format!("did you mean {} or {}?", rest.join(", "), last))
+}
+
+/// The smallest group of questions a query can pick out: a category, plus the
+/// subcategory and alternate subcategory within it (when the category has them)
+///
+/// Alternate subcategories split up the "Other" subcategory (e.g., Math is
+/// Science/Other Science/Math), or the category itself if it has no subcategories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Leaf {
+    pub category: &'static str,
+    pub subcategory: Option<&'static str>,
+    pub alternate: Option<&'static str>,
+}
+
+/// A set of questions, as the leaves that make it up
+pub type CategorySet = BTreeSet<Leaf>;
+
+/// Every leaf in `CATEGORIES`, which is what `-` subtracts from
+pub static UNIVERSE: LazyLock<CategorySet> = LazyLock::new(|| {
+    let mut universe = CategorySet::new();
+    for (category, (subcategories, alternates)) in CATEGORIES.entries() {
+        let leaf = |subcategory, alternate| Leaf {
+            category,
+            subcategory,
+            alternate,
+        };
+        let misc_category = format!("Other {}", category);
+        for subcategory in subcategories.iter() {
+            if *subcategory == misc_category && !alternates.is_empty() {
+                universe.extend(
+                    alternates
+                        .iter()
+                        .map(|alternate| leaf(Some(subcategory), Some(alternate))),
+                );
+            } else {
+                universe.insert(leaf(Some(subcategory), None));
+            }
+        }
+        if subcategories.is_empty() {
+            if alternates.is_empty() {
+                universe.insert(leaf(None, None));
+            }
+            universe.extend(
+                alternates
+                    .iter()
+                    .map(|alternate| leaf(None, Some(alternate))),
+            );
+        }
+    }
+    universe
+});
 
-    distances.sort_by_key(|(_, dist)| *dist);
+/// The leaves a single name (or alias) covers
+fn resolve(t: &str, span: Span) -> Result<CategorySet, QueryError> {
+    let Some(names) = ALIASES.get(t.to_lowercase().as_str()) else {
+        return lookup(t, span);
+    };
+    let mut leaves = CategorySet::new();
+    for name in names.iter() {
+        leaves.extend(lookup(name, span)?);
+    }
+    Ok(leaves)
+}
 
-    let close_matches: Vec<String> = distances
+/// The leaves a single category, subcategory or alternate subcategory name covers
+///
+/// Typos are fine as long as one name is closer than all the others. Otherwise the
+/// error suggests the closest ones.
+fn lookup(t: &str, span: Span) -> Result<CategorySet, QueryError> {
+    let name = find_name(t)
+        .map_err(|suggestions| QueryError::InvalidCategory(t.to_string(), span, suggestions))?;
+    // Names are never shared between categories, subcategories and alternates
+    Ok(UNIVERSE
         .iter()
-        .filter(|(_, dist)| *dist < FUZZY_THRESHOLD)
-        .map(|(item, _)| item.clone())
-        .collect();
+        .filter(|leaf| {
+            leaf.category == name || leaf.subcategory == Some(name) || leaf.alternate == Some(name)
+        })
+        .copied()
+        .collect())
+}
 
-    if close_matches.len() == 1 {
-        Some(close_matches[0].clone())
+/// The name `t` is closest to, or the nearest few if it isn't clearly any of them
+pub fn find_name(t: &str) -> Result<&'static str, Vec<String>> {
+    let comparator = levenshtein::BatchComparator::new(t.to_lowercase().chars());
+    let ranked = ranked_names(&comparator);
+    let best = ranked[0].0;
+    let closest: Vec<_> = ranked
+        .iter()
+        .take_while(|(distance, _)| *distance == best)
+        .map(|(_, name)| *name)
+        .collect();
+    if let [name] = closest[..] {
+        if best < FUZZY_THRESHOLD {
+            return Ok(name);
+        }
+    }
+    // Either a tie, or nothing close enough: suggest whatever's reasonably near
+    let limit = if best < FUZZY_THRESHOLD {
+        best
     } else {
-        None
+        t.chars().count() / 2
+    };
+    Err(ranked
+        .iter()
+        .take_while(|(distance, _)| *distance <= limit)
+        .take(3)
+        .map(|(_, name)| name.to_string())
+        .collect())
+}
+
+/// Evaluate an expression as a set of leaves
+///
+/// `&`, `+` and `-` are plain intersection, union and difference, so the usual set
+/// identities hold (e.g., `A - A` is empty, and `A - B` never overlaps `B`).
+/// An empty result isn't an error here; `build_api_query` decides what to do with it.
+pub fn evaluate(expr: &Expr) -> Result<CategorySet, QueryError> {
+    match expr {
+        // `split_filters` takes these out before we get here
+        Expr::Filter(filter) => Err(QueryError::InvalidFilter(filter.to_string())),
+        Expr::Token(t, span) => resolve(t, *span),
+        Expr::And(a, b) => Ok(&evaluate(a)? & &evaluate(b)?),
+        Expr::Or(a, b) => Ok(&evaluate(a)? | &evaluate(b)?),
+        Expr::Not(a, b) => Ok(&evaluate(a)? - &evaluate(b)?),
+        Expr::Complement(a) => Ok(&*UNIVERSE - &evaluate(a)?),
+        Expr::All => Ok(UNIVERSE.clone()),
+        // Weights don't change which questions are in the set
+        Expr::Weighted(a, _) => evaluate(a),
+        // `expand_saved` replaces these, so any left over weren't found
+        Expr::Saved(name, span) => Err(QueryError::UnknownSavedQuery(
+            name.clone(),
+            *span,
+            Vec::new(),
+        )),
     }
 }
-pub type RawCategories = (Vec<String>, Vec<String>, Vec<String>);
-/// Validate recursively
-/// Validate and convert an expression tree to API query parameters
+
+/// The categories, subcategories and alternate subcategories to ask QBReader for
 ///
-/// Recursively processes the AST and maps category/subcategory names to the
-/// appropriate API parameters. Handles the complex logic of:
-/// - Category name resolution and validation
-/// - Operator semantics (AND, OR, NOT)
-/// - Subcategory and alternate subcategory mapping
-/// - Error detection for impossible queries
-fn validate(expr: &Expr) -> Result<RawCategories, QueryError> {
+/// Subcategory names and alternate subcategory names are never shared between categories,
+/// so listing every one that shows up picks out exactly the leaves in the set.
+pub fn api_categories(leaves: &CategorySet) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let names = |name: &dyn Fn(&Leaf) -> Option<&'static str>| -> Vec<String> {
+        let names: BTreeSet<_> = leaves.iter().filter_map(name).collect();
+        names.into_iter().map(str::to_string).collect()
+    };
+    (
+        names(&|leaf| Some(leaf.category)),
+        names(&|leaf| leaf.subcategory),
+        names(&|leaf| leaf.alternate),
+    )
+}
+
+/// Whether there's a filter anywhere in the expression
+pub(crate) fn contains_filter(expr: &Expr) -> bool {
     match expr {
-        Expr::Token(t) => {
-            let comparator = levenshtein::BatchComparator::new(t.to_lowercase().chars());
-            for (key, value) in CATEGORIES.entries() {
-                // Check if it's a main category (e.g., "Science")
-                if comparator.distance(key.to_lowercase().chars()) < FUZZY_THRESHOLD {
-                    return Ok((
-                        vec![key.to_string()],
-                        value.0.iter().map(|s| s.to_string()).collect(),
-                        value.1.iter().map(|s| s.to_string()).collect(),
-                    ));
-                }
-                // Check if it's a regular subcategory (e.g., "Biology" -> Science/Biology)
-                if let Some(result) = match_against(
-                    &comparator,
-                    &value.0.iter().map(|x| x.to_string()).collect(),
-                ) {
-                    return Ok((vec![key.to_string()], vec![result], vec![]));
-                }
-                // Check if it's an alternate subcategory (e.g., "Math" -> Science/Other Science/Math)
-                if let Some(result) = match_against(
-                    &comparator,
-                    &value.1.iter().map(|x| x.to_string()).collect(),
-                ) {
-                    let misc_category = format!("Other {}", key);
-                    assert!(value.0.contains(&misc_category.as_str()));
-                    return Ok((vec![key.to_string()], vec![misc_category], vec![result]));
-                }
-            }
-            Err(QueryError::InvalidCategory(t.clone()))
+        Expr::Filter(_) => true,
+        Expr::Token(..) | Expr::All | Expr::Saved(..) => false,
+        Expr::And(a, b) | Expr::Or(a, b) | Expr::Not(a, b) => {
+            contains_filter(a) || contains_filter(b)
         }
-        Expr::And(a, b) => {
-            let (ac, asub, aalt) = validate(a)?;
-            let (bc, bsub, balt) = validate(b)?;
-            let cc: Vec<_> = ac.iter().filter(|x| bc.contains(x)).cloned().collect();
-            // Check if categories intersect - if not, it's impossible
-            if cc.is_empty() {
-                return Err(QueryError::ImpossibleBranch(format!("{} & {}", a, b)));
-            }
-
-            // For AND operation: prefer more specific constraints when one side is general
-            // and the other is specific. When both are specific, combine them.
-            let left_has_specifics = !asub.is_empty() || !aalt.is_empty();
-            let right_has_specifics = !bsub.is_empty() || !balt.is_empty();
+        Expr::Complement(a) | Expr::Weighted(a, _) => contains_filter(a),
+    }
+}
 
-            let (result_subs, result_alts) = match (left_has_specifics, right_has_specifics) {
-                (false, true) => {
-                    // Left is general (full category), right is specific - use right
-                    (bsub, balt)
-                }
-                (true, false) => {
-                    // Right is general (full category), left is specific - use left
-                    (asub, aalt)
-                }
-                (true, true) => {
-                    // Both are specific - combine them (e.g., "Biology & Chemistry")
-                    let mut combined_subs = asub;
-                    combined_subs.extend(bsub);
-                    combined_subs.sort();
-                    combined_subs.dedup();
-
-                    let mut combined_alts = aalt;
-                    combined_alts.extend(balt);
-                    combined_alts.sort();
-                    combined_alts.dedup();
-
-                    (combined_subs, combined_alts)
-                }
-                (false, false) => {
-                    // Both are general categories - this case is handled by category intersection
-                    (vec![], vec![])
-                }
+/// Pull the filters out of the top-level `&`s, leaving just the categories (if any)
+///
+/// Filters apply to the whole request, so `Biology + difficulty:3` can't be done.
+pub fn split_filters(expr: &Expr) -> Result<(Option<Expr>, Vec<Filter>), QueryError> {
+    match expr {
+        Expr::Filter(filter) => Ok((None, vec![filter.clone()])),
+        Expr::And(a, b) => {
+            let (a, mut filters) = split_filters(a)?;
+            let (b, b_filters) = split_filters(b)?;
+            filters.extend(b_filters);
+            let categories = match (a, b) {
+                (Some(a), Some(b)) => Some(Expr::And(Box::new(a), Box::new(b))),
+                (a, b) => a.or(b),
             };
+            Ok((categories, filters))
+        }
+        _ if contains_filter(expr) => Err(QueryError::InvalidFilter(format!(
+            "{} (filters can only be combined using &)",
+            expr
+        ))),
+        _ => Ok((Some(expr.clone()), Vec::new())),
+    }
+}
 
-            Ok((cc, result_subs, result_alts))
+/// Whether there's a weight anywhere in the expression
+pub(crate) fn contains_weight(expr: &Expr) -> bool {
+    match expr {
+        Expr::Weighted(..) => true,
+        Expr::Token(..) | Expr::All | Expr::Filter(_) | Expr::Saved(..) => false,
+        Expr::And(a, b) | Expr::Or(a, b) | Expr::Not(a, b) => {
+            contains_weight(a) || contains_weight(b)
         }
-        Expr::Or(a, b) => {
-            let (mut ac, mut asub, mut aalt) = validate(a)?;
-            let (bc, bsub, balt) = validate(b)?;
-            // Union operation: combine all categories, subcategories, and alternates
-            ac.extend(bc);
-            asub.extend(bsub);
-            aalt.extend(balt);
-            // Remove duplicates
-            ac.sort();
-            ac.dedup();
-            asub.sort();
-            asub.dedup();
-            aalt.sort();
-            aalt.dedup();
-            Ok((ac, asub, aalt))
-        }
-        // The minus operator subtracts the second operand from the first
-        // Key insight: we need to be smart about what level to subtract at
-        // - If both sides resolve to the same category, subtract at subcategory/alternate level
-        // - If they resolve to different categories, subtract at category level
-        Expr::Not(a, b) => {
-            let (mut ac, mut asub, mut aalt) = validate(a)?;
-            let (bc, bsub, balt) = validate(b)?;
-
-            // Check if we're subtracting within the same category
-            let common_categories: Vec<_> = ac.iter().filter(|x| bc.contains(x)).cloned().collect();
-
-            if !common_categories.is_empty() {
-                // We're in the same category, so subtract subcategories and alternates
-                asub.retain(|x| !bsub.contains(x));
-                aalt.retain(|x| !balt.contains(x));
-                // Keep the common categories
-                ac = common_categories;
-            } else {
-                // Different categories, subtract entire categories
-                ac.retain(|x| !bc.contains(x));
-                // If we removed all categories, this is an impossible branch
-                if ac.is_empty() {
-                    return Err(QueryError::ImpossibleBranch(format!("{} - {}", a, b)));
-                }
-            }
+        Expr::Complement(a) => contains_weight(a),
+    }
+}
 
-            // If we have no specific subcategories left but still have categories,
-            // we need to include all subcategories of remaining categories
-            if ac.is_empty() && asub.is_empty() && aalt.is_empty() {
-                return Err(QueryError::ImpossibleBranch(format!("{} - {}", a, b)));
+/// The parts of the top-level `+`, with their weights (1 if they don't have one)
+///
+/// Empty if nothing has a weight, since then it's just one big union. An unweighted
+/// `(A + B)` is two parts, so give it a `*1` to keep it together.
+pub fn split_weights(expr: &Expr) -> Result<Vec<(Expr, u32)>, QueryError> {
+    fn summands(expr: &Expr, parts: &mut Vec<(Expr, u32)>) -> Result<(), QueryError> {
+        match expr {
+            Expr::Or(a, b) => {
+                summands(a, parts)?;
+                summands(b, parts)
+            }
+            Expr::Weighted(a, _) if contains_weight(a) => Err(QueryError::InvalidWeight(format!(
+                "{} (weights can't be nested)",
+                expr
+            ))),
+            Expr::Weighted(a, weight) => {
+                parts.push((a.as_ref().clone(), *weight));
+                Ok(())
+            }
+            _ if contains_weight(expr) => Err(QueryError::InvalidWeight(format!(
+                "{} (weights can only go on the parts of a +)",
+                expr
+            ))),
+            _ => {
+                parts.push((expr.clone(), 1));
+                Ok(())
             }
-            Ok((ac, asub, aalt))
         }
     }
+    if !contains_weight(expr) {
+        return Ok(Vec::new());
+    }
+    let mut parts = Vec::new();
+    summands(expr, &mut parts)?;
+    Ok(parts)
+}
+
+/// What to send QBReader for a set of leaves (nothing at all if it's everything)
+fn leaves_to_api(leaves: &CategorySet) -> (Vec<String>, Vec<String>, Vec<String>) {
+    // QBReader picks from everything when it isn't given any categories
+    if *leaves == *UNIVERSE {
+        (vec![], vec![], vec![])
+    } else {
+        api_categories(leaves)
+    }
 }
 
 /// Build the final API query from a validated expression tree
@@ -478,27 +1298,81 @@ fn validate(expr: &Expr) -> Result<RawCategories, QueryError> {
 /// This is the final step that converts the validated expression results
 /// into the ApiQuery structure used by the QBReader API.
 fn build_api_query(expr: &Expr) -> Result<ApiQuery, QueryError> {
-    let (cats, subs, alts) = validate(expr)?;
+    let (categories, filters) = split_filters(expr)?;
+    let mut parts = Vec::new();
+    let (cats, subs, alts) = match &categories {
+        Some(categories) => {
+            for (part, weight) in split_weights(categories)? {
+                let leaves = evaluate(&part)?;
+                if leaves.is_empty() {
+                    return Err(QueryError::ImpossibleBranch(format!(
+                        "no question is in {}",
+                        part
+                    )));
+                }
+                parts.push(QueryPart {
+                    label: part.to_string(),
+                    weight,
+                    requests: plan(&leaves),
+                });
+            }
+            let leaves = evaluate(categories)?;
+            if leaves.is_empty() {
+                return Err(QueryError::ImpossibleBranch(format!(
+                    "no question is in {}",
+                    categories
+                )));
+            }
+            if parts.is_empty() {
+                let requests = plan(&leaves);
+                match <[CategoryRequest; 1]>::try_from(requests) {
+                    // One request covers it, so that's the whole query
+                    Ok([request]) => (
+                        request.categories,
+                        request.subcategories,
+                        request.alternate_subcategories,
+                    ),
+                    Err(requests) => {
+                        parts.push(QueryPart {
+                            label: String::new(),
+                            weight: 1,
+                            requests,
+                        });
+                        leaves_to_api(&leaves)
+                    }
+                }
+            } else {
+                leaves_to_api(&leaves)
+            }
+        }
+        None => (vec![], vec![], vec![]),
+    };
     debug!("Debug normalized expression: {}", expr);
     debug!("Categories: {:?}", cats);
     debug!("Subcategories: {:?}", subs);
     debug!("Alternate subcategories: {:?}", alts);
-    Ok(ApiQuery {
+    let mut api_query = ApiQuery {
         categories: if cats.is_empty() { vec![] } else { cats },
         subcategories: if subs.is_empty() { vec![] } else { subs },
         alternate_subcategories: if alts.is_empty() { vec![] } else { alts },
-        number: 1,
-    })
+        parts,
+        ..Default::default()
+    };
+    for filter in &filters {
+        api_query.apply(filter)?;
+    }
+    Ok(api_query)
 }
 
 /// Parse a query string into API parameters
 ///
 /// This is the main public interface for the query language. It takes a query string
-/// and returns either an ApiQuery ready for the QBReader API, or a QueryError
-/// describing what went wrong.
+/// (which can use any of the `saved` queries as `@name`) and returns either an ApiQuery
+/// ready for the QBReader API, or a QueryError describing what went wrong.
 ///
 /// # Arguments
 /// * `query_str` - The query string to parse (e.g., "Biology + Chemistry - Math")
+/// * `saved` - The saved queries the user can refer to
 ///
 /// # Returns
 /// * `Ok(ApiQuery)` - Successfully parsed query ready for API use
@@ -506,15 +1380,110 @@ fn build_api_query(expr: &Expr) -> Result<ApiQuery, QueryError> {
 ///
 /// # Examples
 /// ```rust
-/// let result = parse_query("Biology + Chemistry");
+/// let saved = SavedQueries::from([("mysci".into(), "Biology + Chemistry".into())]);
+/// let result = parse_query_with("@mysci + History", &saved);
 /// assert!(result.is_ok());
 ///
-/// let result = parse_query("InvalidCategory");
+/// let result = parse_query_with("InvalidCategory", &saved);
 /// assert!(result.is_err());
 /// ```
-pub fn parse_query(query_str: &str) -> Result<ApiQuery, QueryError> {
+pub fn parse_query_with(query_str: &str, saved: &SavedQueries) -> Result<ApiQuery, QueryError> {
     let mut tokens = tokenize(query_str);
-    parse_expr(&mut tokens).and_then(|expr| build_api_query(&expr))
+    let expr = parse_expr(&mut tokens)?;
+    build_api_query(&expand_saved(&expr, saved)?)
+}
+
+/// Parse a query string that doesn't use any saved queries
+#[cfg(test)]
+pub fn parse_query(query_str: &str) -> Result<ApiQuery, QueryError> {
+    parse_query_with(query_str, &SavedQueries::new())
+}
+
+/// Saved queries someone can use, by lowercase name
+pub type SavedQueries = HashMap<String, String>;
+
+/// Saved query names are short and simple so they can't be mistaken for anything else
+pub fn is_saved_query_name(name: &str) -> bool {
+    (1..=32).contains(&name.len()) && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Replace every `@name` with the saved query it stands for
+pub fn expand_saved(expr: &Expr, saved: &SavedQueries) -> Result<Expr, QueryError> {
+    expand(expr, saved, &mut Vec::new())
+}
+
+/// `expanding` is the saved queries we're inside of, to catch them using themselves
+fn expand(
+    expr: &Expr,
+    saved: &SavedQueries,
+    expanding: &mut Vec<String>,
+) -> Result<Expr, QueryError> {
+    let both = |a: &Expr, b: &Expr, expanding: &mut Vec<String>| {
+        Ok::<_, QueryError>((
+            Box::new(expand(a, saved, expanding)?),
+            Box::new(expand(b, saved, expanding)?),
+        ))
+    };
+    Ok(match expr {
+        Expr::Token(..) | Expr::All | Expr::Filter(_) => expr.clone(),
+        Expr::And(a, b) => {
+            let (a, b) = both(a, b, expanding)?;
+            Expr::And(a, b)
+        }
+        Expr::Or(a, b) => {
+            let (a, b) = both(a, b, expanding)?;
+            Expr::Or(a, b)
+        }
+        Expr::Not(a, b) => {
+            let (a, b) = both(a, b, expanding)?;
+            Expr::Not(a, b)
+        }
+        Expr::Complement(a) => Expr::Complement(Box::new(expand(a, saved, expanding)?)),
+        Expr::Weighted(a, weight) => {
+            Expr::Weighted(Box::new(expand(a, saved, expanding)?), *weight)
+        }
+        Expr::Saved(name, span) => {
+            let key = name.to_lowercase();
+            if expanding.contains(&key) {
+                let mut cycle = expanding.clone();
+                cycle.push(key);
+                return Err(QueryError::CyclicSavedQuery(cycle));
+            }
+            let Some(query) = saved.get(&key) else {
+                return Err(QueryError::UnknownSavedQuery(
+                    name.clone(),
+                    *span,
+                    close_saved_names(&key, saved),
+                ));
+            };
+            expanding.push(key);
+            let expanded = parse_expr(&mut tokenize(query))
+                .and_then(|expr| expand(&expr, saved, expanding))
+                .map_err(|err| match err {
+                    // Already says where it's from
+                    QueryError::CyclicSavedQuery(_) => err,
+                    err => QueryError::InSavedQuery(name.clone(), *span, Box::new(err)),
+                });
+            expanding.pop();
+            expanded?
+        }
+    })
+}
+
+/// Saved names that might've been meant instead of `name`, closest first
+fn close_saved_names(name: &str, saved: &SavedQueries) -> Vec<String> {
+    let comparator = levenshtein::BatchComparator::new(name.chars());
+    let mut distances: Vec<_> = saved
+        .keys()
+        .map(|saved| (comparator.distance(saved.chars()), saved))
+        .filter(|(distance, _)| *distance < FUZZY_THRESHOLD)
+        .collect();
+    distances.sort();
+    distances
+        .into_iter()
+        .take(3)
+        .map(|(_, saved)| format!("@{}", saved))
+        .collect()
 }
 #[cfg(test)]
 mod tests {
@@ -527,8 +1496,8 @@ mod tests {
     #[test]
     fn single_category() {
         let r = q("Science").unwrap();
-        assert!(r.categories.contains(&"Science".to_string()));
-        assert!(r.subcategories.contains(&"Biology".to_string()));
+        assert_eq!(r.categories, vec!["Science"]);
+        assert!(r.subcategories.is_empty());
     }
 
     #[test]
@@ -555,10 +1524,12 @@ mod tests {
 
     #[test]
     fn and_operator_same_category() {
-        let r = q("Biology & Chemistry").unwrap();
+        // A question only has one subcategory
+        let r = q("Biology & Chemistry");
+        assert!(matches!(r, Err(QueryError::ImpossibleBranch(_))));
+        let r = q("Science & Biology").unwrap();
         assert_eq!(r.categories, vec!["Science"]);
-        assert!(r.subcategories.contains(&"Biology".to_string()), "{:?}", r);
-        assert!(r.subcategories.contains(&"Chemistry".to_string()));
+        assert_eq!(r.subcategories, vec!["Biology"]);
     }
 
     #[test]
@@ -592,7 +1563,7 @@ mod tests {
     #[test]
     fn unexpected_token_error() {
         let r = q("& Science");
-        assert!(matches!(r, Err(QueryError::UnexpectedToken(_))));
+        assert!(matches!(r, Err(QueryError::UnexpectedToken(..))));
     }
 
     #[test]
@@ -605,7 +1576,7 @@ mod tests {
     #[test]
     fn invalid_category_error() {
         let r = q("MadeUpCategory");
-        assert!(matches!(r, Err(QueryError::InvalidCategory(_))));
+        assert!(matches!(r, Err(QueryError::InvalidCategory(..))));
     }
 
     #[test]
@@ -652,10 +1623,10 @@ mod tests {
 
     #[test]
     fn multiple_and_operators() {
-        let r = q("Science & Biology & Chemistry").unwrap();
+        let r = q("Science & Other Science & Math").unwrap();
         assert_eq!(r.categories, vec!["Science"]);
-        assert!(r.subcategories.contains(&"Biology".to_string()));
-        assert!(r.subcategories.contains(&"Chemistry".to_string()));
+        assert_eq!(r.subcategories, vec!["Other Science"]);
+        assert_eq!(r.alternate_subcategories, vec!["Math"]);
     }
 
     #[test]
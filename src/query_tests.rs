@@ -9,8 +9,10 @@ mod tests {
     #[test]
     fn single_category() {
         let r = q("Science").unwrap();
-        assert!(r.categories.contains(&"Science".to_string()));
-        assert!(r.subcategories.contains(&"Biology".to_string()));
+        assert_eq!(r.categories, vec!["Science"]);
+        // A whole category only needs its name
+        assert!(r.subcategories.is_empty());
+        assert!(r.parts.is_empty());
     }
 
     #[test]
@@ -37,10 +39,12 @@ mod tests {
 
     #[test]
     fn and_operator_same_category() {
-        let r = q("Biology & Chemistry").unwrap();
+        // A question only has one subcategory
+        let r = q("Biology & Chemistry");
+        assert!(matches!(r, Err(QueryError::ImpossibleBranch(_))));
+        let r = q("Science & Biology").unwrap();
         assert_eq!(r.categories, vec!["Science"]);
-        assert!(r.subcategories.contains(&"Biology".to_string()), "{:?}", r);
-        assert!(r.subcategories.contains(&"Chemistry".to_string()));
+        assert_eq!(r.subcategories, vec!["Biology"]);
     }
 
     #[test]
@@ -74,7 +78,7 @@ mod tests {
     #[test]
     fn unexpected_token_error() {
         let r = q("& Science");
-        assert!(matches!(r, Err(QueryError::UnexpectedToken(_))));
+        assert!(matches!(r, Err(QueryError::UnexpectedToken(..))));
     }
 
     #[test]
@@ -87,7 +91,7 @@ mod tests {
     #[test]
     fn invalid_category_error() {
         let r = q("MadeUpCategory");
-        assert!(matches!(r, Err(QueryError::InvalidCategory(_))));
+        assert!(matches!(r, Err(QueryError::InvalidCategory(..))));
     }
 
     #[test]
@@ -137,4 +141,840 @@ mod tests {
         assert!(found.is_some());
         assert_eq!(found.unwrap(), &"Science");
     }
+
+    #[test]
+    fn tokenize_keeps_ranges_in_filters() {
+        assert_eq!(
+            tokenize("Biology & difficulty:3-5 - Math"),
+            vec!["Biology", "&", "difficulty:3-5", "-", "Math"]
+        );
+    }
+
+    #[test]
+    fn difficulty_filter() {
+        let r = q("Biology & difficulty:3-5").unwrap();
+        assert_eq!(r.categories, vec!["Science"]);
+        assert_eq!(r.subcategories, vec!["Biology"]);
+        assert_eq!(r.difficulties, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn difficulty_filter_forms() {
+        assert_eq!(q("difficulty:4").unwrap().difficulties, vec![4]);
+        assert_eq!(q("difficulty:2,4").unwrap().difficulties, vec![2, 4]);
+        assert_eq!(q("difficulty:>=8").unwrap().difficulties, vec![8, 9, 10]);
+        assert_eq!(q("difficulty:<3").unwrap().difficulties, vec![1, 2]);
+    }
+
+    #[test]
+    fn year_filter() {
+        let r = q("Science & year:>=2018").unwrap();
+        assert_eq!(r.min_year, Some(2018));
+        assert_eq!(r.max_year, None);
+        let r = q("year:2015-2018").unwrap();
+        assert_eq!((r.min_year, r.max_year), (Some(2015), Some(2018)));
+        let r = q("year:<2010").unwrap();
+        assert_eq!((r.min_year, r.max_year), (None, Some(2009)));
+    }
+
+    #[test]
+    fn standard_filter() {
+        assert!(q("History & standard:true").unwrap().standard_only);
+        assert!(!q("History & standard:false").unwrap().standard_only);
+    }
+
+    #[test]
+    fn filters_only_query() {
+        let r = q("difficulty:5 & standard:true").unwrap();
+        assert!(r.categories.is_empty());
+        assert_eq!(r.difficulties, vec![5]);
+        assert!(r.standard_only);
+    }
+
+    #[test]
+    fn filters_narrow_each_other() {
+        let r = q("difficulty:3-5 & difficulty:4-6 & year:>=2015 & year:<=2018").unwrap();
+        assert_eq!(r.difficulties, vec![4, 5]);
+        assert_eq!((r.min_year, r.max_year), (Some(2015), Some(2018)));
+    }
+
+    #[test]
+    fn conflicting_filters_are_impossible() {
+        assert!(matches!(
+            q("difficulty:1-2 & difficulty:5"),
+            Err(QueryError::ImpossibleBranch(_))
+        ));
+        assert!(matches!(
+            q("year:>=2020 & year:<2018"),
+            Err(QueryError::ImpossibleBranch(_))
+        ));
+    }
+
+    #[test]
+    fn filters_only_combine_with_and() {
+        assert!(matches!(
+            q("Biology + difficulty:3"),
+            Err(QueryError::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            q("Science - year:2018"),
+            Err(QueryError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_filters() {
+        for query in [
+            "difficulty:hard",
+            "difficulty:11",
+            "year:soon",
+            "year:2020-2010",
+            "standard:maybe",
+            "color:blue",
+        ] {
+            assert!(
+                matches!(q(query), Err(QueryError::InvalidFilter(_))),
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn filter_display() {
+        for filter in [
+            "difficulty:3-5",
+            "difficulty:2,4",
+            "difficulty:7",
+            "year:>=2018",
+            "year:<=2010",
+            "year:2015-2018",
+            "standard:true",
+        ] {
+            assert_eq!(parse_filter(filter).unwrap().to_string(), filter);
+        }
+    }
+
+    #[test]
+    fn describe_filters() {
+        let r = q("difficulty:3-4 & year:>=2018 & standard:true").unwrap();
+        assert_eq!(
+            r.describe_filters(),
+            vec![
+                "Difficulty: 3, 4",
+                "Years: 2018 onwards",
+                "Standard sets only"
+            ]
+        );
+        assert!(q("Biology").unwrap().describe_filters().is_empty());
+    }
+
+    #[test]
+    fn tokenize_keeps_quoted_text_together() {
+        assert_eq!(
+            tokenize(r#"Science & "light-dependent reactions" + answer:"Henry VIII""#),
+            vec![
+                "Science",
+                "&",
+                "\"light-dependent reactions\"",
+                "+",
+                "answer:\"Henry VIII\""
+            ]
+        );
+    }
+
+    #[test]
+    fn question_text_search() {
+        let r = q(r#"Science & "photosynthesis""#).unwrap();
+        assert_eq!(r.categories, vec!["Science"]);
+        assert_eq!(
+            r.search,
+            Some(TextSearch {
+                text: "photosynthesis".to_string(),
+                field: SearchField::Question,
+            })
+        );
+    }
+
+    #[test]
+    fn answer_text_search() {
+        let r = q(r#"answer:"Napoleon" & year:>=2015"#).unwrap();
+        assert_eq!(
+            r.search,
+            Some(TextSearch {
+                text: "Napoleon".to_string(),
+                field: SearchField::Answer,
+            })
+        );
+        assert_eq!(r.min_year, Some(2015));
+        // Quotes are optional for a single word
+        assert_eq!(q("answer:Napoleon").unwrap().search, r.search);
+    }
+
+    #[test]
+    fn only_one_text_search() {
+        assert!(matches!(
+            q(r#""photosynthesis" & answer:"Calvin""#),
+            Err(QueryError::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            q(r#"Biology + "photosynthesis""#),
+            Err(QueryError::InvalidFilter(_))
+        ));
+    }
+
+    #[test]
+    fn bad_text_searches() {
+        assert!(matches!(
+            q(r#""photosynthesis"#),
+            Err(QueryError::UnexpectedEOF)
+        ));
+        assert!(matches!(q(r#""""#), Err(QueryError::InvalidFilter(_))));
+    }
+
+    #[test]
+    fn text_search_display() {
+        let r = q(r#"answer:"Henry VIII""#).unwrap();
+        assert_eq!(r.describe_filters(), vec!["Answer contains \"Henry VIII\""]);
+        assert_eq!(
+            parse_filter(r#"answer:"Henry VIII""#).unwrap().to_string(),
+            r#"answer:"Henry VIII""#
+        );
+    }
+
+    fn set(s: &str) -> CategorySet {
+        let (categories, _) = split_filters(&parse_expr(&mut tokenize(s)).unwrap()).unwrap();
+        evaluate(&categories.unwrap()).unwrap()
+    }
+
+    /// Names that cover different parts of the tree, and overlap in different ways
+    const TERMS: &[&str] = &[
+        "Science",
+        "Biology",
+        "Other Science",
+        "Math",
+        "Literature",
+        "Poetry",
+        "History",
+        "Social Science",
+        "Economics",
+        "Religion",
+    ];
+
+    #[test]
+    fn universe_covers_every_name() {
+        for (category, (subcategories, alternates)) in CATEGORIES.entries() {
+            assert!(UNIVERSE.iter().any(|leaf| leaf.category == *category));
+            for subcategory in subcategories.iter() {
+                assert!(UNIVERSE
+                    .iter()
+                    .any(|leaf| leaf.subcategory == Some(subcategory)));
+            }
+            for alternate in alternates.iter() {
+                assert!(UNIVERSE
+                    .iter()
+                    .any(|leaf| leaf.alternate == Some(alternate)));
+            }
+        }
+        // Social Science has alternates but no subcategories
+        assert_eq!(set("Social Science").len(), 6);
+        assert_eq!(set("Religion").len(), 1);
+    }
+
+    #[test]
+    fn subtracting_itself_is_empty() {
+        for a in TERMS {
+            assert!(set(&format!("{a} - {a}")).is_empty(), "{a} - {a}");
+            assert!(matches!(
+                q(&format!("{a} - {a}")),
+                Err(QueryError::ImpossibleBranch(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn difference_never_meets_what_was_subtracted() {
+        for a in TERMS {
+            for b in TERMS {
+                let difference = set(&format!("{a} - {b}"));
+                assert!(difference.is_disjoint(&set(b)), "{a} - {b}");
+                assert!(set(&format!("({a} - {b}) & {b}")).is_empty());
+                assert!(difference.is_subset(&set(a)));
+            }
+        }
+    }
+
+    #[test]
+    fn set_identities() {
+        for a in TERMS {
+            for b in TERMS {
+                // A = (A - B) + (A & B)
+                assert_eq!(set(&format!("({a} - {b}) + ({a} & {b})")), set(a));
+                // A - B = A - (A & B)
+                assert_eq!(
+                    set(&format!("{a} - {b}")),
+                    set(&format!("{a} - ({a} & {b})"))
+                );
+                assert_eq!(set(&format!("{a} + {b}")), set(&format!("{b} + {a}")));
+                assert_eq!(set(&format!("{a} & {b}")), set(&format!("{b} & {a}")));
+                for c in TERMS {
+                    // (A + B) - C = (A - C) + (B - C)
+                    assert_eq!(
+                        set(&format!("({a} + {b}) - {c}")),
+                        set(&format!("({a} - {c}) + ({b} - {c})"))
+                    );
+                    // A & (B - C) = (A & B) - C
+                    assert_eq!(
+                        set(&format!("{a} & ({b} - {c})")),
+                        set(&format!("({a} & {b}) - {c}"))
+                    );
+                    // A - (B + C) = (A - B) - C
+                    assert_eq!(
+                        set(&format!("{a} - ({b} + {c})")),
+                        set(&format!("{a} - {b} - {c}"))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn subtraction_across_categories() {
+        // Nothing in common, so nothing is taken away
+        assert_eq!(q("Biology - History").unwrap(), q("Biology").unwrap());
+        // Taking away a whole category leaves the rest of the union alone
+        let r = q("(Biology + History) - History").unwrap();
+        assert_eq!(r.categories, vec!["Science"]);
+        assert_eq!(r.subcategories, vec!["Biology"]);
+        // Only part of the category goes
+        let r = q("(Science + History) - Math").unwrap();
+        assert_eq!(r.categories, vec!["History", "Science"]);
+        assert!(r.subcategories.contains(&"American History".to_string()));
+        assert!(r.subcategories.contains(&"Other Science".to_string()));
+        assert!(!r.alternate_subcategories.contains(&"Math".to_string()));
+    }
+
+    #[test]
+    fn subtraction_inside_intersection() {
+        let r = q("Science & (Other Science - Math)").unwrap();
+        assert_eq!(r.subcategories, vec!["Other Science"]);
+        assert_eq!(r.alternate_subcategories.len(), 5);
+        assert!(!r.alternate_subcategories.contains(&"Math".to_string()));
+        assert!(matches!(
+            q("Biology & (Science - Biology)"),
+            Err(QueryError::ImpossibleBranch(_))
+        ));
+    }
+
+    #[test]
+    fn api_categories_round_trip() {
+        // What we send QBReader picks out exactly the leaves we meant
+        for a in TERMS {
+            for b in TERMS {
+                let leaves = set(&format!("{a} + {b} - Math"));
+                let (categories, subcategories, alternates) = api_categories(&leaves);
+                let sent: CategorySet = UNIVERSE
+                    .iter()
+                    .filter(|leaf| categories.iter().any(|c| c == leaf.category))
+                    .filter(|leaf| {
+                        leaf.subcategory
+                            .is_none_or(|s| subcategories.iter().any(|x| x == s))
+                    })
+                    .filter(|leaf| {
+                        leaf.alternate
+                            .is_none_or(|s| alternates.iter().any(|x| x == s))
+                    })
+                    .copied()
+                    .collect();
+                assert_eq!(sent, leaves, "{a} + {b} - Math");
+            }
+        }
+    }
+
+    #[test]
+    fn tokenize_unary_operators() {
+        assert_eq!(tokenize("!Trash & *"), vec!["!", "Trash", "&", "*"]);
+        assert_eq!(
+            tokenize("-Pop Culture - Current Events"),
+            vec!["-", "Pop", "Culture", "-", "Current", "Events"]
+        );
+    }
+
+    #[test]
+    fn everything_except() {
+        let r = q("-Pop Culture - Current Events").unwrap();
+        assert_eq!(r.categories.len(), CATEGORIES.len() - 2);
+        assert!(!r.categories.contains(&"Pop Culture".to_string()));
+        assert!(!r.categories.contains(&"Current Events".to_string()));
+        assert!(r.subcategories.is_empty());
+        // Same thing, spelled differently
+        assert_eq!(r, q("!(Pop Culture + Current Events)").unwrap());
+        assert_eq!(r, q("All - Pop Culture - Current Events").unwrap());
+    }
+
+    #[test]
+    fn negation_inside_expressions() {
+        assert_eq!(
+            q("Science & -Biology").unwrap(),
+            q("Science - Biology").unwrap()
+        );
+        assert_eq!(q("Science & !Math").unwrap(), q("Science - Math").unwrap());
+        assert!(matches!(q("-All"), Err(QueryError::ImpossibleBranch(_))));
+        assert_eq!(set("--Biology"), set("Biology"));
+        for a in TERMS {
+            assert!(set(&format!("{a} & -{a}")).is_empty());
+            assert_eq!(set(&format!("{a} + -{a}")), *UNIVERSE);
+        }
+    }
+
+    #[test]
+    fn all_categories() {
+        assert_eq!(set("*"), *UNIVERSE);
+        assert_eq!(set("all"), *UNIVERSE);
+        // Everything is what QBReader gives us without any categories
+        let r = q("* & difficulty:3").unwrap();
+        assert!(r.categories.is_empty());
+        assert!(r.subcategories.is_empty());
+        assert_eq!(r.difficulties, vec![3]);
+    }
+
+    #[test]
+    fn negated_filters_are_rejected() {
+        assert!(matches!(
+            q("-difficulty:3"),
+            Err(QueryError::InvalidFilter(_))
+        ));
+        assert!(matches!(q("Science & -"), Err(QueryError::UnexpectedEOF)));
+    }
+
+    #[test]
+    fn alias_targets_are_real_names() {
+        for (alias, names) in ALIASES.entries() {
+            assert_eq!(
+                *alias,
+                alias.to_lowercase(),
+                "aliases are looked up in lowercase"
+            );
+            for name in names.iter() {
+                let known = CATEGORIES.entries().any(|(category, (subs, alts))| {
+                    category == name || subs.contains(name) || alts.contains(name)
+                });
+                assert!(known, "{alias} -> {name}");
+            }
+        }
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!(q("bio").unwrap(), q("Biology").unwrap());
+        assert_eq!(q("CHEM").unwrap(), q("Chemistry").unwrap());
+        assert_eq!(q("cs").unwrap(), q("Computer Science").unwrap());
+        assert_eq!(q("am lit").unwrap(), q("American Literature").unwrap());
+        assert_eq!(q("trash").unwrap(), q("Pop Culture").unwrap());
+        assert_eq!(q("geo").unwrap(), q("Geography").unwrap());
+        assert_eq!(q("SS").unwrap(), q("Social Science").unwrap());
+        // Aliases can stand for more than one thing
+        assert_eq!(
+            q("RMP").unwrap(),
+            q("Religion + Mythology + Philosophy").unwrap()
+        );
+        assert_eq!(
+            q("euro").unwrap(),
+            q("European Literature + European History").unwrap()
+        );
+    }
+
+    #[test]
+    fn aliases_in_expressions() {
+        assert_eq!(q("sci - bio").unwrap(), q("Science - Biology").unwrap());
+        assert_eq!(
+            q("-trash - ce").unwrap(),
+            q("-Pop Culture - Current Events").unwrap()
+        );
+        assert_eq!(q("lit & euro").unwrap(), q("European Literature").unwrap());
+    }
+
+    #[test]
+    fn aliases_for_names() {
+        assert_eq!(aliases_for("Biology"), vec!["bio"]);
+        assert_eq!(aliases_for("Pop Culture"), vec!["pop", "trash"]);
+        assert!(aliases_for("Religion").contains(&"rmp"));
+        assert!(aliases_for("Other Science").is_empty());
+    }
+
+    fn span(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    #[test]
+    fn tokenize_spans() {
+        let spans: Vec<_> = tokenize("am lit + (Bio)")
+            .iter()
+            .map(|tok| tok.span)
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                span(0, 2),
+                span(3, 6),
+                span(7, 8),
+                span(9, 10),
+                span(10, 13),
+                span(13, 14)
+            ]
+        );
+        // Quoted text is one token, counted in characters
+        assert_eq!(tokenize(r#"é & "a b""#).back().unwrap().span, span(4, 9));
+    }
+
+    #[test]
+    fn unexpected_token_span() {
+        let input = "Science & & History";
+        let err = q(input).unwrap_err();
+        assert!(
+            matches!(err, QueryError::UnexpectedToken(_, s) if s == span(10, 11)),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.caret(input).unwrap(),
+            "Science & & History\n          ^"
+        );
+        // Leftover tokens point at the first one
+        let err = q("Science)").unwrap_err();
+        assert_eq!(err.span("Science)"), Some(span(7, 8)));
+    }
+
+    #[test]
+    fn invalid_category_span() {
+        let input = "Science + Made Up Category - Math";
+        let err = q(input).unwrap_err();
+        assert!(
+            matches!(&err, QueryError::InvalidCategory(name, _, _) if name == "Made Up Category")
+        );
+        assert_eq!(
+            err.caret(input).unwrap(),
+            "Science + Made Up Category - Math\n          ^^^^^^^^^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn unexpected_eof_points_past_the_end() {
+        let err = q("(Science + ").unwrap_err();
+        assert!(matches!(err, QueryError::UnexpectedEOF));
+        assert_eq!(
+            err.caret("(Science + ").unwrap(),
+            "(Science +\n           ^"
+        );
+        // No particular place for these
+        assert_eq!(
+            q("Biology & History")
+                .unwrap_err()
+                .caret("Biology & History"),
+            None
+        );
+    }
+
+    #[test]
+    fn ambiguous_categories_are_suggested() {
+        let Err(QueryError::InvalidCategory(_, _, suggestions)) = q("Sycology") else {
+            panic!("Sycology should be ambiguous");
+        };
+        assert_eq!(suggestions, vec!["Psychology", "Sociology"]);
+        assert_eq!(
+            did_you_mean(&suggestions).unwrap(),
+            "did you mean Psychology or Sociology?"
+        );
+        // A single closest match still works
+        assert_eq!(q("Psycology").unwrap(), q("Psychology").unwrap());
+    }
+
+    #[test]
+    fn far_off_categories_are_suggested() {
+        let Err(QueryError::InvalidCategory(_, _, suggestions)) = q("Ancient Hist") else {
+            panic!("Ancient Hist is too far from Ancient History");
+        };
+        assert_eq!(suggestions, vec!["Ancient History"]);
+        let Err(QueryError::InvalidCategory(_, _, suggestions)) = q("Qwerty") else {
+            panic!("Qwerty isn't a category");
+        };
+        assert!(suggestions.is_empty());
+    }
+
+    #[test]
+    fn did_you_mean_lists() {
+        assert_eq!(did_you_mean(&[]), None);
+        let names = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            did_you_mean(&names(&["Jazz"])).unwrap(),
+            "did you mean Jazz?"
+        );
+        assert_eq!(
+            did_you_mean(&names(&["Jazz", "Math", "Film"])).unwrap(),
+            "did you mean Jazz, Math or Film?"
+        );
+    }
+
+    fn weights(r: &ApiQuery) -> Vec<(&str, u32)> {
+        r.parts
+            .iter()
+            .map(|part| (part.label.as_str(), part.weight))
+            .collect()
+    }
+
+    #[test]
+    fn weighted_query() {
+        let r = q("Science*3 + History*2 + Literature").unwrap();
+        assert_eq!(
+            weights(&r),
+            vec![("Science", 3), ("History", 2), ("Literature", 1)]
+        );
+        assert_eq!(r.parts[0].requests.len(), 1);
+        assert_eq!(r.parts[0].requests[0].categories, vec!["Science"]);
+        // The union is still there for anything that wants the whole thing
+        assert_eq!(r.categories, vec!["History", "Literature", "Science"]);
+        // Spaces are fine, and weights can go on anything in parentheses
+        let r = q("(bio + chem) * 2 + Physics").unwrap();
+        assert_eq!(weights(&r), vec![("bio + chem", 2), ("Physics", 1)]);
+        assert_eq!(
+            r.parts[0].requests[0].subcategories,
+            vec!["Biology", "Chemistry"]
+        );
+    }
+
+    #[test]
+    fn single_request_queries_have_no_parts() {
+        assert!(q("Science + History").unwrap().parts.is_empty());
+        // `*` on its own is still everything
+        assert!(q("* - Pop Culture").unwrap().parts.is_empty());
+        assert_eq!(
+            weights(&q("**2 + Trash").unwrap()),
+            vec![("All", 2), ("Trash", 1)]
+        );
+    }
+
+    #[test]
+    fn weighted_query_with_filters() {
+        let r = q("(Science*3 + History) & difficulty:3-5").unwrap();
+        assert_eq!(weights(&r), vec![("Science", 3), ("History", 1)]);
+        assert_eq!(r.difficulties, vec![3, 4, 5]);
+        let part = r.for_request(&r.parts[1].requests[0], 4);
+        assert_eq!(part.categories, vec!["History"]);
+        assert_eq!(part.number, 4);
+        assert_eq!(part.difficulties, vec![3, 4, 5]);
+        assert!(part.parts.is_empty());
+    }
+
+    #[test]
+    fn bad_weights() {
+        for query in [
+            "Science*0 + History",
+            "(Science*2 + History)*3",
+            "Science*2 & Biology",
+            "History - Science*2",
+        ] {
+            assert!(
+                matches!(q(query), Err(QueryError::InvalidWeight(_))),
+                "{query}"
+            );
+        }
+        assert!(matches!(
+            q("(Biology & Chemistry)*2 + History"),
+            Err(QueryError::ImpossibleBranch(_))
+        ));
+    }
+
+    #[test]
+    fn presets() {
+        for (name, _) in PRESETS.entries() {
+            let r = q(&format!("preset:{name}")).unwrap();
+            let total: u32 = r.parts.iter().map(|part| part.weight).sum();
+            assert_eq!(total, 20, "{name} is per 20 tossups");
+        }
+        let r = q("preset:ACF & standard:true").unwrap();
+        assert_eq!(
+            &weights(&r)[..3],
+            &[("Literature", 4), ("History", 4), ("Science", 4)]
+        );
+        assert!(r.standard_only);
+        assert!(matches!(
+            q("preset:nope"),
+            Err(QueryError::InvalidFilter(_))
+        ));
+    }
+
+    /// Questions drawn for each part, whichever of its requests they come from
+    fn part_counts(r: &ApiQuery, rng: &mut impl rand::Rng) -> Vec<u32> {
+        r.draw(rng)
+            .iter()
+            .map(|counts| counts.iter().sum())
+            .collect()
+    }
+
+    #[test]
+    fn draw_follows_the_weights() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mut r = q("preset:acf").unwrap();
+        r.number = 20;
+        let exact: Vec<u32> = r.parts.iter().map(|part| part.weight).collect();
+        assert_eq!(part_counts(&r, &mut rng), exact);
+        // Double the packet, double the questions
+        r.number = 40;
+        let doubled: Vec<u32> = exact.iter().map(|weight| weight * 2).collect();
+        assert_eq!(part_counts(&r, &mut rng), doubled);
+
+        // Whatever's left over goes somewhere, never more than one extra per part
+        let mut r = q("Science*3 + History*2 + Literature").unwrap();
+        for number in 0..20 {
+            r.number = number;
+            let counts = part_counts(&r, &mut rng);
+            assert_eq!(counts.iter().sum::<u32>(), number);
+            for (count, weight) in counts.iter().zip([3, 2, 1]) {
+                let share = number * weight / 6;
+                assert!(
+                    *count == share || *count == share + 1,
+                    "{number}: {counts:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn draw_splits_parts_by_leaves() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        // Biology is one leaf and Math one of several, so they split evenly
+        let mut r = q("Biology + Math").unwrap();
+        assert_eq!(r.parts.len(), 1);
+        assert_eq!(r.parts[0].requests.len(), 2);
+        let leaves: Vec<u32> = r.parts[0]
+            .requests
+            .iter()
+            .map(|request| request.leaves as u32)
+            .collect();
+        r.number = leaves.iter().sum::<u32>() * 5;
+        let expected: Vec<u32> = leaves.iter().map(|leaves| leaves * 5).collect();
+        assert_eq!(r.draw(&mut rng), vec![expected]);
+    }
+
+    #[test]
+    fn single_draws_are_random_in_proportion() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let r = q("Science*3 + History").unwrap();
+        let mut science = 0;
+        for _ in 0..1000 {
+            let counts = part_counts(&r, &mut rng);
+            assert_eq!(counts.iter().sum::<u32>(), 1);
+            science += counts[0];
+        }
+        // 750 expected
+        assert!((700..800).contains(&science), "{science}");
+    }
+
+    fn saved(queries: &[(&str, &str)]) -> SavedQueries {
+        queries
+            .iter()
+            .map(|(name, query)| (name.to_string(), query.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn saved_queries() {
+        let saved = saved(&[
+            ("mysci", "(Biology + Chemistry) - Math"),
+            ("hum", "Literature + @arts"),
+            ("arts", "Fine Arts"),
+        ]);
+        let with = |s: &str| parse_query_with(s, &saved);
+        assert_eq!(
+            with("@mysci + History").unwrap(),
+            q("(Biology + Chemistry) + History").unwrap()
+        );
+        // They can use each other, and names aren't case sensitive
+        assert_eq!(with("@HUM").unwrap(), q("Literature + Fine Arts").unwrap());
+        // A saved query is one thing, however it's written
+        assert_eq!(with("@mysci & Biology").unwrap(), q("Biology").unwrap());
+        assert_eq!(
+            with("(@mysci*2 + History) & difficulty:3").unwrap(),
+            q("(((Biology + Chemistry) - Math)*2 + History) & difficulty:3").unwrap()
+        );
+        // Without them, a reference doesn't go anywhere
+        assert!(matches!(
+            q("@mysci"),
+            Err(QueryError::UnknownSavedQuery(..))
+        ));
+    }
+
+    #[test]
+    fn saved_query_cycles() {
+        let saved = saved(&[
+            ("a", "Science + @b"),
+            ("b", "History + @c"),
+            ("c", "@a"),
+            ("me", "@me"),
+            ("twice", "@arts + @arts"),
+            ("arts", "Fine Arts"),
+        ]);
+        assert_eq!(
+            parse_query_with("Literature + @a", &saved),
+            Err(QueryError::CyclicSavedQuery(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "c".to_string(),
+                "a".to_string()
+            ]))
+        );
+        assert_eq!(
+            parse_query_with("@me", &saved),
+            Err(QueryError::CyclicSavedQuery(vec![
+                "me".to_string(),
+                "me".to_string()
+            ]))
+        );
+        // Using the same one twice isn't a loop
+        assert!(parse_query_with("@twice + @arts", &saved).is_ok());
+    }
+
+    #[test]
+    fn saved_query_errors() {
+        let saved = saved(&[("mysci", "Biology"), ("broken", "Biology & &")]);
+        let err = parse_query_with("History + @mysic", &saved).unwrap_err();
+        assert_eq!(
+            err,
+            QueryError::UnknownSavedQuery(
+                "mysic".to_string(),
+                Span { start: 10, end: 16 },
+                vec!["@mysci".to_string()]
+            )
+        );
+        // Same cutoff as category names: three edits is too far to suggest
+        let err = parse_query_with("@mysciabc", &saved).unwrap_err();
+        assert!(matches!(
+            err,
+            QueryError::UnknownSavedQuery(_, _, suggestions) if suggestions.is_empty()
+        ));
+        // Problems inside a saved query point at where it's used
+        let err = parse_query_with("History + @broken", &saved).unwrap_err();
+        assert!(matches!(
+            &err,
+            QueryError::InSavedQuery(name, _, inner)
+                if name == "broken" && matches!(**inner, QueryError::UnexpectedToken(..))
+        ));
+        assert_eq!(
+            err.caret("History + @broken").unwrap(),
+            "History + @broken\n          ^^^^^^^"
+        );
+        assert!(matches!(q("@my.sci"), Err(QueryError::UnexpectedToken(..))));
+    }
+
+    #[test]
+    fn saved_query_names() {
+        assert!(is_saved_query_name("mysci"));
+        assert!(is_saved_query_name("My_Sci_2"));
+        assert!(!is_saved_query_name(""));
+        assert!(!is_saved_query_name("my sci"));
+        assert!(!is_saved_query_name("my-sci"));
+        assert!(!is_saved_query_name(&"a".repeat(33)));
+    }
 }
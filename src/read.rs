@@ -1,260 +1,558 @@
-use ::serenity::all::{Mentionable, ReactionType};
+use ::serenity::all::{ChannelId, Http, Mentionable, Message, ReactionType, UserId};
+use llm::LLMProvider;
 use poise::serenity_prelude as serenity;
-use std::collections::HashSet;
-use std::thread::sleep;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
 
-use tokio::task;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{sleep_until, Duration, Instant};
+use tracing::{debug, error, info, warn};
 
-use tokio::sync::watch;
-use tokio::time::{timeout, Duration};
-use tracing::{debug, info};
-
-use crate::check::{check_correct_answer, Response};
+use crate::check::{Pipeline, Response};
+use crate::score::{BuzzKind, Scoreboard, ScoredBuzz};
+use crate::teams::Roster;
 use crate::utils::*;
-use crate::{qb::Tossup, Context, Data, Error, QuestionState};
-// TODO: this code structure is suicide for maintainance
-pub async fn read_question(
-    ctx: &Context<'_>,
-    tossups: Vec<Tossup>,
-    say: bool,
-) -> Result<(), Error> {
-    let Some(tossup) = tossups.first() else {
-        ctx.say("No tossups found").await?;
-        return Ok(());
-    };
-    // Having it bold (or formatted in any manner) is kinda annoying
-    let formatted = format_question(&tossup.question_sanitized);
-    let mut question = formatted.split(' ');
-    // Start off with 3, an arbitrarily chosen small number
-    let chunk = nth_chunk(&mut question, 3);
-    let mut buffer = chunk.join(" ");
-    let channel = ctx.channel_id();
+use crate::{qb::Tossup, ChannelState, Context, Data, Error, QuestionState};
 
-    // Create notification channel for state changes
-    // lowk this is inefficient, and I should switch this to a notifier
-    // but you know what they say: if it ain't broke, don't fix it
-    let (state_change_tx, mut state_change_rx) = watch::channel(());
+// The numbers here are arbitrarily chosen, empirically tuned
+// for a balance between reading speed and simulating 180 WPM
+// speaking speed
+/// Words shown when the question first appears
+const FIRST_CHUNK: usize = 3;
+/// Words added every tick
+const CHUNK: usize = 5;
+const TICK: Duration = Duration::from_millis(750);
+/// How long people get to buzz once the whole question has been read
+const DEAD_TIME: Duration = Duration::from_secs(6);
+const BUZZ_TIMEOUT: Duration = Duration::from_secs(10);
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(10);
+/// If the judge takes longer than this, we give up on it
+const JUDGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Something that happened in a channel, sent to the game running there
+#[derive(Debug)]
+pub enum GameEvent {
+    /// Someone said "buzz"
+    Buzz(Box<Message>),
+    /// Any other message, which might be an answer
+    Answer(Box<Message>),
+    /// Whatever the game was waiting on took too long
+    Timeout,
+    /// The judge made up its mind about an answer
+    Verdict {
+        user_id: UserId,
+        /// When the answer was given
+        timestamp: i64,
+        prompted: bool,
+        response: Result<Response, String>,
+    },
+    /// Someone used `/stop`
+    Stop,
+}
 
-    // Might be unnecessary but scoped to avoid deadlocks
+/// Mailboxes of the games currently running, by channel
+pub type Games = Arc<Mutex<HashMap<ChannelId, mpsc::UnboundedSender<GameEvent>>>>;
+
+/// Put a game's mailbox up for its channel, unless a game is still running there
+pub fn register_game(
+    games: &mut HashMap<ChannelId, mpsc::UnboundedSender<GameEvent>>,
+    channel: ChannelId,
+    mailbox: &mpsc::UnboundedSender<GameEvent>,
+) -> bool {
+    // A closed mailbox is left over from a game that's over
+    if games.get(&channel).is_some_and(|live| !live.is_closed()) {
+        return false;
+    }
+    games.insert(channel, mailbox.clone());
+    true
+}
+
+/// Take a game's mailbox down once it's over, as long as it's still the one up
+pub fn unregister_game(
+    games: &mut HashMap<ChannelId, mpsc::UnboundedSender<GameEvent>>,
+    channel: ChannelId,
+    mailbox: &mpsc::UnboundedSender<GameEvent>,
+) {
+    if games
+        .get(&channel)
+        .is_some_and(|registered| registered.same_channel(mailbox))
     {
-        ctx.data().reading_states.lock().await.insert(
-            channel,
-            (
-                QuestionState::Reading,
-                !formatted.contains("(*)"),
-                HashSet::new(),
-                state_change_tx,
-                // sob... but it should be fine
-                tossup.clone(),
-                buffer.clone(),
-            ),
-        );
+        games.remove(&channel);
     }
+}
 
-    let mut message = if say {
-        ctx.say(buffer.clone()).await?.into_message().await?
-    } else {
-        ctx.channel_id().say(&ctx.http(), buffer.clone()).await?
-    };
+/// Wait out the pause before a question, returning whether the session's still going after it
+///
+/// Nothing is being read during the pause, so a `/stop` then has no game to reach. Checking
+/// again right before the question is read is what catches it.
+pub async fn still_going_after(
+    sessions: &Mutex<HashSet<ChannelId>>,
+    channel: ChannelId,
+    pause: impl Future<Output = Result<(), Error>>,
+) -> Result<bool, Error> {
+    if !sessions.lock().await.contains(&channel) {
+        return Ok(false);
+    }
+    pause.await?;
+    Ok(sessions.lock().await.contains(&channel))
+}
 
-    loop {
-        // Let potential state transitions happen first
-        task::yield_now().await;
-        let current_state = {
-            let states = ctx.data().reading_states.lock().await;
-            match states.get(&channel) {
-                Some(state) => (state.0.clone(), state.1, state.2.clone()),
-                None => break,
+/// How a single tossup ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuestionOutcome {
+    /// Someone got it
+    Correct(UserId),
+    /// Nobody got it before time ran out
+    Dead,
+    /// The session was stopped while the question was being read
+    Stopped,
+}
+
+/// Everything that happened on a single tossup
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestionReport {
+    pub outcome: QuestionOutcome,
+    /// Every judged buzz, in order
+    pub buzzes: Vec<ScoredBuzz>,
+    /// How many words the question has, for working out buzz depth
+    pub question_length: usize,
+}
+
+/// Summarize a multi-question session for the channel
+pub fn session_summary(outcomes: &[QuestionOutcome], scoreboard: &Scoreboard) -> String {
+    let read = outcomes
+        .iter()
+        .filter(|outcome| **outcome != QuestionOutcome::Stopped)
+        .count();
+    let dead = outcomes
+        .iter()
+        .filter(|outcome| **outcome == QuestionOutcome::Dead)
+        .count();
+    let mut summary = format!(
+        "📊 **Session summary**\n{} question{} read, {} dead",
+        read,
+        if read == 1 { "" } else { "s" },
+        dead
+    );
+    if outcomes.contains(&QuestionOutcome::Stopped) {
+        summary.push_str(" (stopped early)");
+    }
+    summary.push('\n');
+    summary.push_str(&scoreboard.render());
+    summary
+}
+
+/// A single tossup being read in a channel
+///
+/// Each game runs on its own task and owns its state outright. Everything
+/// else talks to it through its mailbox, so nothing (judging included)
+/// ever happens under a shared lock.
+struct Game {
+    channel: ChannelId,
+    http: Arc<Http>,
+    llm: Arc<dyn LLMProvider>,
+    reqwest: reqwest::Client,
+    judges: Pipeline,
+    games: Games,
+    events: mpsc::UnboundedReceiver<GameEvent>,
+    /// Handed out to judging tasks so they can report back
+    mailbox: mpsc::UnboundedSender<GameEvent>,
+    state: ChannelState,
+    /// The message the question is being read into
+    message: Message,
+    /// What `message` currently says
+    buffer: String,
+    /// When the next `GameEvent::Timeout` fires
+    deadline: Instant,
+    /// Whoever buzzed last, so we know who to credit
+    answering: Option<UserId>,
+    buzzes: Vec<ScoredBuzz>,
+}
+
+impl Game {
+    async fn run(mut self) -> Result<QuestionReport, Error> {
+        let outcome = loop {
+            let event = tokio::select! {
+                // We hold a sender ourselves, so this never actually runs dry
+                event = self.events.recv() => event.unwrap_or(GameEvent::Stop),
+                _ = sleep_until(self.deadline) => GameEvent::Timeout,
+            };
+            match self.handle(event).await {
+                Ok(None) => {}
+                Ok(Some(outcome)) => break Ok(outcome),
+                Err(err) => break Err(err),
             }
         };
-        match &current_state.0 {
-            QuestionState::Reading => {
-                // The numbers here are arbitrarily chosen, empirically tuned
-                // for a balance between reading speed and simulating 180 WPM
-                // speaking speed
-                let chunk = nth_chunk(&mut question, 5);
-                if chunk.is_empty() {
-                    task::yield_now().await;
-                    sleep(Duration::from_secs(1));
-                    if timeout(Duration::from_secs(5), state_change_rx.changed())
-                        .await
-                        .is_ok()
-                    {
-                        continue;
-                    }
-                    channel.say(&ctx.http(), "Time's up!").await?;
-                    let states = ctx.data().reading_states.lock().await;
-                    match states.get(&channel) {
-                        Some(state) => {
-                            channel
-                                .say(&ctx.http(), &render_html(&state.4.answer))
-                                .await?;
-                        }
-                        None => break,
-                    }
-                    break;
+        unregister_game(&mut *self.games.lock().await, self.channel, &self.mailbox);
+        Ok(QuestionReport {
+            outcome: outcome?,
+            buzzes: self.buzzes,
+            question_length: self.state.words.len(),
+        })
+    }
+
+    async fn handle(&mut self, event: GameEvent) -> Result<Option<QuestionOutcome>, Error> {
+        match (self.state.question_state.clone(), event) {
+            (_, GameEvent::Stop) => {
+                self.reveal_answer().await?;
+                return Ok(Some(QuestionOutcome::Stopped));
+            }
+            (QuestionState::Reading, GameEvent::Timeout) => {
+                if self.state.finished_reading() {
+                    self.channel.say(&self.http, "Time's up!").await?;
+                    self.reveal_answer().await?;
+                    return Ok(Some(QuestionOutcome::Dead));
                 }
-                buffer.push(' ');
-                buffer.push_str(&chunk.join(" "));
-
-                message
-                    .edit(
-                        &ctx.http(),
-                        serenity::EditMessage::new().content(buffer.clone()),
-                    )
-                    .await?;
-                {
-                    let mut states = ctx.data().reading_states.lock().await;
-                    if let Some(state) = states.get_mut(&channel) {
-                        state.5.push(' ');
-                        state.5.push_str(&chunk.join(" "));
-                    }
+                self.read_chunk(CHUNK).await?;
+            }
+            (QuestionState::Reading, GameEvent::Buzz(message)) => self.buzz(*message).await?,
+            (
+                QuestionState::Buzzed(user_id, timestamp)
+                | QuestionState::Prompt(user_id, _, timestamp),
+                GameEvent::Answer(message),
+            ) => {
+                if message.author.id != user_id {
+                    return Ok(None);
                 }
-                task::yield_now().await;
-                if timeout(Duration::from_millis(750), state_change_rx.changed())
-                    .await
-                    .is_ok()
-                {
-                    continue;
+                if (message.timestamp.unix_timestamp() - timestamp) > 10 {
+                    debug!("Answer skipped since time limit exceeded");
+                    // The timeout will come around soon enough
+                    return Ok(None);
                 }
+                let prompted = matches!(self.state.question_state, QuestionState::Prompt(..));
+                self.judge(*message, prompted).await?;
             }
-            QuestionState::Buzzed(user_id, _) => {
-                buffer.push_str(":bell:");
-                message
-                    .edit(&ctx.http(), serenity::EditMessage::new().content(&buffer))
-                    .await?;
-                channel
-                    .say(
-                        &ctx.http(),
-                        format!("buzz from {}! 10 seconds to answer", user_id.mention()),
-                    )
-                    .await?;
-                task::yield_now().await;
-
-                // wait until the user answers or the timeout is reached
-                if timeout(Duration::from_secs(10), state_change_rx.changed())
-                    .await
-                    // timeout has been reached
-                    .is_err()
-                {
-                    info!("Time out reached! (buzz)");
-                    let mut states = ctx.data().reading_states.lock().await;
-                    if let Some(state) = states.get_mut(&channel) {
-                        if state.0 == QuestionState::Judging {
-                            // Ok idk what happened here but clearly this is a possible state
-                            // transition
-                            continue;
-                        }
-                        debug!("State transition into invalid (timeout)");
-                        state.0 = QuestionState::Invalid(*user_id);
-                        // Notify about state change
-                        let _ = state.3.send(());
-                    }
+            (
+                QuestionState::Buzzed(user_id, _) | QuestionState::Prompt(user_id, _, _),
+                GameEvent::Timeout,
+            ) => {
+                info!("Time out reached! (buzz)");
+                self.no_answer(user_id, true).await?;
+            }
+            (QuestionState::Judging, GameEvent::Timeout) => {
+                error!("Judge took too long in {}", self.channel);
+                if let Some(user_id) = self.answering {
+                    // Not their fault, so no penalty
+                    self.no_answer(user_id, false).await?;
                 }
-                // otherwise, let the state fallthrough the next loop
             }
-            // nearly identical to Buzzed
-            QuestionState::Prompt(user_id, prompt, _) => {
-                // TODO: figure out why it's sending this twice
-                channel
-                    .say(&ctx.http(), format!("{} {}", prompt, user_id.mention()))
-                    .await?;
-                task::yield_now().await;
-                // wait until the user answers or the timeout is reached
-                if timeout(Duration::from_secs(5), state_change_rx.changed())
-                    .await
-                    // timeout has been reached
-                    .is_err()
-                {
-                    info!("Time out reached! (prompt)");
-                    let mut states = ctx.data().reading_states.lock().await;
-                    if let Some(state) = states.get_mut(&channel) {
-                        if state.0 == QuestionState::Judging {
-                            // Ok idk what happened here but clearly this is a possible state
-                            // transition
-                            continue;
+            (
+                QuestionState::Judging,
+                GameEvent::Verdict {
+                    user_id,
+                    timestamp,
+                    prompted,
+                    response,
+                },
+            ) => {
+                if self.answering != Some(user_id) {
+                    debug!("Ignoring stale verdict for {}", user_id);
+                    return Ok(None);
+                }
+                match response {
+                    Ok(Response::Correct(_)) => {
+                        if self.transition(QuestionState::Correct) {
+                            self.correct(user_id).await?;
+                            return Ok(Some(QuestionOutcome::Correct(user_id)));
+                        }
+                    }
+                    Ok(Response::Prompt(text, _)) if !prompted => {
+                        if !self.transition(QuestionState::Prompt(user_id, text.clone(), timestamp))
+                        {
+                            return Ok(None);
                         }
-                        debug!("State transition into invalid (timeout)");
-                        state.0 = QuestionState::Invalid(*user_id);
-                        // Notify about state change
-                        let _ = state.3.send(());
+                        self.channel
+                            .say(&self.http, format!("{} {}", text, user_id.mention()))
+                            .await?;
+                        self.deadline = Instant::now() + PROMPT_TIMEOUT;
+                    }
+                    Ok(Response::Incorrect(..) | Response::Prompt(..)) => {
+                        self.incorrect(user_id).await?;
+                    }
+                    Err(err) => {
+                        error!("Failed to judge answer: {}", err);
+                        self.channel
+                            .say(&self.http, "Couldn't reach the judge!")
+                            .await?;
+                        self.no_answer(user_id, false).await?;
                     }
                 }
-                continue;
             }
-            QuestionState::Judging => {
-                task::yield_now().await;
-                // Wait for state change
-                state_change_rx.changed().await?;
+            // Anything else (e.g. buzzing while someone else is answering)
+            // doesn't change anything
+            _ => {}
+        }
+        Ok(None)
+    }
 
-                continue;
+    /// Move to the next state, refusing (loudly) if the transition table doesn't allow it
+    fn transition(&mut self, next: QuestionState) -> bool {
+        match self.state.question_state.transition(next) {
+            Ok(next) => {
+                debug!("State transition into {:?}", next);
+                self.state.question_state = next;
+                true
             }
-            QuestionState::Invalid(user_id) => {
-                let mut states = ctx.data().reading_states.lock().await;
-                if let Some(state) = states.get_mut(&channel) {
-                    state.0 = QuestionState::Reading;
-                    state.1 = !formatted.contains("(*)");
-                    state.2.insert(*user_id);
-                    // Notify about state change
-                    let _ = state.3.send(());
-                }
-                channel.say(&ctx.http(), "No answer!").await?;
-                buffer = buffer.replace(":bell:", ":no_bell:");
-                message = channel.say(&ctx.http(), &buffer).await?;
-                continue;
+            Err(err) => {
+                warn!(
+                    "Rejected {} in channel {} (user: {:?})",
+                    err,
+                    self.channel,
+                    err.to.user().or(err.from.user()).or(self.answering)
+                );
+                false
             }
+        }
+    }
 
-            QuestionState::Incorrect(user_id) => {
-                let mut states = ctx.data().reading_states.lock().await;
-                if let Some(state) = states.get_mut(&channel) {
-                    state.0 = QuestionState::Reading;
-                    state.1 = !formatted.contains("(*)");
-                    state.2.insert(*user_id);
-                    // Notify about state change
-                    let _ = state.3.send(());
-                }
-                channel.say(&ctx.http(), "incorrect!").await?;
-                buffer = buffer.replace(":bell:", ":no_bell:");
-                message = channel.say(&ctx.http(), &buffer).await?;
-                continue;
-            }
-            QuestionState::Correct => {
-                if formatted.contains("(\\*)") && !buffer.contains("(\\*)") {
-                    channel.say(&ctx.http(), "Correct - power!").await?;
-                } else {
-                    channel.say(&ctx.http(), "Correct").await?;
-                }
-                // reveal correct answer
-                buffer.push(' ');
-                buffer.push_str(&question.collect::<Vec<&str>>().join(" "));
-                message
-                    // no need to clone since we won't ever use it again
-                    .edit(&ctx.http(), serenity::EditMessage::new().content(buffer))
-                    .await?;
-                // TODO: bold matching parts
-
-                let states = ctx.data().reading_states.lock().await;
-                match states.get(&channel) {
-                    Some(state) => {
-                        channel
-                            .say(&ctx.http(), &render_html(&state.4.answer))
-                            .await?;
-                    }
-                    None => break,
-                }
+    /// Go back to reading after someone's turn is over
+    fn resume_reading(&mut self) {
+        if self.transition(QuestionState::Reading) {
+            self.schedule_tick();
+        }
+    }
 
-                break;
-            }
+    /// Schedule the next chunk, giving people the full dead time if we're done
+    fn schedule_tick(&mut self) {
+        self.deadline = Instant::now()
+            + if self.state.finished_reading() {
+                DEAD_TIME
+            } else {
+                TICK
+            };
+    }
+
+    async fn read_chunk(&mut self, size: usize) -> Result<(), Error> {
+        let chunk = self.state.advance(size);
+        self.buffer.push(' ');
+        self.buffer.push_str(&chunk);
+        self.message
+            .edit(
+                &self.http,
+                serenity::EditMessage::new().content(self.buffer.clone()),
+            )
+            .await?;
+        self.schedule_tick();
+        Ok(())
+    }
+
+    async fn buzz(&mut self, message: Message) -> Result<(), Error> {
+        let user_id = message.author.id;
+        if !self.state.can_buzz(user_id) {
+            debug!("Buzz skipped since user is locked out (or not on a team)");
+            message
+                .react(&self.http, ReactionType::Unicode("❌".into()))
+                .await?;
+            return Ok(());
+        }
+        // I'm going to use Discord's timestamps
+        if !self.transition(QuestionState::Buzzed(
+            user_id,
+            message.timestamp.unix_timestamp(),
+        )) {
+            return Ok(());
         }
+        self.answering = Some(user_id);
+        self.state.buzz_index = self.state.words_read;
+        self.deadline = Instant::now() + BUZZ_TIMEOUT;
+
+        self.buffer.push_str(":bell:");
+        self.message
+            .edit(
+                &self.http,
+                serenity::EditMessage::new().content(&self.buffer),
+            )
+            .await?;
+        self.channel
+            .say(
+                &self.http,
+                format!("buzz from {}! 10 seconds to answer", user_id.mention()),
+            )
+            .await?;
+        Ok(())
     }
 
-    ctx.data().reading_states.lock().await.remove(&channel);
-    Ok(())
+    /// Hand the answer off to the judge, who reports back with a `GameEvent::Verdict`
+    async fn judge(&mut self, message: Message, prompted: bool) -> Result<(), Error> {
+        if !self.transition(QuestionState::Judging) {
+            return Ok(());
+        }
+        self.deadline = Instant::now() + JUDGE_TIMEOUT;
+        message.reply(&self.http, "Judging...").await?;
+
+        let llm = self.llm.clone();
+        let reqwest = self.reqwest.clone();
+        let judges = self.judges.clone();
+        let mailbox = self.mailbox.clone();
+        let question_so_far = self.state.question_so_far();
+        let answer_key = (
+            self.state.tossup.answer.clone(),
+            self.state.tossup.answer_sanitized.clone(),
+        );
+        tokio::spawn(async move {
+            let response = judges
+                .judge(
+                    llm.as_ref(),
+                    &reqwest,
+                    &question_so_far,
+                    &message.content,
+                    &answer_key,
+                    prompted,
+                )
+                .await;
+            // If the game is gone, nobody cares about the verdict anymore
+            let _ = mailbox.send(GameEvent::Verdict {
+                user_id: message.author.id,
+                timestamp: message.timestamp.unix_timestamp(),
+                prompted,
+                response,
+            });
+        });
+        Ok(())
+    }
+
+    /// Score the current buzz, remembering it for the report
+    fn score(&mut self, user_id: UserId, correct: bool) -> BuzzKind {
+        let kind = self.state.score(correct);
+        self.buzzes.push(ScoredBuzz {
+            user_id,
+            word_index: self.state.buzz_index,
+            kind,
+        });
+        kind
+    }
+
+    /// Not answering in time counts as answering incorrectly (when `penalize` is set)
+    async fn no_answer(&mut self, user_id: UserId, penalize: bool) -> Result<(), Error> {
+        if !self.transition(QuestionState::Invalid(user_id)) {
+            return Ok(());
+        }
+        let mut text = "No answer!".to_string();
+        if penalize {
+            text.push_str(&points_suffix(self.score(user_id, false)));
+        }
+        self.channel.say(&self.http, text).await?;
+        self.lock_out(user_id).await
+    }
+
+    async fn incorrect(&mut self, user_id: UserId) -> Result<(), Error> {
+        if !self.transition(QuestionState::Incorrect(user_id)) {
+            return Ok(());
+        }
+        let kind = self.score(user_id, false);
+        self.channel
+            .say(&self.http, format!("incorrect!{}", points_suffix(kind)))
+            .await?;
+        self.lock_out(user_id).await
+    }
+
+    /// Keep the user (or their team) from buzzing again and carry on reading in a fresh message
+    async fn lock_out(&mut self, user_id: UserId) -> Result<(), Error> {
+        self.state.lock_out(user_id);
+        self.buffer = self.buffer.replace(":bell:", ":no_bell:");
+        self.message = self.channel.say(&self.http, &self.buffer).await?;
+        self.resume_reading();
+        Ok(())
+    }
+
+    async fn correct(&mut self, user_id: UserId) -> Result<(), Error> {
+        let kind = self.score(user_id, true);
+        let text = if kind == BuzzKind::Power {
+            "Correct - power!"
+        } else {
+            "Correct"
+        };
+        self.channel
+            .say(&self.http, format!("{}{}", text, points_suffix(kind)))
+            .await?;
+        // reveal the rest of the question
+        let rest = self.state.advance(usize::MAX);
+        if !rest.is_empty() {
+            self.buffer.push(' ');
+            self.buffer.push_str(&rest);
+        }
+        self.message
+            .edit(
+                &self.http,
+                serenity::EditMessage::new().content(self.buffer.clone()),
+            )
+            .await?;
+        // TODO: bold matching parts
+        self.reveal_answer().await
+    }
+
+    async fn reveal_answer(&self) -> Result<(), Error> {
+        self.channel
+            .say(&self.http, render_html(&self.state.tossup.answer))
+            .await?;
+        Ok(())
+    }
+}
+
+/// e.g. " (+15)", or nothing when the buzz didn't change the score
+fn points_suffix(kind: BuzzKind) -> String {
+    match kind.points() {
+        0 => String::new(),
+        points => format!(" ({:+})", points),
+    }
+}
+
+/// Read a tossup in the current channel, resolving once it's over
+///
+/// With a `roster`, it's played in team mode: only players on a team can
+/// buzz, and a wrong answer locks out the whole team.
+pub async fn read_question(
+    ctx: &Context<'_>,
+    tossup: &Tossup,
+    say: bool,
+    roster: Option<Roster>,
+) -> Result<QuestionReport, Error> {
+    let channel = ctx.channel_id();
+    let mut state = ChannelState::new(tossup.clone());
+    if let Some(roster) = roster {
+        state = state.with_roster(roster);
+    }
+    // Start off with a small number of words
+    let buffer = state.advance(FIRST_CHUNK);
+
+    let data = ctx.data();
+    let (mailbox, events) = mpsc::unbounded_channel();
+    if !register_game(&mut *data.games.lock().await, channel, &mailbox) {
+        return Err("A question is already being read in this channel".into());
+    }
+
+    let message = if say {
+        ctx.say(buffer.clone()).await?.into_message().await?
+    } else {
+        channel.say(&ctx.http(), buffer.clone()).await?
+    };
+
+    let mut game = Game {
+        channel,
+        http: ctx.serenity_context().http.clone(),
+        llm: data.llm.clone(),
+        reqwest: data.reqwest.clone(),
+        judges: crate::judge_pipeline(*ctx).await,
+        games: data.games.clone(),
+        events,
+        mailbox,
+        state,
+        message,
+        buffer,
+        deadline: Instant::now(),
+        answering: None,
+        buzzes: Vec::new(),
+    };
+    game.schedule_tick();
+
+    // The game ends (and this resolves) once the question is over
+    tokio::spawn(game.run()).await?
 }
 
 // #[instrument]
 pub async fn event_handler(
-    ctx: &serenity::Context,
+    _ctx: &serenity::Context,
     event: &serenity::FullEvent,
     _framework: poise::FrameworkContext<'_, Data, Error>,
     data: &Data,
@@ -263,140 +561,29 @@ pub async fn event_handler(
         serenity::FullEvent::Ready { data_about_bot, .. } => {
             debug!("{} is connected!", data_about_bot.user.name);
         }
-        // Only manage state transitions
+        // Only forward messages to the game in that channel, if there is one
         serenity::FullEvent::Message { new_message } => {
             if new_message.author.bot {
                 return Ok(());
             }
-            let current_state = {
-                let states = data.reading_states.lock().await;
-                match states.get(&new_message.channel_id) {
-                    // Yo... is cloning all of this fine?
-                    Some(state) => (
-                        state.0.clone(),
-                        state.1,
-                        state.2.contains(&new_message.author.id),
-                        // Answer sanitized
-                        (state.4.answer.clone(), state.4.answer_sanitized.clone()),
-                        // Question so far
-                        state.5.clone(),
-                    ),
-                    None => {
-                        return Ok(());
-                    }
-                }
+            // Only hold the lock long enough to find the game
+            let Some(game) = data
+                .games
+                .lock()
+                .await
+                .get(&new_message.channel_id)
+                .cloned()
+            else {
+                return Ok(());
             };
-            match &current_state.0 {
-                QuestionState::Reading => {
-                    if new_message.content.to_lowercase() != "buzz" {
-                        return Ok(());
-                    }
-                    let channel_id = new_message.channel_id;
-                    if current_state.2 {
-                        debug!("Buzz skipped since user already buzzed");
-                        new_message
-                            .react(&ctx.http, ReactionType::Unicode("❌".into()))
-                            .await?;
-                        return Ok(());
-                    };
-
-                    // State transition
-                    {
-                        let mut states = data.reading_states.lock().await;
-                        if let Some(state) = states.get_mut(&channel_id) {
-                            debug!("State transition into Buzzed");
-                            state.0 = QuestionState::Buzzed(
-                                new_message.author.id,
-                                // I'm going to use Discord's timestamps
-                                new_message.timestamp.unix_timestamp(),
-                            );
-                            // Notify about state change
-                            let _ = state.3.send(());
-                        }
-                    }
-                }
-                QuestionState::Buzzed(user_id, timestamp) => {
-                    if *user_id != new_message.author.id {
-                        return Ok(());
-                    }
-                    let new_message_timestamp = new_message.timestamp.unix_timestamp();
-                    if (new_message_timestamp - timestamp) > 10 {
-                        debug!("Buzz skipped since time limit exceeded");
-                        // State transition to invalid is bound to happen
-                        return Ok(());
-                    }
-                    let mut states = data.reading_states.lock().await;
-                    if let Some(state) = states.get_mut(&new_message.channel_id) {
-                        new_message.reply(&ctx.http, "Judging...").await?;
-                        state.0 = QuestionState::Judging;
-                        let _ = state.3.send(());
-                        state.0 = match check_correct_answer(
-                            &data.llm,
-                            &data.reqwest,
-                            &current_state.4,
-                            new_message.content.as_str(),
-                            &current_state.3,
-                            false,
-                        )
-                        .await
-                        .map_err(|_|"Failed to access LLM")?
-                        // State transition
-                        {
-                            Response::Correct => QuestionState::Correct,
-                            Response::Incorrect(_) => {
-                                state.2.insert(new_message.author.id);
-                                QuestionState::Incorrect(new_message.author.id)
-                            }
-                            Response::Prompt(text) => QuestionState::Prompt(
-                                new_message.author.id,
-                                text,
-                                new_message_timestamp,
-                            ),
-                        };
-                        // Notify about state change
-                        let _ = state.3.send(());
-                    }
-                }
-                QuestionState::Prompt(user_id, _, timestamp) => {
-                    if *user_id != new_message.author.id {
-                        return Ok(());
-                    }
-                    let new_message_timestamp = new_message.timestamp.unix_timestamp();
-                    if (new_message_timestamp - timestamp) > 10 {
-                        debug!("Buzz skipped since time limit exceeded");
-                        // State transition to invalid is bound to happen
-                        return Ok(());
-                    }
-                    let mut states = data.reading_states.lock().await;
-                    if let Some(state) = states.get_mut(&new_message.channel_id) {
-                        new_message.reply(&ctx.http, "Judging...").await?;
-                        state.0 = QuestionState::Judging;
-                        let _ = state.3.send(());
-
-                        state.0 = match check_correct_answer(
-                            &data.llm,
-                            &data.reqwest,
-                            &current_state.4,
-                            new_message.content.as_str(),
-                            &current_state.3,
-                            true,
-                        )
-                        .await
-                        .map_err(|_|"Failed to access LLM")?
-                        // State transition
-                        {
-                            Response::Correct => QuestionState::Correct,
-                            Response::Incorrect(_) | Response::Prompt(_) => {
-                                state.2.insert(new_message.author.id);
-                                QuestionState::Incorrect(new_message.author.id)
-                            }
-                        };
-                        // Notify about state change
-                        let _ = state.3.send(());
-                    }
-                }
-                _ => {}
-            }
+            let message = Box::new(new_message.clone());
+            let event = if new_message.content.to_lowercase() == "buzz" {
+                GameEvent::Buzz(message)
+            } else {
+                GameEvent::Answer(message)
+            };
+            // The game might have just ended, in which case there's nobody to tell
+            let _ = game.send(event);
         }
         _ => {}
     }
@@ -0,0 +1,314 @@
+#[cfg(test)]
+mod tests {
+    use crate::qb::{Packet, Set, Tossup};
+    use crate::read::*;
+    use crate::score::{BuzzKind, Scoreboard, ScoredBuzz};
+    use crate::teams::Roster;
+    use crate::{ChannelState, QuestionState};
+    use serenity::all::{ChannelId, UserId};
+    use std::collections::{HashMap, HashSet};
+    use tokio::sync::{mpsc, Mutex};
+
+    fn tossup(question: &str) -> Tossup {
+        Tossup {
+            id: "tossup_id".to_string(),
+            question: question.to_string(),
+            answer: "<b>Paris</b>".to_string(),
+            category: "Geography".to_string(),
+            subcategory: "Geography".to_string(),
+            packet: Packet {
+                id: "packet_id".to_string(),
+                name: "Test Packet".to_string(),
+                number: 1,
+            },
+            set: Set {
+                id: "set_id".to_string(),
+                name: "Test Set".to_string(),
+                year: 2023,
+                standard: true,
+            },
+            updated_at: "2023-01-01T00:00:00Z".to_string(),
+            difficulty: 3,
+            number: 1,
+            answer_sanitized: "Paris".to_string(),
+            question_sanitized: question.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_channel_state_starts_reading() {
+        let state = ChannelState::new(tossup("This city is the capital of France."));
+        assert_eq!(state.question_state, QuestionState::Reading);
+        assert!(state.blocklist.is_empty());
+        assert_eq!(state.words.len(), 7);
+        assert_eq!(state.words_read, 0);
+        assert_eq!(state.question_so_far(), "");
+    }
+
+    #[test]
+    fn test_channel_state_advance() {
+        let mut state = ChannelState::new(tossup("This city is the capital of France."));
+        assert_eq!(state.advance(3), "This city is");
+        assert_eq!(state.question_so_far(), "This city is");
+        assert!(!state.finished_reading());
+        assert_eq!(state.advance(5), "the capital of France.");
+        assert!(state.finished_reading());
+        // Nothing left to read
+        assert_eq!(state.advance(5), "");
+        assert_eq!(state.words_read, 7);
+    }
+
+    #[test]
+    fn test_channel_state_escapes_power_mark() {
+        let mut state = ChannelState::new(tossup("A (*) B"));
+        assert_eq!(state.advance(usize::MAX), "A (\\*) B");
+    }
+
+    #[test]
+    fn test_lock_out_individual() {
+        let mut state = ChannelState::new(tossup("A B C"));
+        state.lock_out(UserId::new(1));
+        assert!(!state.can_buzz(UserId::new(1)));
+        assert!(state.can_buzz(UserId::new(2)));
+    }
+
+    #[test]
+    fn test_lock_out_whole_team() {
+        let roster = Roster::from([
+            (UserId::new(1), "Red".to_string()),
+            (UserId::new(2), "Red".to_string()),
+            (UserId::new(3), "Blue".to_string()),
+        ]);
+        let mut state = ChannelState::new(tossup("A B C")).with_roster(roster);
+        assert!(state.can_buzz(UserId::new(2)));
+        // Players who aren't on a team can't buzz in team mode
+        assert!(!state.can_buzz(UserId::new(4)));
+
+        state.lock_out(UserId::new(1));
+        assert!(!state.can_buzz(UserId::new(1)));
+        assert!(!state.can_buzz(UserId::new(2)));
+        assert!(state.can_buzz(UserId::new(3)));
+    }
+
+    #[test]
+    fn test_session_summary_counts() {
+        let alice = UserId::new(1);
+        let bob = UserId::new(2);
+        let mut scoreboard = Scoreboard::default();
+        scoreboard.record(&[ScoredBuzz {
+            user_id: bob,
+            word_index: 12,
+            kind: BuzzKind::Ten,
+        }]);
+        scoreboard.record(&[ScoredBuzz {
+            user_id: alice,
+            word_index: 3,
+            kind: BuzzKind::Power,
+        }]);
+        let summary = session_summary(
+            &[
+                QuestionOutcome::Correct(bob),
+                QuestionOutcome::Dead,
+                QuestionOutcome::Correct(alice),
+            ],
+            &scoreboard,
+        );
+        assert!(summary.contains("3 questions read, 1 dead"), "{}", summary);
+        assert!(!summary.contains("stopped early"));
+        // Alice scored more, so she's listed first
+        let alice_line = summary.find("<@1>: 15 points (1/0/0)").unwrap();
+        let bob_line = summary.find("<@2>: 10 points (0/1/0)").unwrap();
+        assert!(alice_line < bob_line);
+    }
+
+    #[test]
+    fn test_session_summary_stopped() {
+        let summary = session_summary(
+            &[QuestionOutcome::Dead, QuestionOutcome::Stopped],
+            &Scoreboard::default(),
+        );
+        assert!(summary.contains("1 question read, 1 dead"), "{}", summary);
+        assert!(summary.contains("stopped early"));
+    }
+
+    #[test]
+    fn test_session_summary_empty() {
+        let summary = session_summary(&[], &Scoreboard::default());
+        assert!(summary.contains("0 questions read, 0 dead"), "{}", summary);
+    }
+
+    #[test]
+    fn test_channel_state_power_index() {
+        let state = ChannelState::new(tossup("One two (*) three four"));
+        assert_eq!(state.power_index, Some(2));
+        let state = ChannelState::new(tossup("No power mark here"));
+        assert_eq!(state.power_index, None);
+    }
+
+    #[test]
+    fn test_channel_state_scores_by_buzz_position() {
+        let mut state = ChannelState::new(tossup("One two (*) three four"));
+        state.advance(2);
+        state.buzz_index = state.words_read;
+        assert_eq!(state.score(true), BuzzKind::Power);
+        assert_eq!(state.score(false), BuzzKind::Neg);
+
+        state.advance(1);
+        state.buzz_index = state.words_read;
+        assert_eq!(state.score(true), BuzzKind::Ten);
+
+        state.advance(usize::MAX);
+        state.buzz_index = state.words_read;
+        assert_eq!(state.score(false), BuzzKind::NoPenalty);
+    }
+
+    fn all_states(user_id: UserId) -> Vec<QuestionState> {
+        vec![
+            QuestionState::Reading,
+            QuestionState::Buzzed(user_id, 0),
+            QuestionState::Prompt(user_id, "Be more specific".to_string(), 0),
+            QuestionState::Invalid(user_id),
+            QuestionState::Incorrect(user_id),
+            QuestionState::Correct,
+            QuestionState::Judging,
+        ]
+    }
+
+    /// Every edge in the state diagram (plus judging and prompts)
+    fn legal_edges(user_id: UserId) -> Vec<(QuestionState, QuestionState)> {
+        let prompt = QuestionState::Prompt(user_id, "Be more specific".to_string(), 0);
+        vec![
+            (QuestionState::Reading, QuestionState::Buzzed(user_id, 0)),
+            (QuestionState::Buzzed(user_id, 0), QuestionState::Judging),
+            (
+                QuestionState::Buzzed(user_id, 0),
+                QuestionState::Invalid(user_id),
+            ),
+            (QuestionState::Judging, QuestionState::Correct),
+            (QuestionState::Judging, QuestionState::Incorrect(user_id)),
+            (QuestionState::Judging, prompt.clone()),
+            (QuestionState::Judging, QuestionState::Invalid(user_id)),
+            (prompt.clone(), QuestionState::Judging),
+            (prompt, QuestionState::Invalid(user_id)),
+            (QuestionState::Invalid(user_id), QuestionState::Reading),
+            (QuestionState::Incorrect(user_id), QuestionState::Reading),
+        ]
+    }
+
+    #[test]
+    fn test_legal_transitions() {
+        let user_id = UserId::new(1);
+        for (from, to) in legal_edges(user_id) {
+            assert!(from.can_transition_to(&to), "{:?} -> {:?}", from, to);
+            assert_eq!(from.transition(to.clone()), Ok(to));
+        }
+    }
+
+    #[test]
+    fn test_everything_else_is_illegal() {
+        let user_id = UserId::new(1);
+        let legal = legal_edges(user_id);
+        for from in all_states(user_id) {
+            for to in all_states(user_id) {
+                if legal.contains(&(from.clone(), to.clone())) {
+                    continue;
+                }
+                let err = from.transition(to.clone()).unwrap_err();
+                assert_eq!(err.from, from);
+                assert_eq!(err.to, to);
+            }
+        }
+    }
+
+    #[test]
+    fn test_correct_is_terminal() {
+        let user_id = UserId::new(1);
+        for to in all_states(user_id) {
+            assert!(!QuestionState::Correct.can_transition_to(&to), "{:?}", to);
+        }
+    }
+
+    #[test]
+    fn test_transition_keeps_the_same_user() {
+        let alice = UserId::new(1);
+        let bob = UserId::new(2);
+        // Someone else's buzz can't time out on your behalf
+        assert!(!QuestionState::Buzzed(alice, 0).can_transition_to(&QuestionState::Invalid(bob)));
+        assert!(!QuestionState::Prompt(alice, String::new(), 0)
+            .can_transition_to(&QuestionState::Invalid(bob)));
+        assert!(QuestionState::Buzzed(alice, 0).can_transition_to(&QuestionState::Invalid(alice)));
+    }
+
+    #[test]
+    fn test_illegal_transition_message() {
+        let err = QuestionState::Correct
+            .transition(QuestionState::Reading)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "illegal transition Correct -> Reading");
+    }
+
+    #[test]
+    fn test_state_user() {
+        let user_id = UserId::new(7);
+        assert_eq!(QuestionState::Reading.user(), None);
+        assert_eq!(QuestionState::Judging.user(), None);
+        assert_eq!(QuestionState::Correct.user(), None);
+        assert_eq!(QuestionState::Buzzed(user_id, 0).user(), Some(user_id));
+        assert_eq!(QuestionState::Incorrect(user_id).user(), Some(user_id));
+    }
+
+    #[tokio::test]
+    async fn test_stop_between_questions() {
+        let channel = ChannelId::new(1);
+        let sessions = Mutex::new(HashSet::from([channel]));
+        let mut read = Vec::new();
+        for index in 0..3 {
+            if index > 0 {
+                // `/stop` lands during the pause after the first question
+                let pause = async {
+                    sessions.lock().await.remove(&channel);
+                    Ok(())
+                };
+                if !still_going_after(&sessions, channel, pause).await.unwrap() {
+                    break;
+                }
+            }
+            read.push(index);
+        }
+        assert_eq!(read, vec![0]);
+    }
+
+    #[tokio::test]
+    async fn test_still_going_after_pause() {
+        let channel = ChannelId::new(1);
+        let sessions = Mutex::new(HashSet::from([channel]));
+        assert!(still_going_after(&sessions, channel, async { Ok(()) })
+            .await
+            .unwrap());
+        // Already stopped, so there's no pause at all
+        let other = ChannelId::new(2);
+        let pause = async { panic!("paused for a stopped session") };
+        assert!(!still_going_after(&sessions, other, pause).await.unwrap());
+    }
+
+    #[test]
+    fn test_one_game_per_channel() {
+        let channel = ChannelId::new(1);
+        let mut games = HashMap::new();
+        let (first, first_events) = mpsc::unbounded_channel();
+        let (second, _second_events) = mpsc::unbounded_channel();
+        assert!(register_game(&mut games, channel, &first));
+        // The first game's still running
+        assert!(!register_game(&mut games, channel, &second));
+        unregister_game(&mut games, channel, &second);
+        assert!(games[&channel].same_channel(&first));
+        // Once it's over, the next game can take the channel
+        drop(first_events);
+        assert!(register_game(&mut games, channel, &second));
+        // And the old game finishing up doesn't take the new one's mailbox down
+        unregister_game(&mut games, channel, &first);
+        assert!(games[&channel].same_channel(&second));
+        unregister_game(&mut games, channel, &second);
+        assert!(games.is_empty());
+    }
+}
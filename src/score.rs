@@ -0,0 +1,223 @@
+/// Tossup scoring: powers, tens and negs
+use std::collections::HashMap;
+
+use ::serenity::all::{ChannelId, Mentionable, UserId};
+
+use crate::teams::Roster;
+
+pub const POWER_POINTS: i32 = 15;
+pub const TEN_POINTS: i32 = 10;
+pub const NEG_POINTS: i32 = -5;
+
+/// What a single buzz ended up being worth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuzzKind {
+    /// Correct before the power mark
+    Power,
+    /// Correct after the power mark (or on a question without one)
+    Ten,
+    /// Incorrect before the question finished
+    Neg,
+    /// Incorrect after the question finished, which costs nothing
+    NoPenalty,
+}
+
+impl BuzzKind {
+    pub fn points(self) -> i32 {
+        match self {
+            BuzzKind::Power => POWER_POINTS,
+            BuzzKind::Ten => TEN_POINTS,
+            BuzzKind::Neg => NEG_POINTS,
+            BuzzKind::NoPenalty => 0,
+        }
+    }
+
+    pub fn is_correct(self) -> bool {
+        matches!(self, BuzzKind::Power | BuzzKind::Ten)
+    }
+
+    /// What gets stored in the database
+    pub fn name(self) -> &'static str {
+        match self {
+            BuzzKind::Power => "power",
+            BuzzKind::Ten => "ten",
+            BuzzKind::Neg => "neg",
+            BuzzKind::NoPenalty => "no_penalty",
+        }
+    }
+}
+
+/// Score a buzz by standard tossup rules
+///
+/// `word_index` is how many words had been read when the player buzzed,
+/// `power_index` is the index of the word holding the power mark (if any).
+pub fn score_buzz(
+    correct: bool,
+    word_index: usize,
+    power_index: Option<usize>,
+    question_length: usize,
+) -> BuzzKind {
+    match (correct, power_index) {
+        // The power mark hasn't been read yet
+        (true, Some(power_index)) if word_index <= power_index => BuzzKind::Power,
+        (true, _) => BuzzKind::Ten,
+        (false, _) if word_index < question_length => BuzzKind::Neg,
+        (false, _) => BuzzKind::NoPenalty,
+    }
+}
+
+/// A buzz, after it's been judged and scored
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoredBuzz {
+    pub user_id: UserId,
+    /// How many words had been read when they buzzed
+    pub word_index: usize,
+    pub kind: BuzzKind,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerScore {
+    pub points: i32,
+    pub powers: u32,
+    pub tens: u32,
+    pub negs: u32,
+}
+
+impl PlayerScore {
+    pub fn record(&mut self, kind: BuzzKind) {
+        self.points += kind.points();
+        match kind {
+            BuzzKind::Power => self.powers += 1,
+            BuzzKind::Ten => self.tens += 1,
+            BuzzKind::Neg => self.negs += 1,
+            BuzzKind::NoPenalty => {}
+        }
+    }
+
+    /// e.g. "25 points (1/1/0)", in the usual powers/tens/negs notation
+    pub fn line(&self) -> String {
+        format!(
+            "{} points ({}/{}/{})",
+            self.points, self.powers, self.tens, self.negs
+        )
+    }
+}
+
+/// Scores for a single session
+#[derive(Debug, Clone, Default)]
+pub struct Scoreboard {
+    pub players: HashMap<UserId, PlayerScore>,
+    /// Team totals, kept separately from the players' own
+    pub teams: HashMap<String, PlayerScore>,
+    /// Set in team mode
+    pub roster: Option<Roster>,
+    pub tossups_heard: u32,
+}
+
+impl Scoreboard {
+    /// A team mode scoreboard, with every team starting at zero
+    pub fn with_teams(roster: Roster) -> Self {
+        Scoreboard {
+            teams: roster
+                .values()
+                .map(|team| (team.clone(), PlayerScore::default()))
+                .collect(),
+            roster: Some(roster),
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, buzzes: &[ScoredBuzz]) {
+        self.tossups_heard += 1;
+        for buzz in buzzes {
+            self.players
+                .entry(buzz.user_id)
+                .or_default()
+                .record(buzz.kind);
+            if let Some(team) = self
+                .roster
+                .as_ref()
+                .and_then(|roster| roster.get(&buzz.user_id))
+            {
+                self.teams
+                    .entry(team.clone())
+                    .or_default()
+                    .record(buzz.kind);
+            }
+        }
+    }
+
+    /// Teams from highest to lowest score (ties broken by name)
+    pub fn team_standings(&self) -> Vec<(String, PlayerScore)> {
+        let mut standings: Vec<_> = self
+            .teams
+            .iter()
+            .map(|(team, score)| (team.clone(), *score))
+            .collect();
+        standings.sort_by(|a, b| b.1.points.cmp(&a.1.points).then(a.0.cmp(&b.0)));
+        standings
+    }
+
+    /// Players from highest to lowest score (ties broken by user id so the order is stable)
+    pub fn standings(&self) -> Vec<(UserId, PlayerScore)> {
+        let mut standings: Vec<_> = self
+            .players
+            .iter()
+            .map(|(user_id, score)| (*user_id, *score))
+            .collect();
+        standings.sort_by(|a, b| b.1.points.cmp(&a.1.points).then(a.0.cmp(&b.0)));
+        standings
+    }
+
+    /// Team totals first (in team mode), then the players
+    pub fn render(&self) -> String {
+        let mut lines: Vec<String> = self
+            .team_standings()
+            .iter()
+            .map(|(team, score)| format!("• **{}**: {}", team, score.line()))
+            .collect();
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        if self.players.is_empty() {
+            lines.push("*Nobody has scored yet.*".to_string());
+        }
+        lines.extend(
+            self.standings()
+                .iter()
+                .map(|(user_id, score)| format!("• {}: {}", user_id.mention(), score.line())),
+        );
+        lines.join("\n")
+    }
+}
+
+/// Every score the bot keeps track of (in memory)
+#[derive(Debug, Default)]
+pub struct Scores {
+    /// Running totals for each player
+    pub players: HashMap<UserId, PlayerScore>,
+    /// The current (or most recent) session in each channel
+    pub channels: HashMap<ChannelId, Scoreboard>,
+}
+
+impl Scores {
+    /// Start a fresh scoreboard for the channel (in team mode if there's a `roster`)
+    pub fn start_session(&mut self, channel: ChannelId, roster: Option<Roster>) {
+        let scoreboard = match roster {
+            Some(roster) => Scoreboard::with_teams(roster),
+            None => Scoreboard::default(),
+        };
+        self.channels.insert(channel, scoreboard);
+    }
+
+    /// Record the buzzes on a single tossup read in `channel`
+    pub fn record(&mut self, channel: ChannelId, buzzes: &[ScoredBuzz]) {
+        self.channels.entry(channel).or_default().record(buzzes);
+        for buzz in buzzes {
+            self.players
+                .entry(buzz.user_id)
+                .or_default()
+                .record(buzz.kind);
+        }
+    }
+}
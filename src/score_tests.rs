@@ -0,0 +1,164 @@
+#[cfg(test)]
+mod tests {
+    use crate::score::*;
+    use crate::teams::Roster;
+    use serenity::all::{ChannelId, UserId};
+
+    fn buzz(user: u64, kind: BuzzKind) -> ScoredBuzz {
+        ScoredBuzz {
+            user_id: UserId::new(user),
+            word_index: 0,
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_points() {
+        assert_eq!(BuzzKind::Power.points(), 15);
+        assert_eq!(BuzzKind::Ten.points(), 10);
+        assert_eq!(BuzzKind::Neg.points(), -5);
+        assert_eq!(BuzzKind::NoPenalty.points(), 0);
+    }
+
+    #[test]
+    fn test_power_before_mark() {
+        // Power mark is the 11th word, and only 10 words were read
+        assert_eq!(score_buzz(true, 10, Some(10), 40), BuzzKind::Power);
+        assert_eq!(score_buzz(true, 0, Some(10), 40), BuzzKind::Power);
+    }
+
+    #[test]
+    fn test_ten_after_mark() {
+        // The mark itself was read
+        assert_eq!(score_buzz(true, 11, Some(10), 40), BuzzKind::Ten);
+        assert_eq!(score_buzz(true, 40, Some(10), 40), BuzzKind::Ten);
+    }
+
+    #[test]
+    fn test_ten_without_mark() {
+        assert_eq!(score_buzz(true, 3, None, 40), BuzzKind::Ten);
+    }
+
+    #[test]
+    fn test_neg_before_end() {
+        assert_eq!(score_buzz(false, 3, Some(10), 40), BuzzKind::Neg);
+        assert_eq!(score_buzz(false, 39, None, 40), BuzzKind::Neg);
+    }
+
+    #[test]
+    fn test_no_penalty_after_end() {
+        assert_eq!(score_buzz(false, 40, Some(10), 40), BuzzKind::NoPenalty);
+    }
+
+    #[test]
+    fn test_player_score_record() {
+        let mut score = PlayerScore::default();
+        score.record(BuzzKind::Power);
+        score.record(BuzzKind::Ten);
+        score.record(BuzzKind::Neg);
+        score.record(BuzzKind::NoPenalty);
+        assert_eq!(score.points, 20);
+        assert_eq!((score.powers, score.tens, score.negs), (1, 1, 1));
+        assert_eq!(score.line(), "20 points (1/1/1)");
+    }
+
+    #[test]
+    fn test_scoreboard_standings() {
+        let mut scoreboard = Scoreboard::default();
+        scoreboard.record(&[buzz(1, BuzzKind::Neg), buzz(2, BuzzKind::Ten)]);
+        scoreboard.record(&[buzz(3, BuzzKind::Ten)]);
+        scoreboard.record(&[]);
+        assert_eq!(scoreboard.tossups_heard, 3);
+
+        let standings = scoreboard.standings();
+        let order: Vec<_> = standings.iter().map(|(user, _)| user.get()).collect();
+        // Tied players are ordered by id
+        assert_eq!(order, vec![2, 3, 1]);
+        assert_eq!(standings[2].1.points, -5);
+    }
+
+    #[test]
+    fn test_scoreboard_render() {
+        let mut scoreboard = Scoreboard::default();
+        assert!(scoreboard.render().contains("Nobody"));
+        scoreboard.record(&[buzz(1, BuzzKind::Power)]);
+        assert_eq!(scoreboard.render(), "• <@1>: 15 points (1/0/0)");
+    }
+
+    #[test]
+    fn test_scores_session_and_totals() {
+        let channel = ChannelId::new(10);
+        let mut scores = Scores::default();
+        scores.start_session(channel, None);
+        scores.record(channel, &[buzz(1, BuzzKind::Power)]);
+        // A new session resets the channel, but not the running totals
+        scores.start_session(channel, None);
+        scores.record(channel, &[buzz(1, BuzzKind::Ten)]);
+
+        assert_eq!(
+            scores.channels[&channel].players[&UserId::new(1)].points,
+            10
+        );
+        assert_eq!(scores.channels[&channel].tossups_heard, 1);
+        assert_eq!(scores.players[&UserId::new(1)].points, 25);
+    }
+
+    fn roster() -> Roster {
+        Roster::from([
+            (UserId::new(1), "Red".to_string()),
+            (UserId::new(2), "Red".to_string()),
+            (UserId::new(3), "Blue".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_team_scores() {
+        let mut scoreboard = Scoreboard::with_teams(roster());
+        // Both teams start on the board
+        assert_eq!(scoreboard.team_standings().len(), 2);
+        scoreboard.record(&[buzz(1, BuzzKind::Neg), buzz(3, BuzzKind::Ten)]);
+        scoreboard.record(&[buzz(2, BuzzKind::Power)]);
+        // Players who aren't on a team still get their own score
+        scoreboard.record(&[buzz(4, BuzzKind::Ten)]);
+
+        assert_eq!(
+            scoreboard.team_standings(),
+            vec![
+                (
+                    "Blue".to_string(),
+                    PlayerScore {
+                        points: 10,
+                        powers: 0,
+                        tens: 1,
+                        negs: 0
+                    }
+                ),
+                (
+                    "Red".to_string(),
+                    PlayerScore {
+                        points: 10,
+                        powers: 1,
+                        tens: 0,
+                        negs: 1
+                    }
+                ),
+            ]
+        );
+        assert_eq!(scoreboard.players[&UserId::new(2)].points, 15);
+        assert_eq!(scoreboard.players.len(), 4);
+    }
+
+    #[test]
+    fn test_team_scoreboard_render() {
+        let mut scoreboard = Scoreboard::with_teams(roster());
+        assert_eq!(
+            scoreboard.render(),
+            "• **Blue**: 0 points (0/0/0)\n• **Red**: 0 points (0/0/0)\n\n*Nobody has scored yet.*"
+        );
+        scoreboard.record(&[buzz(1, BuzzKind::Power)]);
+        assert_eq!(
+            scoreboard.render(),
+            "• **Red**: 15 points (1/0/0)\n• **Blue**: 0 points (0/0/0)\n\n• <@1>: 15 points (1/0/0)"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+/// Simplifying queries, and writing sets of leaves back out as queries
+///
+/// `simplify` tidies up what someone typed without changing what it means (so saved
+/// queries and echoed queries look the same however they were written), and `set_expr`
+/// goes the other way, from the leaves a request covers to a short query for them.
+use crate::query::{
+    contains_filter, contains_weight, evaluate, find_name, parse_expr, tokenize, CategorySet, Expr,
+    QueryError, Span, ALIASES, UNIVERSE,
+};
+
+/// The name as it's usually written, e.g. "biology" or "Bilogy" is "Biology"
+///
+/// Aliases stay aliases (they're usually the short way of writing something), and names
+/// that don't match anything are left for `evaluate` to complain about.
+fn canonical_name(t: &str) -> String {
+    let lowercase = t.to_lowercase();
+    match ALIASES.get(lowercase.as_str()) {
+        // Some aliases are just another way of spelling the name, like "physics"
+        Some([name]) if name.to_lowercase() == lowercase => return name.to_string(),
+        Some(_) => return lowercase,
+        None => {}
+    }
+    find_name(t).map_or(t.to_string(), str::to_string)
+}
+
+fn token(name: &str) -> Expr {
+    Expr::Token(name.to_string(), Span::default())
+}
+
+/// The operands of a chain of `+` (or `&`), however it's parenthesized
+fn operands(expr: &Expr, or: bool, found: &mut Vec<Expr>) {
+    match (expr, or) {
+        (Expr::Or(a, b), true) | (Expr::And(a, b), false) => {
+            operands(a, or, found);
+            operands(b, or, found);
+        }
+        _ => {
+            // Simplifying can turn something else into more of the chain (e.g. `--(A + B)`)
+            let simplified = simplify(expr);
+            match (&simplified, or) {
+                (Expr::Or(..), true) | (Expr::And(..), false) => operands(&simplified, or, found),
+                _ => found.push(simplified),
+            }
+        }
+    }
+}
+
+/// Put a chain back together, left to right
+fn chain(operands: Vec<Expr>, join: fn(Box<Expr>, Box<Expr>) -> Expr) -> Option<Expr> {
+    operands
+        .into_iter()
+        .reduce(|a, b| join(Box::new(a), Box::new(b)))
+}
+
+/// The set an operand stands for, if it's just categories (no filters, weights or saved
+/// queries). Anything else is kept as it is, since dropping it could change the query.
+fn set_of(expr: &Expr) -> Option<CategorySet> {
+    if contains_filter(expr) || contains_weight(expr) {
+        return None;
+    }
+    evaluate(expr).ok()
+}
+
+/// Drop operands that don't add anything to a chain
+///
+/// `redundant(a, b)` says whether `a` can go because of `b`. When two operands are the
+/// same set (like `bio` and `Biology`), the first one stays.
+fn drop_redundant(
+    operands: Vec<Expr>,
+    redundant: fn(&CategorySet, &CategorySet) -> bool,
+) -> Vec<Expr> {
+    let sets: Vec<_> = operands.iter().map(set_of).collect();
+    operands
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            let Some(a) = &sets[*i] else {
+                return true;
+            };
+            !sets.iter().enumerate().any(|(j, b)| {
+                b.as_ref()
+                    .is_some_and(|b| j != *i && redundant(a, b) && (a != b || j < *i))
+            })
+        })
+        .map(|(_, operand)| operand)
+        .collect()
+}
+
+/// Leave out repeats (by how they're written, which catches filters too)
+fn dedup(operands: Vec<Expr>) -> Vec<Expr> {
+    let mut seen = Vec::new();
+    operands
+        .into_iter()
+        .filter(|operand| {
+            let written = operand.to_string();
+            let new = !seen.contains(&written);
+            seen.push(written);
+            new
+        })
+        .collect()
+}
+
+/// The same query, written more simply
+///
+/// - Names are written the usual way (`biology` is `Biology`)
+/// - Unions and intersections are flattened, without repeats (`A + (B + A)` is `A + B`)
+/// - Anything already covered by the rest goes (`Science + Biology` is `Science`,
+///   `Science & Biology` is `Biology`, and `Biology - History` is `Biology`)
+/// - `--A` is `A`, `A & All` is `A`, and `All - A` is `-A`
+///
+/// Weighted queries keep all their parts, since a repeat there draws from it more often.
+/// Parts with filters or weights are never dropped for being covered by something else.
+/// Nothing here changes which questions a query matches.
+pub fn simplify(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Token(t, span) => Expr::Token(canonical_name(t), *span),
+        Expr::All | Expr::Filter(_) | Expr::Saved(..) => expr.clone(),
+        Expr::Or(..) => {
+            let mut parts = Vec::new();
+            operands(expr, true, &mut parts);
+            if parts.iter().any(contains_weight) {
+                return chain(parts, Expr::Or).expect("a chain has operands");
+            }
+            let parts = drop_redundant(dedup(parts), |a, b| a.is_subset(b));
+            chain(parts, Expr::Or).expect("something is always left")
+        }
+        Expr::And(..) => {
+            let mut parts = Vec::new();
+            operands(expr, false, &mut parts);
+            let parts: Vec<_> = dedup(parts)
+                .into_iter()
+                .filter(|part| !matches!(part, Expr::All))
+                .collect();
+            let parts = drop_redundant(parts, |a, b| b.is_subset(a));
+            chain(parts, Expr::And).unwrap_or(Expr::All)
+        }
+        Expr::Not(a, b) => {
+            let (a, b) = (simplify(a), simplify(b));
+            if let (Some(left), Some(right)) = (set_of(&a), set_of(&b)) {
+                if left.is_disjoint(&right) {
+                    return a;
+                }
+            }
+            match a {
+                Expr::All => Expr::Complement(Box::new(b)),
+                a => Expr::Not(Box::new(a), Box::new(b)),
+            }
+        }
+        Expr::Complement(a) => match simplify(a) {
+            Expr::Complement(a) => *a,
+            a => Expr::Complement(Box::new(a)),
+        },
+        Expr::Weighted(a, weight) => Expr::Weighted(Box::new(simplify(a)), *weight),
+    }
+}
+
+/// A query as it should be saved: simplified, and written the canonical way
+///
+/// Saved queries (`@name`) are left as they are rather than expanded, so changing one
+/// changes everything that uses it.
+pub fn normalize(query: &str) -> Result<String, QueryError> {
+    Ok(simplify(&parse_expr(&mut tokenize(query))?).to_string())
+}
+
+fn count_tokens(expr: &Expr) -> usize {
+    match expr {
+        Expr::And(a, b) | Expr::Or(a, b) | Expr::Not(a, b) => count_tokens(a) + count_tokens(b),
+        Expr::Complement(a) | Expr::Weighted(a, _) => count_tokens(a),
+        Expr::Token(..) | Expr::All | Expr::Filter(_) | Expr::Saved(..) => 1,
+    }
+}
+
+/// Names for the leaves of one category, using whole subcategories where possible
+fn subcategory_pieces(category: &str, leaves: &CategorySet) -> Vec<Expr> {
+    let mut pieces = Vec::new();
+    let mut done = Vec::new();
+    for leaf in leaves {
+        match (leaf.subcategory, leaf.alternate) {
+            (Some(subcategory), _) if done.contains(&subcategory) => {}
+            (Some(subcategory), alternate) => {
+                let whole = UNIVERSE
+                    .iter()
+                    .filter(|other| {
+                        other.category == category && other.subcategory == Some(subcategory)
+                    })
+                    .all(|other| leaves.contains(other));
+                match alternate {
+                    Some(alternate) if !whole => pieces.push(token(alternate)),
+                    _ => {
+                        pieces.push(token(subcategory));
+                        done.push(subcategory);
+                    }
+                }
+            }
+            (None, Some(alternate)) => pieces.push(token(alternate)),
+            (None, None) => pieces.push(token(category)),
+        }
+    }
+    pieces
+}
+
+/// Names for the leaves, whichever way of writing each category is shorter
+///
+/// A category's leaves are either listed (`Biology + Chemistry`) or taken out of the
+/// whole thing (`Science - Math`).
+fn pieces(leaves: &CategorySet) -> Vec<Expr> {
+    let mut categories: Vec<&str> = leaves.iter().map(|leaf| leaf.category).collect();
+    categories.dedup();
+    let mut pieces = Vec::new();
+    for category in categories {
+        let all: CategorySet = UNIVERSE
+            .iter()
+            .filter(|leaf| leaf.category == category)
+            .copied()
+            .collect();
+        let included: CategorySet = leaves
+            .iter()
+            .filter(|leaf| leaf.category == category)
+            .copied()
+            .collect();
+        if included == all {
+            pieces.push(token(category));
+            continue;
+        }
+        let listed = subcategory_pieces(category, &included);
+        let excluded: CategorySet = &all - &included;
+        let taken_out = subcategory_pieces(category, &excluded);
+        if listed.len() <= taken_out.len() + 1 {
+            pieces.extend(listed);
+        } else {
+            pieces.push(taken_out.into_iter().fold(token(category), |rest, piece| {
+                Expr::Not(Box::new(rest), Box::new(piece))
+            }));
+        }
+    }
+    pieces
+}
+
+/// A short query for exactly these leaves (or nothing, if there aren't any)
+///
+/// Whichever's shorter of listing what's in the set, or taking what isn't out of
+/// everything: `-Pop Culture - Current Events` rather than a dozen categories.
+pub fn set_expr(leaves: &CategorySet) -> Option<Expr> {
+    if leaves.is_empty() {
+        return None;
+    }
+    if *leaves == *UNIVERSE {
+        return Some(Expr::All);
+    }
+    let listed = chain(pieces(leaves), Expr::Or)?;
+    let excluded: CategorySet = &*UNIVERSE - leaves;
+    let mut taken_out = pieces(&excluded).into_iter();
+    let first = Expr::Complement(Box::new(taken_out.next()?));
+    let taken_out = taken_out.fold(first, |rest, piece| {
+        Expr::Not(Box::new(rest), Box::new(piece))
+    });
+    if count_tokens(&taken_out) < count_tokens(&listed) {
+        Some(taken_out)
+    } else {
+        Some(listed)
+    }
+}
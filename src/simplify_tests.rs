@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod tests {
+    use crate::query::{
+        evaluate, parse_expr, parse_query, split_filters, tokenize, ApiQuery, CategorySet,
+    };
+    use crate::simplify::*;
+
+    fn printed(s: &str) -> String {
+        parse_expr(&mut tokenize(s)).unwrap().to_string()
+    }
+
+    fn simplified(s: &str) -> String {
+        normalize(s).unwrap()
+    }
+
+    fn set(s: &str) -> CategorySet {
+        let (categories, _) = split_filters(&parse_expr(&mut tokenize(s)).unwrap()).unwrap();
+        evaluate(&categories.unwrap()).unwrap()
+    }
+
+    fn echo(s: &str) -> String {
+        parse_query(s).unwrap().to_expr().to_string()
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        for query in [
+            "Science + History & Literature",
+            "(Science + History) & Literature",
+            "Science - Math - Biology",
+            "Science - (Math - Astronomy)",
+            "Science & (Biology + Math)",
+            "-Pop Culture - Current Events",
+            "-(Pop Culture + Current Events)",
+            "Science*3 + (Biology + Math)*2",
+            "-Science*2 + History",
+            "Biology & difficulty:3-5 & year:>=2018",
+            "@mysci + History",
+            "All - Science",
+        ] {
+            assert_eq!(printed(query), query);
+        }
+        // Parentheses that don't do anything go
+        assert_eq!(
+            printed("((Science)) + (History & Literature)"),
+            "Science + History & Literature"
+        );
+        assert_eq!(
+            printed("(Science - Math) & Biology"),
+            "Science - Math & Biology"
+        );
+        assert_eq!(printed("(A + B) + C"), "A + B + C");
+        assert_eq!(printed("A + (B + C)"), "A + (B + C)");
+    }
+
+    #[test]
+    fn printing_round_trips() {
+        for query in [
+            "Science - (Math - Astronomy)",
+            "-(Pop Culture + Current Events) & difficulty:3",
+            "((Biology + Chemistry) - Math) & !Physics",
+            "(Science*3 + History) & standard:true",
+        ] {
+            assert_eq!(
+                parse_query(&printed(query)).unwrap(),
+                parse_query(query).unwrap(),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn simplifies() {
+        let cases = [
+            ("Biology + Biology", "Biology"),
+            ("biology + (Chemistry + BIOLOGY)", "Biology + Chemistry"),
+            ("bio + Biology", "bio"),
+            ("Science + Biology", "Science"),
+            ("Biology + Science", "Science"),
+            ("Science & Biology", "Biology"),
+            ("Science & Science & Biology", "Biology"),
+            ("Biology - History", "Biology"),
+            ("Science - Math", "Science - Math"),
+            ("--Biology", "Biology"),
+            ("Biology & All", "Biology"),
+            ("Biology + All", "All"),
+            ("All - Trash", "-trash"),
+            ("Psycology", "Psychology"),
+            (
+                "Biology & difficulty:3 & difficulty:3",
+                "Biology & difficulty:3",
+            ),
+            // Weighted parts all count, repeats included
+            (
+                "Science*2 + History + History",
+                "Science*2 + History + History",
+            ),
+            ("(bio + bio)*2 + Physics", "bio*2 + Physics"),
+            // Filters and weights aren't covered by anything else
+            ("All + difficulty:3", "All + difficulty:3"),
+            (
+                "Science + Biology & year:2020",
+                "Science + Biology & year:2020",
+            ),
+            (
+                "(Science + History) & (Science*3 + History*2)",
+                "(Science + History) & (Science*3 + History*2)",
+            ),
+            ("Science & Biology*2", "Science & Biology*2"),
+            // Saved queries aren't looked into
+            ("@mysci + @mysci + Biology", "@mysci + Biology"),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(simplified(query), expected, "{query}");
+        }
+    }
+
+    #[test]
+    fn simplifying_keeps_the_meaning() {
+        let terms = [
+            "Science",
+            "Biology",
+            "Math",
+            "History",
+            "Social Science",
+            "All",
+        ];
+        for a in terms {
+            for b in terms {
+                for query in [
+                    format!("{a} + {b}"),
+                    format!("{a} & {b}"),
+                    format!("{a} - {b}"),
+                    format!("-{a} + {b} & {a}"),
+                ] {
+                    assert_eq!(set(&simplified(&query)), set(&query), "{query}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_expressions() {
+        let expr = |s: &str| set_expr(&set(s)).unwrap().to_string();
+        assert_eq!(expr("Science"), "Science");
+        assert_eq!(expr("Biology + Chemistry"), "Biology + Chemistry");
+        assert_eq!(expr("Math + Biology"), "Biology + Math");
+        assert_eq!(expr("Science - Math"), "Science - Math");
+        assert_eq!(expr("Other Science"), "Other Science");
+        assert_eq!(expr("Economics + Psychology"), "Economics + Psychology");
+        assert_eq!(
+            expr("-Pop Culture - Current Events"),
+            "-Current Events - Pop Culture"
+        );
+        assert_eq!(expr("*"), "All");
+        assert!(set_expr(&CategorySet::new()).is_none());
+    }
+
+    #[test]
+    fn echoes_what_is_searched_for() {
+        assert_eq!(echo("sci & bio"), "Biology");
+        assert_eq!(echo("(bio + chem) - Math"), "Biology + Chemistry");
+        assert_eq!(echo("Biology + Math"), "Biology + Math");
+        assert_eq!(echo("Science & -Math"), "Science - Math");
+        assert_eq!(echo("*"), "All");
+        assert_eq!(
+            echo("Biology & difficulty:3-5 & standard:true"),
+            "Biology & difficulty:3-5 & standard:true"
+        );
+        assert_eq!(echo("year:2015-2018"), "year:2015-2018");
+        assert_eq!(
+            echo("(Science*3 + bio + chem) & answer:\"Darwin\""),
+            "(Science*3 + Biology*1 + Chemistry*1) & answer:\"Darwin\""
+        );
+        assert_eq!(ApiQuery::default().to_expr().to_string(), "All");
+    }
+
+    #[test]
+    fn echoes_round_trip() {
+        for query in [
+            "Science",
+            "Biology + Math",
+            "Science - Math",
+            "-Pop Culture - Current Events",
+            "(Literature + Biology) & difficulty:2,4",
+            "(History*2 + Science - Math) & year:<=2010",
+            "\"photosynthesis\" & standard:true",
+            "difficulty:>=6",
+        ] {
+            let parsed = parse_query(query).unwrap();
+            let echoed = parse_query(&parsed.to_expr().to_string()).unwrap();
+            let strip = |query: ApiQuery| ApiQuery {
+                parts: query
+                    .parts
+                    .into_iter()
+                    .map(|part| crate::query::QueryPart {
+                        label: String::new(),
+                        ..part
+                    })
+                    .collect(),
+                ..query
+            };
+            assert_eq!(strip(echoed), strip(parsed), "{query}");
+        }
+    }
+}
@@ -0,0 +1,561 @@
+/// Persistent stats, kept in an embedded SQLite database
+use std::sync::{Arc, Mutex};
+
+use ::serenity::all::{ChannelId, GuildId, Mentionable, UserId};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::qb::Tossup;
+use crate::query::SavedQueries;
+use crate::score::{PlayerScore, ScoredBuzz};
+use crate::Error;
+
+/// Each entry brings the database up a version (tracked with `PRAGMA user_version`)
+const MIGRATIONS: &[&str] = &[
+    "
+CREATE TABLE IF NOT EXISTS buzzes (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    guild_id INTEGER,
+    channel_id INTEGER NOT NULL,
+    tossup_id TEXT NOT NULL,
+    category TEXT NOT NULL,
+    subcategory TEXT NOT NULL,
+    difficulty INTEGER NOT NULL,
+    word_index INTEGER NOT NULL,
+    question_length INTEGER NOT NULL,
+    verdict TEXT NOT NULL,
+    points INTEGER NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (unixepoch())
+);
+CREATE INDEX IF NOT EXISTS buzzes_by_user ON buzzes (user_id, guild_id);
+",
+    "
+-- Seasons are per guild, and everything before the first /newseason is season 1
+CREATE TABLE seasons (
+    guild_id INTEGER NOT NULL,
+    number INTEGER NOT NULL,
+    started_at INTEGER NOT NULL DEFAULT (unixepoch()),
+    PRIMARY KEY (guild_id, number)
+);
+-- Every tossup read, buzzed on or not, for working out points per tossup heard
+CREATE TABLE tossups_read (
+    id INTEGER PRIMARY KEY,
+    guild_id INTEGER,
+    channel_id INTEGER NOT NULL,
+    session_id INTEGER NOT NULL,
+    season INTEGER NOT NULL,
+    tossup_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (unixepoch())
+);
+ALTER TABLE buzzes ADD COLUMN session_id INTEGER;
+ALTER TABLE buzzes ADD COLUMN season INTEGER NOT NULL DEFAULT 1;
+CREATE INDEX buzzes_by_season ON buzzes (guild_id, season);
+",
+    "
+-- Saved queries belong to a user, or to a guild (shared with everyone in it)
+CREATE TABLE saved_queries (
+    owner_id INTEGER NOT NULL,
+    shared INTEGER NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,
+    expression TEXT NOT NULL,
+    updated_at INTEGER NOT NULL DEFAULT (unixepoch()),
+    PRIMARY KEY (owner_id, shared, name)
+);
+",
+    "
+-- How each guild judges answers, for the ones that don't use the default
+CREATE TABLE judge_pipelines (
+    guild_id INTEGER PRIMARY KEY,
+    pipeline TEXT NOT NULL
+);
+",
+];
+
+/// How a player does in a single category
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategoryStats {
+    pub category: String,
+    pub buzzes: u32,
+    pub correct: u32,
+    pub points: i32,
+    /// How far into the question (0 to 1) their correct buzzes came, on average
+    pub average_depth: Option<f64>,
+}
+
+impl CategoryStats {
+    pub fn accuracy(&self) -> f64 {
+        if self.buzzes == 0 {
+            0.0
+        } else {
+            self.correct as f64 / self.buzzes as f64
+        }
+    }
+
+    /// e.g. "7/10 correct (70%), 85 points, average buzz 62% of the way in"
+    pub fn line(&self) -> String {
+        let mut line = format!(
+            "{}/{} correct ({:.0}%), {} points",
+            self.correct,
+            self.buzzes,
+            self.accuracy() * 100.0,
+            self.points
+        );
+        if let Some(depth) = self.average_depth {
+            line.push_str(&format!(
+                ", average buzz {:.0}% of the way in",
+                depth * 100.0
+            ));
+        }
+        line
+    }
+}
+
+/// Everything we know about a player, one entry per category they've buzzed on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerStats {
+    pub categories: Vec<CategoryStats>,
+}
+
+impl PlayerStats {
+    /// All the categories rolled into one
+    pub fn overall(&self) -> CategoryStats {
+        let correct: u32 = self.categories.iter().map(|stats| stats.correct).sum();
+        // Weight each category's depth by how many correct buzzes went into it
+        let depth_sum: f64 = self
+            .categories
+            .iter()
+            .filter_map(|stats| Some(stats.average_depth? * stats.correct as f64))
+            .sum();
+        CategoryStats {
+            category: "Overall".to_string(),
+            buzzes: self.categories.iter().map(|stats| stats.buzzes).sum(),
+            correct,
+            points: self.categories.iter().map(|stats| stats.points).sum(),
+            average_depth: (correct > 0).then(|| depth_sum / correct as f64),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        if self.categories.is_empty() {
+            return "*No buzzes recorded yet.*".to_string();
+        }
+        let mut response = format!("**Overall**: {}\n", self.overall().line());
+        for stats in &self.categories {
+            response.push_str(&format!("• **{}**: {}\n", stats.category, stats.line()));
+        }
+        response
+    }
+}
+
+/// What to rank players by on the leaderboard
+#[derive(Debug, Clone, Copy, Default, PartialEq, poise::ChoiceParameter)]
+pub enum Ranking {
+    #[default]
+    #[name = "Points"]
+    Points,
+    #[name = "Powers"]
+    Powers,
+    /// Fewest negs first
+    #[name = "Negs"]
+    Negs,
+    #[name = "Points per tossup heard"]
+    PointsPerTossup,
+}
+
+impl Ranking {
+    /// Best first, with ties going to whoever has more points (then the lower user id)
+    pub fn sort(self, standings: &mut [Standing]) {
+        standings.sort_by(|a, b| {
+            let order = match self {
+                Ranking::Points => std::cmp::Ordering::Equal,
+                Ranking::Powers => b.score.powers.cmp(&a.score.powers),
+                Ranking::Negs => a.score.negs.cmp(&b.score.negs),
+                Ranking::PointsPerTossup => b
+                    .points_per_tossup()
+                    .unwrap_or(f64::NEG_INFINITY)
+                    .total_cmp(&a.points_per_tossup().unwrap_or(f64::NEG_INFINITY)),
+            };
+            order
+                .then(b.score.points.cmp(&a.score.points))
+                .then(a.user_id.cmp(&b.user_id))
+        });
+    }
+}
+
+/// A player's place on the leaderboard
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Standing {
+    pub user_id: UserId,
+    pub score: PlayerScore,
+    pub tossups_heard: u32,
+}
+
+impl Standing {
+    pub fn points_per_tossup(&self) -> Option<f64> {
+        (self.tossups_heard > 0).then(|| self.score.points as f64 / self.tossups_heard as f64)
+    }
+
+    /// e.g. "45 points (1/3/0), 7.50 PPTH"
+    pub fn line(&self) -> String {
+        match self.points_per_tossup() {
+            Some(ppth) => format!("{}, {:.2} PPTH", self.score.line(), ppth),
+            None => self.score.line(),
+        }
+    }
+}
+
+/// Number the standings, e.g. "1. @player: 45 points (1/3/0), 7.50 PPTH"
+pub fn render_standings(standings: &[Standing]) -> String {
+    if standings.is_empty() {
+        return "*Nobody has buzzed this season.*".to_string();
+    }
+    standings
+        .iter()
+        .enumerate()
+        .map(|(index, standing)| {
+            format!(
+                "{}. {}: {}",
+                index + 1,
+                standing.user_id.mention(),
+                standing.line()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whose a saved query is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryOwner {
+    User(UserId),
+    /// Shared with everyone in the guild
+    Guild(GuildId),
+}
+
+impl QueryOwner {
+    fn key(self) -> (i64, bool) {
+        match self {
+            QueryOwner::User(user) => (user.get() as i64, false),
+            QueryOwner::Guild(guild) => (guild.get() as i64, true),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedQuery {
+    pub name: String,
+    pub expression: String,
+    pub shared: bool,
+}
+
+pub struct Storage {
+    // rusqlite connections can't be shared between threads on their own
+    conn: Mutex<Connection>,
+}
+
+impl Storage {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Opens the database at `DATABASE_PATH` (or `qbot.db`)
+    pub fn open_from_env() -> rusqlite::Result<Self> {
+        let path = std::env::var("DATABASE_PATH").unwrap_or("qbot.db".into());
+        Self::open(&path)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> rusqlite::Result<Self> {
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", index + 1)?;
+            tx.commit()?;
+        }
+        Ok(Storage {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Run some queries on a blocking thread, so SQLite (and waiting on the lock) never
+    /// holds up the async runtime
+    pub async fn run<T, F>(self: &Arc<Self>, queries: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Storage) -> rusqlite::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let storage = self.clone();
+        Ok(tokio::task::spawn_blocking(move || queries(&storage)).await??)
+    }
+
+    /// Record a tossup being read, along with every buzz on it
+    ///
+    /// `session` groups the tossups read by a single command, so we know
+    /// which tossups each player was around to hear.
+    pub fn record_tossup(
+        &self,
+        guild: Option<GuildId>,
+        channel: ChannelId,
+        session: u64,
+        tossup: &Tossup,
+        question_length: usize,
+        buzzes: &[ScoredBuzz],
+    ) -> rusqlite::Result<()> {
+        let guild = guild.map(|guild| guild.get() as i64);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let season = current_season(&tx, guild)?;
+        tx.execute(
+            "INSERT INTO tossups_read (guild_id, channel_id, session_id, season, tossup_id)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                guild,
+                channel.get() as i64,
+                session as i64,
+                season,
+                tossup.id
+            ],
+        )?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO buzzes (user_id, guild_id, channel_id, tossup_id, category, subcategory,
+                    difficulty, word_index, question_length, verdict, points, session_id, season)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            )?;
+            for buzz in buzzes {
+                insert.execute(params![
+                    buzz.user_id.get() as i64,
+                    guild,
+                    channel.get() as i64,
+                    tossup.id,
+                    tossup.category,
+                    tossup.subcategory,
+                    tossup.difficulty,
+                    buzz.word_index as i64,
+                    question_length as i64,
+                    buzz.kind.name(),
+                    buzz.kind.points(),
+                    session as i64,
+                    season,
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    pub fn current_season(&self, guild: GuildId) -> rusqlite::Result<u32> {
+        current_season(&self.conn.lock().unwrap(), Some(guild.get() as i64))
+    }
+
+    /// End the current season, returning the number of the new one
+    ///
+    /// Nothing is deleted: the old standings stay around under their season number.
+    pub fn new_season(&self, guild: GuildId) -> rusqlite::Result<u32> {
+        let guild = Some(guild.get() as i64);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let season = current_season(&tx, guild)? + 1;
+        tx.execute(
+            "INSERT INTO seasons (guild_id, number) VALUES (?1, ?2)",
+            params![guild, season],
+        )?;
+        tx.commit()?;
+        Ok(season)
+    }
+
+    /// Standings for a season in a guild, or just one of its channels
+    pub fn standings(
+        &self,
+        guild: GuildId,
+        channel: Option<ChannelId>,
+        season: u32,
+        ranking: Ranking,
+    ) -> rusqlite::Result<Vec<Standing>> {
+        let conn = self.conn.lock().unwrap();
+        // A player heard every tossup read in the sessions they buzzed in
+        let mut query = conn.prepare_cached(
+            "WITH scoped AS (
+                SELECT * FROM buzzes
+                WHERE guild_id = ?1 AND (?2 IS NULL OR channel_id = ?2) AND season = ?3
+            ),
+            heard AS (
+                SELECT played.user_id, COUNT(*) AS tossups_heard
+                FROM (SELECT DISTINCT user_id, session_id FROM scoped) AS played
+                JOIN tossups_read ON tossups_read.session_id = played.session_id
+                GROUP BY played.user_id
+            )
+            SELECT scoped.user_id,
+                SUM(points),
+                SUM(verdict = 'power'),
+                SUM(verdict = 'ten'),
+                SUM(verdict = 'neg'),
+                COALESCE(heard.tossups_heard, 0)
+            FROM scoped LEFT JOIN heard ON heard.user_id = scoped.user_id
+            GROUP BY scoped.user_id",
+        )?;
+        let mut standings = query
+            .query_map(
+                params![
+                    guild.get() as i64,
+                    channel.map(|channel| channel.get() as i64),
+                    season
+                ],
+                |row| {
+                    Ok(Standing {
+                        user_id: UserId::new(row.get::<_, i64>(0)? as u64),
+                        score: PlayerScore {
+                            points: row.get(1)?,
+                            powers: row.get(2)?,
+                            tens: row.get(3)?,
+                            negs: row.get(4)?,
+                        },
+                        tossups_heard: row.get(5)?,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        ranking.sort(&mut standings);
+        Ok(standings)
+    }
+
+    /// A player's stats, limited to a single server if `guild` is set
+    pub fn player_stats(
+        &self,
+        user: UserId,
+        guild: Option<GuildId>,
+    ) -> rusqlite::Result<PlayerStats> {
+        let conn = self.conn.lock().unwrap();
+        let mut query = conn.prepare_cached(
+            "SELECT category,
+                COUNT(*),
+                SUM(verdict IN ('power', 'ten')),
+                SUM(points),
+                AVG(CASE WHEN verdict IN ('power', 'ten')
+                    THEN CAST(word_index AS REAL) / MAX(question_length, 1) END)
+            FROM buzzes
+            WHERE user_id = ?1 AND (?2 IS NULL OR guild_id = ?2)
+            GROUP BY category
+            ORDER BY COUNT(*) DESC, category",
+        )?;
+        let categories = query
+            .query_map(
+                params![user.get() as i64, guild.map(|guild| guild.get() as i64)],
+                |row| {
+                    Ok(CategoryStats {
+                        category: row.get(0)?,
+                        buzzes: row.get(1)?,
+                        correct: row.get(2)?,
+                        points: row.get(3)?,
+                        average_depth: row.get(4)?,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(PlayerStats { categories })
+    }
+
+    /// Save a query under `name`, replacing whatever was there
+    pub fn save_query(
+        &self,
+        owner: QueryOwner,
+        name: &str,
+        expression: &str,
+    ) -> rusqlite::Result<()> {
+        let (owner, shared) = owner.key();
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO saved_queries (owner_id, shared, name, expression) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT DO UPDATE SET name = ?3, expression = ?4, updated_at = unixepoch()",
+            params![owner, shared, name, expression],
+        )?;
+        Ok(())
+    }
+
+    /// A user's own saved queries, then the ones shared in `guild`, each sorted by name
+    pub fn saved_queries(
+        &self,
+        user: UserId,
+        guild: Option<GuildId>,
+    ) -> rusqlite::Result<Vec<SavedQuery>> {
+        let conn = self.conn.lock().unwrap();
+        let mut query = conn.prepare_cached(
+            "SELECT name, expression, shared FROM saved_queries
+            WHERE (owner_id = ?1 AND NOT shared) OR (owner_id = ?2 AND shared)
+            ORDER BY shared, name",
+        )?;
+        let queries = query
+            .query_map(
+                params![user.get() as i64, guild.map(|guild| guild.get() as i64)],
+                |row| {
+                    Ok(SavedQuery {
+                        name: row.get(0)?,
+                        expression: row.get(1)?,
+                        shared: row.get(2)?,
+                    })
+                },
+            )?
+            .collect();
+        queries
+    }
+
+    /// The judges a guild picked with `/judges`, if it's picked any
+    pub fn judge_pipeline(&self, guild: GuildId) -> rusqlite::Result<Option<String>> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT pipeline FROM judge_pipelines WHERE guild_id = ?1",
+                params![guild.get() as i64],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// Pick the judges for a guild, or go back to the default with `None`
+    pub fn set_judge_pipeline(
+        &self,
+        guild: GuildId,
+        pipeline: Option<&str>,
+    ) -> rusqlite::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        match pipeline {
+            Some(pipeline) => conn.execute(
+                "INSERT INTO judge_pipelines (guild_id, pipeline) VALUES (?1, ?2)
+                ON CONFLICT DO UPDATE SET pipeline = ?2",
+                params![guild.get() as i64, pipeline],
+            )?,
+            None => conn.execute(
+                "DELETE FROM judge_pipelines WHERE guild_id = ?1",
+                params![guild.get() as i64],
+            )?,
+        };
+        Ok(())
+    }
+}
+
+/// Saved queries by lowercase name, with a user's own taking priority over shared ones
+pub fn saved_query_table(queries: &[SavedQuery]) -> SavedQueries {
+    let mut table = SavedQueries::new();
+    for shared in [true, false] {
+        table.extend(
+            queries
+                .iter()
+                .filter(|query| query.shared == shared)
+                .map(|query| (query.name.to_lowercase(), query.expression.clone())),
+        );
+    }
+    table
+}
+
+/// Guilds without a season yet are on season 1 (and so are DMs)
+fn current_season(conn: &Connection, guild: Option<i64>) -> rusqlite::Result<u32> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(number), 1) FROM seasons WHERE guild_id = ?1",
+        params![guild],
+        |row| row.get(0),
+    )
+}
@@ -0,0 +1,408 @@
+#[cfg(test)]
+mod tests {
+    use crate::qb::{Packet, Set, Tossup};
+    use crate::score::{BuzzKind, PlayerScore, ScoredBuzz};
+    use crate::storage::*;
+    use serenity::all::{ChannelId, GuildId, UserId};
+
+    fn tossup(id: &str, category: &str) -> Tossup {
+        Tossup {
+            id: id.to_string(),
+            question: "question".to_string(),
+            answer: "answer".to_string(),
+            category: category.to_string(),
+            subcategory: category.to_string(),
+            packet: Packet {
+                id: "packet_id".to_string(),
+                name: "Test Packet".to_string(),
+                number: 1,
+            },
+            set: Set {
+                id: "set_id".to_string(),
+                name: "Test Set".to_string(),
+                year: 2023,
+                standard: true,
+            },
+            updated_at: "2023-01-01T00:00:00Z".to_string(),
+            difficulty: 3,
+            number: 1,
+            answer_sanitized: "answer".to_string(),
+            question_sanitized: "question".to_string(),
+        }
+    }
+
+    fn buzz(user: u64, word_index: usize, kind: BuzzKind) -> ScoredBuzz {
+        ScoredBuzz {
+            user_id: UserId::new(user),
+            word_index,
+            kind,
+        }
+    }
+
+    const GUILD: Option<GuildId> = Some(GuildId::new(10));
+    const CHANNEL: ChannelId = ChannelId::new(20);
+
+    #[test]
+    fn test_no_stats() {
+        let storage = Storage::open_in_memory().unwrap();
+        let stats = storage.player_stats(UserId::new(1), GUILD).unwrap();
+        assert!(stats.categories.is_empty());
+        assert_eq!(stats.render(), "*No buzzes recorded yet.*");
+    }
+
+    #[test]
+    fn test_accuracy_per_category() {
+        let storage = Storage::open_in_memory().unwrap();
+        // A neg from player 1, then player 2 picks it up
+        storage
+            .record_tossup(
+                GUILD,
+                CHANNEL,
+                1,
+                &tossup("a", "Science"),
+                100,
+                &[buzz(1, 20, BuzzKind::Neg), buzz(2, 100, BuzzKind::Ten)],
+            )
+            .unwrap();
+        storage
+            .record_tossup(
+                GUILD,
+                CHANNEL,
+                1,
+                &tossup("b", "Science"),
+                100,
+                &[buzz(1, 40, BuzzKind::Power)],
+            )
+            .unwrap();
+        storage
+            .record_tossup(
+                GUILD,
+                CHANNEL,
+                1,
+                &tossup("c", "History"),
+                100,
+                &[buzz(1, 80, BuzzKind::Ten)],
+            )
+            .unwrap();
+
+        let stats = storage.player_stats(UserId::new(1), GUILD).unwrap();
+        assert_eq!(stats.categories.len(), 2);
+        // Most buzzed category first
+        let science = &stats.categories[0];
+        assert_eq!(science.category, "Science");
+        assert_eq!(science.buzzes, 2);
+        assert_eq!(science.correct, 1);
+        assert_eq!(science.points, 10);
+        assert_eq!(science.accuracy(), 0.5);
+        // Only correct buzzes count towards depth
+        assert_eq!(science.average_depth, Some(0.4));
+
+        let overall = stats.overall();
+        assert_eq!(overall.buzzes, 3);
+        assert_eq!(overall.correct, 2);
+        assert_eq!(overall.points, 20);
+        assert!((overall.average_depth.unwrap() - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_no_correct_buzzes_has_no_depth() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage
+            .record_tossup(
+                GUILD,
+                CHANNEL,
+                1,
+                &tossup("a", "Science"),
+                50,
+                &[buzz(1, 10, BuzzKind::Neg)],
+            )
+            .unwrap();
+        let stats = storage.player_stats(UserId::new(1), GUILD).unwrap();
+        assert_eq!(stats.categories[0].average_depth, None);
+        assert_eq!(stats.overall().average_depth, None);
+        assert_eq!(stats.categories[0].line(), "0/1 correct (0%), -5 points");
+    }
+
+    #[test]
+    fn test_stats_are_per_guild() {
+        let storage = Storage::open_in_memory().unwrap();
+        let science = tossup("a", "Science");
+        storage
+            .record_tossup(
+                GUILD,
+                CHANNEL,
+                1,
+                &science,
+                10,
+                &[buzz(1, 5, BuzzKind::Ten)],
+            )
+            .unwrap();
+        storage
+            .record_tossup(
+                Some(GuildId::new(11)),
+                ChannelId::new(21),
+                2,
+                &science,
+                10,
+                &[buzz(1, 5, BuzzKind::Neg)],
+            )
+            .unwrap();
+
+        let here = storage.player_stats(UserId::new(1), GUILD).unwrap();
+        assert_eq!(here.overall().buzzes, 1);
+        assert_eq!(here.overall().points, 10);
+        // Without a guild (e.g. in DMs) everything counts
+        let everywhere = storage.player_stats(UserId::new(1), None).unwrap();
+        assert_eq!(everywhere.overall().buzzes, 2);
+    }
+
+    #[test]
+    fn test_stats_line() {
+        let stats = CategoryStats {
+            category: "Science".to_string(),
+            buzzes: 10,
+            correct: 7,
+            points: 85,
+            average_depth: Some(0.62),
+        };
+        assert_eq!(
+            stats.line(),
+            "7/10 correct (70%), 85 points, average buzz 62% of the way in"
+        );
+    }
+
+    /// Points are worked out from the powers, tens and negs
+    fn standing(user: u64, powers: u32, tens: u32, negs: u32, heard: u32) -> Standing {
+        Standing {
+            user_id: UserId::new(user),
+            score: PlayerScore {
+                points: powers as i32 * 15 + tens as i32 * 10 - negs as i32 * 5,
+                powers,
+                tens,
+                negs,
+            },
+            tossups_heard: heard,
+        }
+    }
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let path = std::env::temp_dir().join(format!("qbot-test-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+        Storage::open(path).unwrap();
+        // Opening it again shouldn't try to run the migrations twice
+        Storage::open(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_standings() {
+        let storage = Storage::open_in_memory().unwrap();
+        let guild = GUILD.unwrap();
+        // Session 1: player 1 powers the first tossup, the second goes dead
+        storage
+            .record_tossup(
+                GUILD,
+                CHANNEL,
+                1,
+                &tossup("a", "Science"),
+                100,
+                &[buzz(1, 10, BuzzKind::Power)],
+            )
+            .unwrap();
+        storage
+            .record_tossup(GUILD, CHANNEL, 1, &tossup("b", "Science"), 100, &[])
+            .unwrap();
+        // Session 2 (another channel): player 2 negs, then gets one
+        let other = ChannelId::new(21);
+        storage
+            .record_tossup(
+                GUILD,
+                other,
+                2,
+                &tossup("c", "History"),
+                100,
+                &[buzz(2, 10, BuzzKind::Neg)],
+            )
+            .unwrap();
+        storage
+            .record_tossup(
+                GUILD,
+                other,
+                2,
+                &tossup("d", "History"),
+                100,
+                &[buzz(2, 100, BuzzKind::Ten)],
+            )
+            .unwrap();
+
+        let standings = storage.standings(guild, None, 1, Ranking::Points).unwrap();
+        assert_eq!(
+            standings,
+            vec![standing(1, 1, 0, 0, 2), standing(2, 0, 1, 1, 2)]
+        );
+        assert_eq!(standings[0].points_per_tossup(), Some(7.5));
+
+        let channel = storage
+            .standings(guild, Some(other), 1, Ranking::Points)
+            .unwrap();
+        assert_eq!(channel.len(), 1);
+        assert_eq!(channel[0].user_id, UserId::new(2));
+    }
+
+    #[test]
+    fn test_new_season_resets_standings() {
+        let storage = Storage::open_in_memory().unwrap();
+        let guild = GUILD.unwrap();
+        assert_eq!(storage.current_season(guild).unwrap(), 1);
+        storage
+            .record_tossup(
+                GUILD,
+                CHANNEL,
+                1,
+                &tossup("a", "Science"),
+                100,
+                &[buzz(1, 10, BuzzKind::Power)],
+            )
+            .unwrap();
+
+        assert_eq!(storage.new_season(guild).unwrap(), 2);
+        assert_eq!(storage.current_season(guild).unwrap(), 2);
+        assert!(storage
+            .standings(guild, None, 2, Ranking::Points)
+            .unwrap()
+            .is_empty());
+        // The old season is still there
+        assert_eq!(
+            storage.standings(guild, None, 1, Ranking::Points).unwrap(),
+            vec![standing(1, 1, 0, 0, 1)]
+        );
+        // Seasons are per guild
+        assert_eq!(storage.current_season(GuildId::new(11)).unwrap(), 1);
+        // Stats carry on across seasons
+        let stats = storage.player_stats(UserId::new(1), GUILD).unwrap();
+        assert_eq!(stats.overall().buzzes, 1);
+    }
+
+    #[test]
+    fn test_rankings() {
+        let mut standings = vec![
+            // 30 points
+            standing(1, 0, 3, 0, 10),
+            // 40 points
+            standing(2, 2, 2, 2, 20),
+            // 25 points
+            standing(3, 1, 2, 1, 5),
+            standing(4, 0, 0, 0, 0),
+        ];
+        let order = |standings: &[Standing]| -> Vec<u64> {
+            standings.iter().map(|s| s.user_id.get()).collect()
+        };
+
+        Ranking::Points.sort(&mut standings);
+        assert_eq!(order(&standings), vec![2, 1, 3, 4]);
+        Ranking::Powers.sort(&mut standings);
+        assert_eq!(order(&standings), vec![2, 3, 1, 4]);
+        // Fewest negs first, ties broken by points
+        Ranking::Negs.sort(&mut standings);
+        assert_eq!(order(&standings), vec![1, 4, 3, 2]);
+        // Nobody heard anything goes last
+        Ranking::PointsPerTossup.sort(&mut standings);
+        assert_eq!(order(&standings), vec![3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn test_render_standings() {
+        assert_eq!(render_standings(&[]), "*Nobody has buzzed this season.*");
+        assert_eq!(
+            render_standings(&[standing(1, 1, 0, 0, 2), standing(2, 0, 0, 0, 0)]),
+            "1. <@1>: 15 points (1/0/0), 7.50 PPTH\n2. <@2>: 0 points (0/0/0)"
+        );
+    }
+
+    #[test]
+    fn test_saved_queries() {
+        let storage = Storage::open_in_memory().unwrap();
+        let user = UserId::new(1);
+        let guild = GUILD.unwrap();
+        storage
+            .save_query(QueryOwner::User(user), "mysci", "Biology + Chemistry")
+            .unwrap();
+        storage
+            .save_query(QueryOwner::Guild(guild), "arts", "Fine Arts")
+            .unwrap();
+        storage
+            .save_query(QueryOwner::Guild(guild), "MySci", "Physics")
+            .unwrap();
+        // Saving it again replaces it, whatever the case
+        storage
+            .save_query(QueryOwner::User(user), "MYSCI", "Biology")
+            .unwrap();
+        let saved = |user: u64, guild: Option<GuildId>| {
+            storage.saved_queries(UserId::new(user), guild).unwrap()
+        };
+        let query = |name: &str, expression: &str, shared: bool| SavedQuery {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            shared,
+        };
+        assert_eq!(
+            saved(1, GUILD),
+            vec![
+                query("MYSCI", "Biology", false),
+                query("arts", "Fine Arts", true),
+                query("MySci", "Physics", true),
+            ]
+        );
+        // Other people only get the shared ones, and only in that guild
+        assert_eq!(saved(2, GUILD).len(), 2);
+        assert_eq!(saved(2, Some(GuildId::new(11))), vec![]);
+        assert_eq!(saved(1, None), vec![query("MYSCI", "Biology", false)]);
+
+        // Your own win over the server's
+        let table = saved_query_table(&saved(1, GUILD));
+        assert_eq!(table["mysci"], "Biology");
+        assert_eq!(table["arts"], "Fine Arts");
+        assert_eq!(saved_query_table(&saved(2, GUILD))["mysci"], "Physics");
+    }
+
+    #[test]
+    fn test_judge_pipelines() {
+        let storage = Storage::open_in_memory().unwrap();
+        let guild = GUILD.unwrap();
+        assert_eq!(storage.judge_pipeline(guild).unwrap(), None);
+        storage
+            .set_judge_pipeline(guild, Some("answer_line, llm"))
+            .unwrap();
+        storage
+            .set_judge_pipeline(guild, Some("answer_line, levenshtein"))
+            .unwrap();
+        assert_eq!(
+            storage.judge_pipeline(guild).unwrap().as_deref(),
+            Some("answer_line, levenshtein")
+        );
+        assert_eq!(storage.judge_pipeline(GuildId::new(11)).unwrap(), None);
+        storage.set_judge_pipeline(guild, None).unwrap();
+        assert_eq!(storage.judge_pipeline(guild).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let storage = std::sync::Arc::new(Storage::open_in_memory().unwrap());
+        let guild = GUILD.unwrap();
+        storage
+            .run(move |storage| storage.set_judge_pipeline(guild, Some("llm")))
+            .await
+            .unwrap();
+        let pipeline = storage
+            .run(move |storage| storage.judge_pipeline(guild))
+            .await
+            .unwrap();
+        assert_eq!(pipeline.as_deref(), Some("llm"));
+        // Errors from the queries come back out
+        let failed: Result<(), _> = storage
+            .run(|_| Err(rusqlite::Error::QueryReturnedNoRows))
+            .await;
+        assert!(failed.is_err());
+    }
+}
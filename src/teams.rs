@@ -0,0 +1,141 @@
+/// Teams for scrimmaging in a channel
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use ::serenity::all::{ChannelId, Mentionable, UserId};
+
+/// Who's on which team, as of when a session starts
+pub type Roster = HashMap<UserId, String>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TeamError {
+    AlreadyExists(String),
+    NotFound(String),
+    EmptyName,
+}
+
+impl fmt::Display for TeamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TeamError::AlreadyExists(name) => write!(f, "There's already a team called {}", name),
+            TeamError::NotFound(name) => write!(f, "There's no team called {}", name),
+            TeamError::EmptyName => write!(f, "Team names can't be empty"),
+        }
+    }
+}
+
+impl std::error::Error for TeamError {}
+
+/// The teams in each channel (in memory)
+#[derive(Debug, Default)]
+pub struct Teams {
+    /// Team name -> members, sorted so listings come out the same every time
+    channels: HashMap<ChannelId, BTreeMap<String, Vec<UserId>>>,
+}
+
+impl Teams {
+    /// Team names are case-insensitive, but keep the case they were created with
+    fn find(&self, channel: ChannelId, name: &str) -> Option<String> {
+        self.channels
+            .get(&channel)?
+            .keys()
+            .find(|team| team.to_lowercase() == name.to_lowercase())
+            .cloned()
+    }
+
+    pub fn create(&mut self, channel: ChannelId, name: &str) -> Result<(), TeamError> {
+        if name.trim().is_empty() {
+            return Err(TeamError::EmptyName);
+        }
+        if let Some(team) = self.find(channel, name) {
+            return Err(TeamError::AlreadyExists(team));
+        }
+        self.channels
+            .entry(channel)
+            .or_default()
+            .insert(name.to_string(), Vec::new());
+        Ok(())
+    }
+
+    /// Join a team (leaving any other team in the channel), returning its name
+    pub fn join(
+        &mut self,
+        channel: ChannelId,
+        user: UserId,
+        name: &str,
+    ) -> Result<String, TeamError> {
+        let team = self
+            .find(channel, name)
+            .ok_or_else(|| TeamError::NotFound(name.to_string()))?;
+        self.leave(channel, user);
+        // `find` succeeded, so both of these exist
+        self.channels
+            .get_mut(&channel)
+            .unwrap()
+            .get_mut(&team)
+            .unwrap()
+            .push(user);
+        Ok(team)
+    }
+
+    /// Leave whatever team the user is on, returning its name
+    ///
+    /// Teams stick around once everyone's left, so people can rejoin them.
+    pub fn leave(&mut self, channel: ChannelId, user: UserId) -> Option<String> {
+        let teams = self.channels.get_mut(&channel)?;
+        teams.iter_mut().find_map(|(team, members)| {
+            let index = members.iter().position(|member| *member == user)?;
+            members.remove(index);
+            Some(team.clone())
+        })
+    }
+
+    /// A team's name (as it was created) and members
+    pub fn members(&self, channel: ChannelId, name: &str) -> Option<(String, Vec<UserId>)> {
+        let team = self.find(channel, name)?;
+        let members = self.channels[&channel][&team].clone();
+        Some((team, members))
+    }
+
+    pub fn team_names(&self, channel: ChannelId) -> Vec<String> {
+        self.channels
+            .get(&channel)
+            .map(|teams| teams.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn roster(&self, channel: ChannelId) -> Roster {
+        self.channels
+            .get(&channel)
+            .into_iter()
+            .flatten()
+            .flat_map(|(team, members)| members.iter().map(|member| (*member, team.clone())))
+            .collect()
+    }
+
+    pub fn render(&self, channel: ChannelId) -> String {
+        let Some(teams) = self
+            .channels
+            .get(&channel)
+            .filter(|teams| !teams.is_empty())
+        else {
+            return "*No teams in this channel yet.*".to_string();
+        };
+        teams
+            .iter()
+            .map(|(team, members)| {
+                let members = if members.is_empty() {
+                    "*nobody*".to_string()
+                } else {
+                    members
+                        .iter()
+                        .map(|member| member.mention().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                format!("• **{}**: {}", team, members)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
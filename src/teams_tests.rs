@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::teams::*;
+    use serenity::all::{ChannelId, UserId};
+
+    const CHANNEL: ChannelId = ChannelId::new(1);
+
+    #[test]
+    fn test_create_and_join() {
+        let mut teams = Teams::default();
+        teams.create(CHANNEL, "Red").unwrap();
+        assert_eq!(
+            teams.create(CHANNEL, "red"),
+            Err(TeamError::AlreadyExists("Red".to_string()))
+        );
+        // Names are case-insensitive
+        assert_eq!(teams.join(CHANNEL, UserId::new(1), "RED").unwrap(), "Red");
+        assert_eq!(
+            teams.join(CHANNEL, UserId::new(2), "Blue"),
+            Err(TeamError::NotFound("Blue".to_string()))
+        );
+        assert_eq!(teams.roster(CHANNEL)[&UserId::new(1)], "Red");
+    }
+
+    #[test]
+    fn test_joining_leaves_old_team() {
+        let mut teams = Teams::default();
+        teams.create(CHANNEL, "Red").unwrap();
+        teams.create(CHANNEL, "Blue").unwrap();
+        teams.join(CHANNEL, UserId::new(1), "Red").unwrap();
+        teams.join(CHANNEL, UserId::new(1), "Blue").unwrap();
+        assert_eq!(teams.roster(CHANNEL).len(), 1);
+        assert_eq!(teams.roster(CHANNEL)[&UserId::new(1)], "Blue");
+        assert_eq!(
+            teams.render(CHANNEL),
+            "• **Blue**: <@1>\n• **Red**: *nobody*"
+        );
+    }
+
+    #[test]
+    fn test_leave() {
+        let mut teams = Teams::default();
+        teams.create(CHANNEL, "Red").unwrap();
+        teams.join(CHANNEL, UserId::new(1), "Red").unwrap();
+        assert_eq!(
+            teams.leave(CHANNEL, UserId::new(1)),
+            Some("Red".to_string())
+        );
+        assert_eq!(teams.leave(CHANNEL, UserId::new(1)), None);
+        assert!(teams.roster(CHANNEL).is_empty());
+        // The team is still there to rejoin
+        assert_eq!(teams.team_names(CHANNEL), vec!["Red"]);
+    }
+
+    #[test]
+    fn test_teams_are_per_channel() {
+        let mut teams = Teams::default();
+        teams.create(CHANNEL, "Red").unwrap();
+        let other = ChannelId::new(2);
+        teams.create(other, "Red").unwrap();
+        teams.join(other, UserId::new(1), "Red").unwrap();
+        assert!(teams.roster(CHANNEL).is_empty());
+        assert_eq!(
+            teams.render(ChannelId::new(3)),
+            "*No teams in this channel yet.*"
+        );
+    }
+
+    #[test]
+    fn test_error_messages() {
+        assert_eq!(
+            TeamError::NotFound("Blue".to_string()).to_string(),
+            "There's no team called Blue"
+        );
+    }
+
+    #[test]
+    fn test_empty_names() {
+        let mut teams = Teams::default();
+        assert_eq!(teams.create(CHANNEL, ""), Err(TeamError::EmptyName));
+        assert_eq!(teams.create(CHANNEL, "   "), Err(TeamError::EmptyName));
+        assert!(teams.team_names(CHANNEL).is_empty());
+    }
+}
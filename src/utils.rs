@@ -40,7 +40,7 @@ pub async fn get_llm(reqwest: &reqwest::Client) -> Box<dyn LLMProvider> {
             .build()
             .expect("Failed to build LLM (Google)")
     } else {
-        if !healthcheck(&reqwest, &ollama_base_url).await {
+        if !healthcheck(reqwest, &ollama_base_url).await {
             panic!("Ollama is not running");
         }
         LLMBuilder::new()
@@ -54,6 +54,7 @@ pub async fn get_llm(reqwest: &reqwest::Client) -> Box<dyn LLMProvider> {
             .expect("Failed to build LLM (Ollama)")
     }
 }
+#[cfg(test)]
 pub fn get_llm_no_healthcheck() -> Box<dyn LLMProvider> {
     let gemini_api_key = std::env::var("GEMINI_API_KEY").ok();
     let ollama_base_url = std::env::var("OLLAMA_URL").unwrap_or("http://127.0.0.1:11434".into());